@@ -0,0 +1,190 @@
+use std::alloc::{self, Layout};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// An allocator for the heap tier of an `ArenaSmallHashMap`.
+///
+/// This is a crate-local stand-in for the unstable `Allocator` trait, so
+/// arena-backed maps build on stable Rust. Maps hold a shared reference to
+/// the allocator, so implementations use interior mutability.
+///
+/// # Safety
+///
+/// Memory returned by `allocate` must be valid for reads and writes of
+/// `layout.size()` bytes, aligned to `layout.align()`, and not handed out
+/// again until it is passed to `deallocate` or the allocator is dropped.
+pub unsafe trait MapAllocator {
+    /// Allocates a block of memory for `layout`, or returns `None` if the
+    /// allocation fails.
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>>;
+
+    /// Releases a block returned by `allocate`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `allocate` on this allocator with the
+    /// same `layout`, and must not be used afterwards.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+}
+
+/// A bump allocator that hands out memory from large chunks and frees it
+/// all at once.
+///
+/// Individual deallocations are no-ops: a table that grows leaves its old
+/// buckets behind until the arena is dropped or [`reset`](Self::reset).
+/// This makes spilling cheap when many short-lived maps are created and
+/// then discarded together.
+///
+/// # Example
+///
+/// ```
+/// use small_hash_map::{ArenaSmallHashMap, BumpArena};
+///
+/// let arena = BumpArena::new();
+/// let mut scopes: Vec<ArenaSmallHashMap<u32, u32, 4>> = Vec::new();
+/// for node in 0..100 {
+///     let mut scope = ArenaSmallHashMap::new_in(&arena);
+///     scope.extend((0..node % 10).map(|i| (i, node)));
+///     scopes.push(scope);
+/// }
+/// assert!(arena.allocated_bytes() > 0);
+///
+/// // Dropping the maps frees nothing; dropping the arena frees everything.
+/// drop(scopes);
+/// drop(arena);
+/// ```
+pub struct BumpArena {
+    chunks: RefCell<Vec<(NonNull<u8>, Layout)>>,
+    /// Next free byte in the last chunk, and the end of that chunk.
+    cursor: Cell<usize>,
+    end: Cell<usize>,
+    chunk_size: usize,
+    allocated: Cell<usize>,
+}
+
+impl BumpArena {
+    /// The default chunk size, in bytes.
+    pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Creates an arena that allocates chunks of `DEFAULT_CHUNK_SIZE` bytes.
+    pub fn new() -> Self {
+        Self::with_chunk_size(Self::DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Creates an arena that allocates chunks of at least `chunk_size`
+    /// bytes. Larger requests get a chunk of their own size.
+    pub fn with_chunk_size(chunk_size: usize) -> Self {
+        Self {
+            chunks: RefCell::new(Vec::new()),
+            cursor: Cell::new(0),
+            end: Cell::new(0),
+            chunk_size: chunk_size.max(1),
+            allocated: Cell::new(0),
+        }
+    }
+
+    /// Returns the number of bytes handed out since creation or the last
+    /// `reset`, including alignment padding.
+    pub fn allocated_bytes(&self) -> usize {
+        self.allocated.get()
+    }
+
+    /// Returns the number of chunks currently held.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.borrow().len()
+    }
+
+    /// Releases all memory at once, keeping the most recent chunk for reuse.
+    ///
+    /// Taking `&mut self` ensures no map still borrows the arena.
+    pub fn reset(&mut self) {
+        let chunks = self.chunks.get_mut();
+        let last = chunks.pop();
+        for (ptr, layout) in chunks.drain(..) {
+            // SAFETY: Each chunk was allocated with its stored layout.
+            unsafe { alloc::dealloc(ptr.as_ptr(), layout) };
+        }
+        if let Some((ptr, layout)) = last {
+            chunks.push((ptr, layout));
+            self.cursor.set(ptr.as_ptr() as usize);
+            self.end.set(ptr.as_ptr() as usize + layout.size());
+        }
+        self.allocated.set(0);
+    }
+
+    /// Allocates a new chunk with room for `layout` and makes it current.
+    fn grow(&self, layout: Layout) -> Option<()> {
+        let size = self.chunk_size.max(layout.size() + layout.align());
+        let chunk_layout = Layout::from_size_align(size, 16).ok()?;
+        // SAFETY: `size` is non-zero.
+        let ptr = NonNull::new(unsafe { alloc::alloc(chunk_layout) })?;
+        self.chunks.borrow_mut().push((ptr, chunk_layout));
+        self.cursor.set(ptr.as_ptr() as usize);
+        self.end.set(ptr.as_ptr() as usize + size);
+        Some(())
+    }
+
+    /// Returns a pointer for `layout` in the current chunk, if it fits.
+    fn bump(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let start = self.cursor.get().checked_add(layout.align() - 1)? & !(layout.align() - 1);
+        let end = start.checked_add(layout.size())?;
+        if self.cursor.get() == 0 || end > self.end.get() {
+            return None;
+        }
+        let padding = start - self.cursor.get();
+        self.cursor.set(end);
+        self.allocated
+            .set(self.allocated.get() + padding + layout.size());
+        let chunk = self.chunks.borrow().last()?.0;
+        // Derive the pointer from the chunk so it keeps the chunk's
+        // provenance.
+        let offset = start - chunk.as_ptr() as usize;
+        // SAFETY: `start..end` lies within the current chunk.
+        Some(unsafe { NonNull::new_unchecked(chunk.as_ptr().add(offset)) })
+    }
+}
+
+impl Default for BumpArena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for BumpArena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BumpArena")
+            .field("chunks", &self.chunk_count())
+            .field("allocated_bytes", &self.allocated_bytes())
+            .finish()
+    }
+}
+
+// SAFETY: Each allocation advances the cursor past the returned block, so
+// blocks never overlap, and chunks are only freed by `reset` (which needs
+// exclusive access) or `Drop`.
+unsafe impl MapAllocator for BumpArena {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        if layout.size() == 0 {
+            return NonNull::new(layout.align() as *mut u8);
+        }
+        match self.bump(layout) {
+            Some(ptr) => Some(ptr),
+            None => {
+                self.grow(layout)?;
+                self.bump(layout)
+            }
+        }
+    }
+
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
+
+impl Drop for BumpArena {
+    fn drop(&mut self) {
+        for (ptr, layout) in self.chunks.get_mut().drain(..) {
+            // SAFETY: Each chunk was allocated with its stored layout.
+            unsafe { alloc::dealloc(ptr.as_ptr(), layout) };
+        }
+    }
+}
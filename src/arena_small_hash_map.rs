@@ -0,0 +1,712 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::iter::Zip;
+use std::mem;
+use std::slice;
+
+use super::arena::{BumpArena, MapAllocator};
+use super::arena_table::{ArenaTable, ArenaTableIntoIter, ArenaTableIter, ArenaTableIterMut};
+use super::equivalence::DefaultEquivalence;
+use super::inline_map::{InlineMap, IntoEntries};
+
+/// A `SmallHashMap` whose heap tier allocates from a caller-supplied
+/// [`MapAllocator`] instead of the global allocator.
+///
+/// The map starts inline like `SmallHashMap` and spills into an
+/// open-addressing table in the allocator once it grows past `N` entries.
+/// With a [`BumpArena`], dropping a spilled map frees nothing; the memory of
+/// every map created in the arena is released together when the arena is
+/// dropped or reset.
+///
+/// Because a bump arena reclaims nothing when a table grows, a map that
+/// spills and then doubles its way up leaves old tables behind totalling
+/// about the size of its final table. When the final size is known, use
+/// [`with_capacity_in`](Self::with_capacity_in) or
+/// [`reserve`](Self::reserve) to allocate the table once.
+///
+/// # Example
+///
+/// ```
+/// use small_hash_map::{ArenaSmallHashMap, BumpArena};
+///
+/// let arena = BumpArena::new();
+/// let mut map: ArenaSmallHashMap<&str, i32, 2> = ArenaSmallHashMap::new_in(&arena);
+/// map.insert("a", 1);
+/// map.insert("b", 2);
+/// assert!(map.is_inline());
+///
+/// map.insert("c", 3);
+/// assert!(!map.is_inline());
+/// assert_eq!(map.get(&"c"), Some(&3));
+/// ```
+pub struct ArenaSmallHashMap<'a, K, V, const N: usize, A = BumpArena, S = RandomState>
+where
+    A: MapAllocator,
+{
+    inner: ArenaMapKind<'a, K, V, N, A>,
+    alloc: &'a A,
+    hash_builder: S,
+}
+
+enum ArenaMapKind<'a, K, V, const N: usize, A: MapAllocator> {
+    InlineMap(InlineMap<K, V, N>),
+    Arena(ArenaTable<'a, K, V, A>),
+}
+
+impl<'a, K, V, const N: usize, A, S> ArenaSmallHashMap<'a, K, V, N, A, S>
+where
+    K: Hash + Eq,
+    A: MapAllocator,
+    S: BuildHasher + Default,
+{
+    /// Creates an empty map whose heap tier allocates from `alloc`.
+    pub fn new_in(alloc: &'a A) -> Self {
+        Self::with_hasher_in(S::default(), alloc)
+    }
+
+    /// Creates a map with room for `capacity` entries, allocating from
+    /// `alloc` right away if `capacity > N`.
+    pub fn with_capacity_in(capacity: usize, alloc: &'a A) -> Self {
+        let mut map = Self::new_in(alloc);
+        if capacity > N {
+            map.inner = ArenaMapKind::Arena(ArenaTable::with_capacity_in(capacity, alloc));
+        }
+        map
+    }
+}
+
+impl<'a, K, V, const N: usize, A, S> ArenaSmallHashMap<'a, K, V, N, A, S>
+where
+    K: Hash + Eq,
+    A: MapAllocator,
+    S: BuildHasher,
+{
+    /// Creates an empty map with the given hasher, whose heap tier allocates
+    /// from `alloc`.
+    pub fn with_hasher_in(hash_builder: S, alloc: &'a A) -> Self {
+        Self {
+            inner: ArenaMapKind::InlineMap(InlineMap::new()),
+            alloc,
+            hash_builder,
+        }
+    }
+
+    /// Returns the allocator used by the heap tier.
+    pub fn allocator(&self) -> &'a A {
+        self.alloc
+    }
+
+    /// Returns a reference to the map's hasher.
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    /// Returns the number of elements in the map.
+    pub fn len(&self) -> usize {
+        match &self.inner {
+            ArenaMapKind::InlineMap(map) => map.len(),
+            ArenaMapKind::Arena(table) => table.len(),
+        }
+    }
+
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the map is still using inline storage.
+    pub fn is_inline(&self) -> bool {
+        matches!(self.inner, ArenaMapKind::InlineMap(_))
+    }
+
+    /// Returns the number of elements the map can hold without growing.
+    pub fn capacity(&self) -> usize {
+        match &self.inner {
+            ArenaMapKind::InlineMap(map) => map.capacity(),
+            ArenaMapKind::Arena(table) => table.capacity(),
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        match &self.inner {
+            ArenaMapKind::InlineMap(map) => map.get(key),
+            ArenaMapKind::Arena(table) => {
+                let index = table.find(self.hash_builder.hash_one(key), key)?;
+                Some(table.entry_at(index).1)
+            }
+        }
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        match &mut self.inner {
+            ArenaMapKind::InlineMap(map) => map.get_mut(key),
+            ArenaMapKind::Arena(table) => {
+                let index = table.find(self.hash_builder.hash_one(key), key)?;
+                Some(table.entry_at_mut(index).1)
+            }
+        }
+    }
+
+    /// Returns `true` if the map contains a value for the key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Gets the entry for `key`, for in-place insertion or update.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::{ArenaSmallHashMap, BumpArena};
+    ///
+    /// let arena = BumpArena::new();
+    /// let mut counts: ArenaSmallHashMap<char, u32, 2> = ArenaSmallHashMap::new_in(&arena);
+    /// for c in "hello".chars() {
+    ///     *counts.entry(c).or_insert(0) += 1;
+    /// }
+    /// assert_eq!(counts.get(&'l'), Some(&2));
+    /// assert!(!counts.is_inline());
+    /// ```
+    pub fn entry(&mut self, key: K) -> ArenaEntry<'_, 'a, K, V, N, A, S> {
+        let (index, hash) = match &self.inner {
+            ArenaMapKind::InlineMap(map) => (map.find_key_index(&key), None),
+            ArenaMapKind::Arena(table) => {
+                let hash = self.hash_builder.hash_one(&key);
+                (table.find(hash, &key), Some(hash))
+            }
+        };
+        match index {
+            Some(index) => ArenaEntry::Occupied(ArenaOccupiedEntry { map: self, index }),
+            None => ArenaEntry::Vacant(ArenaVacantEntry {
+                map: self,
+                key,
+                hash,
+            }),
+        }
+    }
+
+    /// Inserts a key-value pair into the map, returning the old value if the
+    /// key was present.
+    ///
+    /// If inserting would exceed the inline capacity, the entries move into a
+    /// table allocated from the map's allocator.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            ArenaEntry::Occupied(mut entry) => Some(entry.insert(value)),
+            ArenaEntry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    /// Reserves room for at least `additional` more entries.
+    ///
+    /// Moves the map into the allocator if the entries no longer fit inline.
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self
+            .len()
+            .checked_add(additional)
+            .expect("capacity overflow");
+        match &mut self.inner {
+            ArenaMapKind::InlineMap(_) if needed <= N => {}
+            ArenaMapKind::InlineMap(_) => {
+                self.transition_to_arena(needed);
+            }
+            ArenaMapKind::Arena(table) => table.reserve(additional),
+        }
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        match &mut self.inner {
+            ArenaMapKind::InlineMap(map) => map.remove(key),
+            ArenaMapKind::Arena(table) => {
+                let index = table.find(self.hash_builder.hash_one(key), key)?;
+                Some(table.remove_at(index).1)
+            }
+        }
+    }
+
+    /// Removes a key from the map, returning the stored key and value if the
+    /// key was present.
+    pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
+        match &mut self.inner {
+            ArenaMapKind::InlineMap(map) => map.remove_entry(key),
+            ArenaMapKind::Arena(table) => {
+                let index = table.find(self.hash_builder.hash_one(key), key)?;
+                Some(table.remove_at(index))
+            }
+        }
+    }
+
+    /// Keeps only the entries for which `f` returns `true`.
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        match &mut self.inner {
+            ArenaMapKind::InlineMap(map) => map.retain(f),
+            ArenaMapKind::Arena(table) => table.retain(f),
+        }
+    }
+
+    /// Removes all entries. A spilled map keeps its table.
+    pub fn clear(&mut self) {
+        match &mut self.inner {
+            ArenaMapKind::InlineMap(map) => map.clear(),
+            ArenaMapKind::Arena(table) => table.clear(),
+        }
+    }
+
+    /// Returns an iterator over the entries, in arbitrary order.
+    pub fn iter(&self) -> ArenaSmallHashMapIter<'_, K, V> {
+        let inner = match &self.inner {
+            ArenaMapKind::InlineMap(map) => ArenaIterKind::InlineMap(map.iter()),
+            ArenaMapKind::Arena(table) => ArenaIterKind::Arena(table.iter()),
+        };
+        ArenaSmallHashMapIter { inner }
+    }
+
+    /// Returns an iterator over the entries with mutable values, in
+    /// arbitrary order.
+    pub fn iter_mut(&mut self) -> ArenaSmallHashMapIterMut<'_, K, V> {
+        let inner = match &mut self.inner {
+            ArenaMapKind::InlineMap(map) => ArenaIterMutKind::InlineMap(map.iter_mut()),
+            ArenaMapKind::Arena(table) => ArenaIterMutKind::Arena(table.iter_mut()),
+        };
+        ArenaSmallHashMapIterMut { inner }
+    }
+
+    /// Moves the inline entries into a new table with room for `capacity`
+    /// entries, and returns the table.
+    ///
+    /// Every key is hashed before anything moves, so a panicking hasher
+    /// leaves the map inline and intact.
+    fn transition_to_arena(&mut self, capacity: usize) -> &mut ArenaTable<'a, K, V, A> {
+        if let ArenaMapKind::InlineMap(map) = &self.inner {
+            let hashes: [u64; N] = std::array::from_fn(|i| {
+                map.get_index(i)
+                    .map_or(0, |(key, _)| self.hash_builder.hash_one(key))
+            });
+            let mut table = ArenaTable::with_capacity_in(capacity, self.alloc);
+            let inner = mem::replace(&mut self.inner, ArenaMapKind::InlineMap(InlineMap::new()));
+            if let ArenaMapKind::InlineMap(map) = inner {
+                for ((key, value), hash) in map.into_entries().zip(hashes) {
+                    table.insert_unique(hash, key, value);
+                }
+            }
+            self.inner = ArenaMapKind::Arena(table);
+        }
+        match &mut self.inner {
+            ArenaMapKind::Arena(table) => table,
+            ArenaMapKind::InlineMap(_) => unreachable!(),
+        }
+    }
+
+    /// Returns the entry at `index`, which must come from a lookup.
+    fn entry_at(&self, index: usize) -> (&K, &V) {
+        match &self.inner {
+            ArenaMapKind::InlineMap(map) => {
+                map.get_index(index).expect("entry index out of bounds")
+            }
+            ArenaMapKind::Arena(table) => table.entry_at(index),
+        }
+    }
+
+    /// Returns the entry at `index`, which must come from a lookup.
+    fn entry_at_mut(&mut self, index: usize) -> (&K, &mut V) {
+        match &mut self.inner {
+            ArenaMapKind::InlineMap(map) => {
+                map.get_index_mut(index).expect("entry index out of bounds")
+            }
+            ArenaMapKind::Arena(table) => table.entry_at_mut(index),
+        }
+    }
+
+    /// Removes the entry at `index`, which must come from a lookup.
+    fn remove_at(&mut self, index: usize) -> (K, V) {
+        match &mut self.inner {
+            ArenaMapKind::InlineMap(map) => map.remove_index(index),
+            ArenaMapKind::Arena(table) => table.remove_at(index),
+        }
+    }
+}
+
+impl<K, V, const N: usize, A, S> Extend<(K, V)> for ArenaSmallHashMap<'_, K, V, N, A, S>
+where
+    K: Hash + Eq,
+    A: MapAllocator,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<'m, K, V, const N: usize, A, S> IntoIterator for &'m mut ArenaSmallHashMap<'_, K, V, N, A, S>
+where
+    K: Hash + Eq,
+    A: MapAllocator,
+    S: BuildHasher,
+{
+    type Item = (&'m K, &'m mut V);
+    type IntoIter = ArenaSmallHashMapIterMut<'m, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<'a, K, V, const N: usize, A, S> IntoIterator for ArenaSmallHashMap<'a, K, V, N, A, S>
+where
+    A: MapAllocator,
+{
+    type Item = (K, V);
+    type IntoIter = ArenaSmallHashMapIntoIter<'a, K, V, N, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let inner = match self.inner {
+            ArenaMapKind::InlineMap(map) => ArenaIntoIterKind::InlineMap(map.into_entries()),
+            ArenaMapKind::Arena(table) => ArenaIntoIterKind::Arena(table.into_entries()),
+        };
+        ArenaSmallHashMapIntoIter { inner }
+    }
+}
+
+impl<'a, K, V, const N: usize, A, S> Clone for ArenaSmallHashMap<'a, K, V, N, A, S>
+where
+    K: Clone,
+    V: Clone,
+    A: MapAllocator,
+    S: Clone,
+{
+    /// Clones the map; a spilled map's copy allocates from the same
+    /// allocator.
+    fn clone(&self) -> Self {
+        let inner = match &self.inner {
+            ArenaMapKind::InlineMap(map) => ArenaMapKind::InlineMap(map.clone()),
+            ArenaMapKind::Arena(table) => ArenaMapKind::Arena(table.clone()),
+        };
+        Self {
+            inner,
+            alloc: self.alloc,
+            hash_builder: self.hash_builder.clone(),
+        }
+    }
+}
+
+impl<K, V, const N: usize, const M: usize, A, B, S, T>
+    PartialEq<ArenaSmallHashMap<'_, K, V, M, B, T>> for ArenaSmallHashMap<'_, K, V, N, A, S>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    A: MapAllocator,
+    B: MapAllocator,
+    S: BuildHasher,
+    T: BuildHasher,
+{
+    /// Two maps are equal if they contain the same key-value pairs,
+    /// regardless of storage mode, capacity parameter, allocator or hasher.
+    fn eq(&self, other: &ArenaSmallHashMap<'_, K, V, M, B, T>) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K, V, const N: usize, A, S> Eq for ArenaSmallHashMap<'_, K, V, N, A, S>
+where
+    K: Hash + Eq,
+    V: Eq,
+    A: MapAllocator,
+    S: BuildHasher,
+{
+}
+
+impl<'m, K, V, const N: usize, A, S> IntoIterator for &'m ArenaSmallHashMap<'_, K, V, N, A, S>
+where
+    K: Hash + Eq,
+    A: MapAllocator,
+    S: BuildHasher,
+{
+    type Item = (&'m K, &'m V);
+    type IntoIter = ArenaSmallHashMapIter<'m, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K, V, const N: usize, A, S> fmt::Debug for ArenaSmallHashMap<'_, K, V, N, A, S>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    A: MapAllocator,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Iterator over the entries of an `ArenaSmallHashMap`.
+///
+/// Created by `ArenaSmallHashMap::iter`.
+pub struct ArenaSmallHashMapIter<'m, K, V> {
+    inner: ArenaIterKind<'m, K, V>,
+}
+
+enum ArenaIterKind<'m, K, V> {
+    InlineMap(Zip<slice::Iter<'m, K>, slice::Iter<'m, V>>),
+    Arena(ArenaTableIter<'m, K, V>),
+}
+
+impl<'m, K, V> Iterator for ArenaSmallHashMapIter<'m, K, V> {
+    type Item = (&'m K, &'m V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            ArenaIterKind::InlineMap(iter) => iter.next(),
+            ArenaIterKind::Arena(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            ArenaIterKind::InlineMap(iter) => iter.size_hint(),
+            ArenaIterKind::Arena(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<K, V> ExactSizeIterator for ArenaSmallHashMapIter<'_, K, V> {}
+
+/// Mutable iterator over the entries of an `ArenaSmallHashMap`.
+///
+/// Created by `ArenaSmallHashMap::iter_mut`.
+pub struct ArenaSmallHashMapIterMut<'m, K, V> {
+    inner: ArenaIterMutKind<'m, K, V>,
+}
+
+enum ArenaIterMutKind<'m, K, V> {
+    InlineMap(Zip<slice::Iter<'m, K>, slice::IterMut<'m, V>>),
+    Arena(ArenaTableIterMut<'m, K, V>),
+}
+
+impl<'m, K, V> Iterator for ArenaSmallHashMapIterMut<'m, K, V> {
+    type Item = (&'m K, &'m mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            ArenaIterMutKind::InlineMap(iter) => iter.next(),
+            ArenaIterMutKind::Arena(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            ArenaIterMutKind::InlineMap(iter) => iter.size_hint(),
+            ArenaIterMutKind::Arena(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<K, V> ExactSizeIterator for ArenaSmallHashMapIterMut<'_, K, V> {}
+
+/// Consuming iterator over the entries of an `ArenaSmallHashMap`.
+///
+/// Created by `ArenaSmallHashMap::into_iter`.
+pub struct ArenaSmallHashMapIntoIter<'a, K, V, const N: usize, A: MapAllocator = BumpArena> {
+    inner: ArenaIntoIterKind<'a, K, V, N, A>,
+}
+
+enum ArenaIntoIterKind<'a, K, V, const N: usize, A: MapAllocator> {
+    InlineMap(IntoEntries<K, V, N, DefaultEquivalence>),
+    Arena(ArenaTableIntoIter<'a, K, V, A>),
+}
+
+impl<K, V, const N: usize, A: MapAllocator> Iterator for ArenaSmallHashMapIntoIter<'_, K, V, N, A> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            ArenaIntoIterKind::InlineMap(iter) => iter.next(),
+            ArenaIntoIterKind::Arena(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            ArenaIntoIterKind::InlineMap(iter) => iter.size_hint(),
+            ArenaIntoIterKind::Arena(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<K, V, const N: usize, A: MapAllocator> ExactSizeIterator
+    for ArenaSmallHashMapIntoIter<'_, K, V, N, A>
+{
+}
+
+/// A view into a single entry of an `ArenaSmallHashMap`.
+///
+/// Created by `ArenaSmallHashMap::entry`.
+pub enum ArenaEntry<'m, 'a, K, V, const N: usize, A: MapAllocator, S> {
+    /// The key is in the map.
+    Occupied(ArenaOccupiedEntry<'m, 'a, K, V, N, A, S>),
+    /// The key is not in the map.
+    Vacant(ArenaVacantEntry<'m, 'a, K, V, N, A, S>),
+}
+
+/// An entry of an `ArenaSmallHashMap` whose key is present.
+pub struct ArenaOccupiedEntry<'m, 'a, K, V, const N: usize, A: MapAllocator, S> {
+    map: &'m mut ArenaSmallHashMap<'a, K, V, N, A, S>,
+    index: usize,
+}
+
+/// An entry of an `ArenaSmallHashMap` whose key is absent.
+pub struct ArenaVacantEntry<'m, 'a, K, V, const N: usize, A: MapAllocator, S> {
+    map: &'m mut ArenaSmallHashMap<'a, K, V, N, A, S>,
+    key: K,
+    /// The key's hash, if the lookup already computed it.
+    hash: Option<u64>,
+}
+
+impl<'m, K, V, const N: usize, A, S> ArenaEntry<'m, '_, K, V, N, A, S>
+where
+    K: Hash + Eq,
+    A: MapAllocator,
+    S: BuildHasher,
+{
+    /// Returns the entry's key.
+    pub fn key(&self) -> &K {
+        match self {
+            ArenaEntry::Occupied(entry) => entry.key(),
+            ArenaEntry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Inserts `default` if the key is absent, and returns a mutable
+    /// reference to the value.
+    pub fn or_insert(self, default: V) -> &'m mut V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Inserts the result of `default` if the key is absent, and returns a
+    /// mutable reference to the value.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'m mut V {
+        match self {
+            ArenaEntry::Occupied(entry) => entry.into_mut(),
+            ArenaEntry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Inserts `V::default()` if the key is absent, and returns a mutable
+    /// reference to the value.
+    pub fn or_default(self) -> &'m mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Calls `f` on the value if the key is present.
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let ArenaEntry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'m, K, V, const N: usize, A, S> ArenaOccupiedEntry<'m, '_, K, V, N, A, S>
+where
+    K: Hash + Eq,
+    A: MapAllocator,
+    S: BuildHasher,
+{
+    /// Returns the stored key.
+    pub fn key(&self) -> &K {
+        self.map.entry_at(self.index).0
+    }
+
+    /// Returns a reference to the value.
+    pub fn get(&self) -> &V {
+        self.map.entry_at(self.index).1
+    }
+
+    /// Returns a mutable reference to the value.
+    pub fn get_mut(&mut self) -> &mut V {
+        self.map.entry_at_mut(self.index).1
+    }
+
+    /// Converts the entry into a mutable reference to the value, with the
+    /// lifetime of the map borrow.
+    pub fn into_mut(self) -> &'m mut V {
+        self.map.entry_at_mut(self.index).1
+    }
+
+    /// Replaces the value, returning the old one. The stored key is kept.
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes the entry, returning the stored key and the value.
+    pub fn remove_entry(self) -> (K, V) {
+        self.map.remove_at(self.index)
+    }
+
+    /// Removes the entry, returning the value.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+}
+
+impl<'m, K, V, const N: usize, A, S> ArenaVacantEntry<'m, '_, K, V, N, A, S>
+where
+    K: Hash + Eq,
+    A: MapAllocator,
+    S: BuildHasher,
+{
+    /// Returns the key that would be inserted.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Takes back the key without inserting.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Inserts `value` under the entry's key, and returns a mutable reference
+    /// to it.
+    ///
+    /// If the inline storage is full, the entries first move into a table
+    /// allocated from the map's allocator.
+    pub fn insert(self, value: V) -> &'m mut V {
+        let map = self.map;
+        let hash = match (&map.inner, self.hash) {
+            (ArenaMapKind::InlineMap(inline), _) if inline.len() < N => None,
+            (_, Some(hash)) => Some(hash),
+            (_, None) => Some(map.hash_builder.hash_one(&self.key)),
+        };
+        let Some(hash) = hash else {
+            match &mut map.inner {
+                ArenaMapKind::InlineMap(inline) => {
+                    return inline.insert_unique_unchecked(self.key, value)
+                }
+                ArenaMapKind::Arena(_) => unreachable!(),
+            }
+        };
+        let table = map.transition_to_arena(N * 2);
+        let index = table.insert_unique(hash, self.key, value);
+        table.entry_at_mut(index).1
+    }
+}
@@ -0,0 +1,439 @@
+use std::alloc::Layout;
+use std::marker::PhantomData;
+use std::mem::{self, MaybeUninit};
+use std::ptr::NonNull;
+use std::slice;
+
+use super::arena::MapAllocator;
+
+/// Marks a bucket as occupied; empty buckets store a hash of 0.
+const OCCUPIED: u64 = 1 << 63;
+
+/// A bucket of an `ArenaTable`. The key and value are initialized exactly
+/// when `hash != 0`.
+struct Bucket<K, V> {
+    hash: u64,
+    key: MaybeUninit<K>,
+    value: MaybeUninit<V>,
+}
+
+/// An open-addressing hash table whose buckets come from a `MapAllocator`.
+///
+/// Used as the heap tier of `ArenaSmallHashMap`. Collisions are resolved
+/// with linear probing, and removals shift later entries back instead of
+/// leaving tombstones. Hashing is left to the caller: every operation takes
+/// the key's hash, which is stored so the table can grow without rehashing.
+pub(crate) struct ArenaTable<'a, K, V, A: MapAllocator> {
+    buckets: NonNull<Bucket<K, V>>,
+    /// Number of buckets; zero or a power of two.
+    bucket_count: usize,
+    len: usize,
+    alloc: &'a A,
+    /// The table owns its entries, for drop checking.
+    entries: PhantomData<Bucket<K, V>>,
+}
+
+impl<'a, K, V, A: MapAllocator> ArenaTable<'a, K, V, A> {
+    /// Creates a table with room for at least `capacity` entries.
+    pub(crate) fn with_capacity_in(capacity: usize, alloc: &'a A) -> Self {
+        let mut table = Self {
+            buckets: NonNull::dangling(),
+            bucket_count: 0,
+            len: 0,
+            alloc,
+            entries: PhantomData,
+        };
+        table.reserve(capacity);
+        table
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of entries the table holds before growing.
+    pub(crate) fn capacity(&self) -> usize {
+        self.bucket_count / 4 * 3
+    }
+
+    fn buckets(&self) -> &[Bucket<K, V>] {
+        // SAFETY: `buckets` points to `bucket_count` buckets whose hashes are
+        // always initialized; keys and values are MaybeUninit.
+        unsafe { slice::from_raw_parts(self.buckets.as_ptr(), self.bucket_count) }
+    }
+
+    fn buckets_mut(&mut self) -> &mut [Bucket<K, V>] {
+        // SAFETY: As in `buckets`, and `&mut self` gives exclusive access.
+        unsafe { slice::from_raw_parts_mut(self.buckets.as_ptr(), self.bucket_count) }
+    }
+
+    /// Returns the index of the entry for `key`, if any.
+    pub(crate) fn find(&self, hash: u64, key: &K) -> Option<usize>
+    where
+        K: Eq,
+    {
+        if self.len == 0 {
+            return None;
+        }
+        let hash = hash | OCCUPIED;
+        let mask = self.bucket_count - 1;
+        let mut index = hash as usize & mask;
+        loop {
+            let bucket = &self.buckets()[index];
+            if bucket.hash == 0 {
+                return None;
+            }
+            // SAFETY: The bucket is occupied, so its key is initialized.
+            if bucket.hash == hash && unsafe { bucket.key.assume_init_ref() } == key {
+                return Some(index);
+            }
+            index = (index + 1) & mask;
+        }
+    }
+
+    /// Returns the entry at `index`, which must come from `find`.
+    pub(crate) fn entry_at(&self, index: usize) -> (&K, &V) {
+        let bucket = &self.buckets()[index];
+        debug_assert!(bucket.hash != 0);
+        // SAFETY: `find` only returns occupied buckets.
+        unsafe { (bucket.key.assume_init_ref(), bucket.value.assume_init_ref()) }
+    }
+
+    /// Returns the entry at `index`, which must come from `find`.
+    pub(crate) fn entry_at_mut(&mut self, index: usize) -> (&K, &mut V) {
+        let bucket = &mut self.buckets_mut()[index];
+        debug_assert!(bucket.hash != 0);
+        // SAFETY: `find` only returns occupied buckets.
+        unsafe { (bucket.key.assume_init_ref(), bucket.value.assume_init_mut()) }
+    }
+
+    /// Inserts an entry whose key is known not to be in the table, and
+    /// returns its index.
+    pub(crate) fn insert_unique(&mut self, hash: u64, key: K, value: V) -> usize {
+        self.reserve(1);
+        let hash = hash | OCCUPIED;
+        let mask = self.bucket_count - 1;
+        let mut index = hash as usize & mask;
+        while self.buckets()[index].hash != 0 {
+            index = (index + 1) & mask;
+        }
+        let bucket = &mut self.buckets_mut()[index];
+        bucket.key.write(key);
+        bucket.value.write(value);
+        bucket.hash = hash;
+        self.len += 1;
+        index
+    }
+
+    /// Removes the entry at `index`, which must come from `find`.
+    pub(crate) fn remove_at(&mut self, index: usize) -> (K, V) {
+        let mask = self.bucket_count - 1;
+        let buckets = self.buckets_mut();
+        debug_assert!(buckets[index].hash != 0);
+        // SAFETY: The bucket is occupied; marking it empty below hands
+        // ownership of the key and value to the caller.
+        let entry = unsafe {
+            (
+                buckets[index].key.assume_init_read(),
+                buckets[index].value.assume_init_read(),
+            )
+        };
+        buckets[index].hash = 0;
+
+        // Shift back later entries in the same probe run, so lookups never
+        // stop early at the hole.
+        let mut hole = index;
+        let mut next = (index + 1) & mask;
+        while buckets[next].hash != 0 {
+            let home = buckets[next].hash as usize & mask;
+            // Move the entry if the hole lies between its home and its
+            // current position, going around the end of the table.
+            if (next.wrapping_sub(home) & mask) >= (next.wrapping_sub(hole) & mask) {
+                let (moved, rest) = if hole < next {
+                    let (left, right) = buckets.split_at_mut(next);
+                    (&mut right[0], &mut left[hole])
+                } else {
+                    let (left, right) = buckets.split_at_mut(hole);
+                    (&mut left[next], &mut right[0])
+                };
+                mem::swap(moved, rest);
+                hole = next;
+            }
+            next = (next + 1) & mask;
+        }
+        self.len -= 1;
+        entry
+    }
+
+    /// Keeps only the entries for which `f` returns `true`.
+    pub(crate) fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        if self.len == 0 {
+            return;
+        }
+        let mask = self.bucket_count - 1;
+        // Start after an empty bucket. Removals only shift later entries of
+        // the same probe run back, and no run crosses an empty bucket, so
+        // every entry is visited exactly once.
+        let start = self
+            .buckets()
+            .iter()
+            .position(|bucket| bucket.hash == 0)
+            .expect("an ArenaTable always has an empty bucket");
+        let mut offset = 1;
+        while offset < self.bucket_count {
+            let index = (start + offset) & mask;
+            if self.buckets()[index].hash != 0 {
+                let (key, value) = self.entry_at_mut(index);
+                if !f(key, value) {
+                    // Check the same index again: an entry may have moved in.
+                    self.remove_at(index);
+                    continue;
+                }
+            }
+            offset += 1;
+        }
+    }
+
+    /// Makes room for `additional` more entries, moving the entries into a
+    /// larger allocation if needed.
+    ///
+    /// The old buckets are handed back to the allocator, which for a
+    /// `BumpArena` reclaims nothing. Growing by doubling therefore leaves
+    /// behind about as much memory as the final table uses.
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        let needed = self.len.checked_add(additional).expect("capacity overflow");
+        if needed <= self.capacity() && self.bucket_count > 0 {
+            return;
+        }
+        let mut bucket_count = self.bucket_count.max(8);
+        while bucket_count / 4 * 3 < needed {
+            bucket_count = bucket_count.checked_mul(2).expect("capacity overflow");
+        }
+        let new_buckets = Self::allocate(self.alloc, bucket_count);
+        let old_buckets = mem::replace(&mut self.buckets, new_buckets);
+        let old_count = mem::replace(&mut self.bucket_count, bucket_count);
+        self.len = 0;
+        for i in 0..old_count {
+            // SAFETY: `old_buckets` holds `old_count` initialized hashes; each
+            // occupied entry is moved out exactly once.
+            unsafe {
+                let bucket = old_buckets.as_ptr().add(i).read();
+                if bucket.hash != 0 {
+                    self.insert_unique(
+                        bucket.hash,
+                        bucket.key.assume_init(),
+                        bucket.value.assume_init(),
+                    );
+                }
+            }
+        }
+        if old_count > 0 {
+            // SAFETY: The old buckets were allocated with this layout.
+            unsafe {
+                self.alloc
+                    .deallocate(old_buckets.cast(), Self::layout(old_count))
+            };
+        }
+    }
+
+    fn layout(bucket_count: usize) -> Layout {
+        Layout::array::<Bucket<K, V>>(bucket_count).expect("capacity overflow")
+    }
+
+    /// Allocates `bucket_count` empty buckets.
+    fn allocate(alloc: &A, bucket_count: usize) -> NonNull<Bucket<K, V>> {
+        let layout = Self::layout(bucket_count);
+        let ptr = alloc
+            .allocate(layout)
+            .unwrap_or_else(|| std::alloc::handle_alloc_error(layout))
+            .cast::<Bucket<K, V>>();
+        for i in 0..bucket_count {
+            // SAFETY: The allocation has room for `bucket_count` buckets.
+            unsafe { std::ptr::addr_of_mut!((*ptr.as_ptr().add(i)).hash).write(0) };
+        }
+        ptr
+    }
+
+    /// Drops every entry, keeping the allocation.
+    ///
+    /// If a destructor panics, the entries not yet dropped are leaked and
+    /// the table is still left empty.
+    pub(crate) fn clear(&mut self) {
+        /// Marks every bucket empty when dropped, including on unwind.
+        struct EmptyOnDrop<'t, K, V>(&'t mut [Bucket<K, V>]);
+
+        impl<K, V> Drop for EmptyOnDrop<'_, K, V> {
+            fn drop(&mut self) {
+                for bucket in self.0.iter_mut() {
+                    bucket.hash = 0;
+                }
+            }
+        }
+
+        if mem::replace(&mut self.len, 0) == 0 {
+            return;
+        }
+        let guard = EmptyOnDrop(self.buckets_mut());
+        for bucket in guard.0.iter_mut() {
+            if bucket.hash != 0 {
+                bucket.hash = 0;
+                // SAFETY: The bucket was occupied and is now marked empty, so
+                // its entry is dropped exactly once.
+                unsafe {
+                    bucket.key.assume_init_drop();
+                    bucket.value.assume_init_drop();
+                }
+            }
+        }
+    }
+
+    /// Returns an iterator over the entries, in table order.
+    pub(crate) fn iter(&self) -> ArenaTableIter<'_, K, V> {
+        ArenaTableIter {
+            buckets: self.buckets().iter(),
+            remaining: self.len,
+        }
+    }
+
+    /// Returns an iterator over the entries with mutable values, in table
+    /// order.
+    pub(crate) fn iter_mut(&mut self) -> ArenaTableIterMut<'_, K, V> {
+        let remaining = self.len;
+        ArenaTableIterMut {
+            buckets: self.buckets_mut().iter_mut(),
+            remaining,
+        }
+    }
+
+    /// Returns an iterator that moves the entries out, in table order.
+    pub(crate) fn into_entries(self) -> ArenaTableIntoIter<'a, K, V, A> {
+        ArenaTableIntoIter {
+            table: self,
+            next: 0,
+        }
+    }
+}
+
+impl<K: Clone, V: Clone, A: MapAllocator> Clone for ArenaTable<'_, K, V, A> {
+    /// Clones the entries into a new table of the same size, from the same
+    /// allocator. Stored hashes are reused, so no key is rehashed.
+    fn clone(&self) -> Self {
+        let mut table = Self::with_capacity_in(self.capacity(), self.alloc);
+        for bucket in self.buckets() {
+            if bucket.hash != 0 {
+                // SAFETY: The bucket is occupied.
+                let (key, value) =
+                    unsafe { (bucket.key.assume_init_ref(), bucket.value.assume_init_ref()) };
+                table.insert_unique(bucket.hash, key.clone(), value.clone());
+            }
+        }
+        table
+    }
+}
+
+impl<K, V, A: MapAllocator> Drop for ArenaTable<'_, K, V, A> {
+    fn drop(&mut self) {
+        if mem::needs_drop::<(K, V)>() {
+            self.clear();
+        }
+        if self.bucket_count > 0 {
+            // SAFETY: The buckets were allocated with this layout.
+            unsafe {
+                self.alloc
+                    .deallocate(self.buckets.cast(), Self::layout(self.bucket_count))
+            };
+        }
+    }
+}
+
+/// Iterator over the entries of an `ArenaTable`.
+pub(crate) struct ArenaTableIter<'t, K, V> {
+    buckets: slice::Iter<'t, Bucket<K, V>>,
+    remaining: usize,
+}
+
+impl<'t, K, V> Iterator for ArenaTableIter<'t, K, V> {
+    type Item = (&'t K, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let bucket = self.buckets.find(|bucket| bucket.hash != 0)?;
+        self.remaining -= 1;
+        // SAFETY: The bucket is occupied.
+        Some(unsafe { (bucket.key.assume_init_ref(), bucket.value.assume_init_ref()) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// Iterator over the entries of an `ArenaTable`, with mutable values.
+pub(crate) struct ArenaTableIterMut<'t, K, V> {
+    buckets: slice::IterMut<'t, Bucket<K, V>>,
+    remaining: usize,
+}
+
+impl<'t, K, V> Iterator for ArenaTableIterMut<'t, K, V> {
+    type Item = (&'t K, &'t mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let bucket = self.buckets.find(|bucket| bucket.hash != 0)?;
+        self.remaining -= 1;
+        // SAFETY: The bucket is occupied.
+        Some(unsafe { (bucket.key.assume_init_ref(), bucket.value.assume_init_mut()) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// Owning iterator over the entries of an `ArenaTable`.
+///
+/// Entries not yet yielded are dropped with the table.
+pub(crate) struct ArenaTableIntoIter<'a, K, V, A: MapAllocator> {
+    table: ArenaTable<'a, K, V, A>,
+    next: usize,
+}
+
+impl<K, V, A: MapAllocator> Iterator for ArenaTableIntoIter<'_, K, V, A> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.table.len == 0 {
+            return None;
+        }
+        let buckets = self.table.buckets_mut();
+        while buckets[self.next].hash == 0 {
+            self.next += 1;
+        }
+        let bucket = &mut buckets[self.next];
+        // The table is never probed again, so emptying the bucket without
+        // shifting its run back is fine.
+        bucket.hash = 0;
+        // SAFETY: The bucket was occupied and is now marked empty, so its
+        // entry is moved out exactly once.
+        let entry = unsafe {
+            (
+                bucket.key.assume_init_read(),
+                bucket.value.assume_init_read(),
+            )
+        };
+        self.next += 1;
+        self.table.len -= 1;
+        Some(entry)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.table.len, Some(self.table.len))
+    }
+}
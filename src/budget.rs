@@ -0,0 +1,81 @@
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+
+/// Returns the largest inline capacity `N` for which
+/// `InlineMap<K, V, N>` takes at most `budget` bytes.
+///
+/// The inline storage holds `N` keys, `N` values and a `usize` length,
+/// rounded up to the largest alignment among them. Returns 0 if not even
+/// an empty map fits, and `usize::MAX` if `K` and `V` are both zero-sized.
+///
+/// Const generic arguments can't depend on type parameters on stable Rust,
+/// so this is used with concrete types in a const block.
+///
+/// # Example
+///
+/// ```
+/// use small_hash_map::{inline_capacity, InlineMap, SmallHashMap};
+///
+/// const CAP: usize = inline_capacity::<u32, u64>(128);
+/// let map: SmallHashMap<u32, u64, CAP> = SmallHashMap::new();
+/// assert_eq!(map.capacity(), 10);
+/// assert!(std::mem::size_of::<InlineMap<u32, u64, CAP>>() <= 128);
+/// ```
+pub const fn inline_capacity<K, V>(budget: usize) -> usize {
+    let entry = size_of::<K>() + size_of::<V>();
+    let mut align = align_of::<usize>();
+    if align_of::<K>() > align {
+        align = align_of::<K>();
+    }
+    if align_of::<V>() > align {
+        align = align_of::<V>();
+    }
+    // The total is rounded up to `align`, so only whole multiples of it
+    // below the budget are usable.
+    let usable = budget / align * align;
+    if usable < size_of::<usize>() {
+        return 0;
+    }
+    if entry == 0 {
+        return usize::MAX;
+    }
+    (usable - size_of::<usize>()) / entry
+}
+
+/// The inline capacity that fits `K` and `V` into `BYTES` bytes.
+///
+/// A type-level form of [`inline_capacity`], for when the budget is a
+/// const parameter of the surrounding code.
+///
+/// # Example
+///
+/// ```
+/// use small_hash_map::{InlineBudget, SmallHashMap};
+///
+/// type Budget = InlineBudget<u16, u16, 64>;
+///
+/// let map: SmallHashMap<u16, u16, { Budget::CAPACITY }> = SmallHashMap::new();
+/// assert_eq!(map.capacity(), 14);
+/// ```
+pub struct InlineBudget<K, V, const BYTES: usize> {
+    entry: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V, const BYTES: usize> InlineBudget<K, V, BYTES> {
+    /// The largest `N` for which `InlineMap<K, V, N>` fits in `BYTES`.
+    pub const CAPACITY: usize = inline_capacity::<K, V>(BYTES);
+}
+
+/// Compile-time check that a `T` takes at most `BYTES` bytes, used by the
+/// `new_within_budget` constructors.
+pub(crate) struct StackBudget<T, const BYTES: usize> {
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T, const BYTES: usize> StackBudget<T, BYTES> {
+    /// Fails the build when evaluated for a `T` larger than `BYTES`.
+    pub(crate) const CHECK: () = assert!(
+        size_of::<T>() <= BYTES,
+        "map exceeds its stack budget; reduce N or use new_boxed()"
+    );
+}
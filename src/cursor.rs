@@ -0,0 +1,168 @@
+use std::hash::BuildHasher;
+use std::vec;
+
+use super::equivalence::{DefaultEquivalence, KeyEquivalence};
+use super::heap_map::HeapMap;
+use super::inline_map::InlineMap;
+use super::map::MapKind;
+use super::pool::{NoPool, TablePolicy};
+
+/// A cursor over the entries of a `SmallHashMap` that can remove, replace
+/// and insert entries while walking.
+///
+/// Created by `SmallHashMap::cursor_mut`. The cursor starts at the first
+/// entry; [`current`](Self::current) returns `None` once it has moved past
+/// the last one. It never changes the map's storage mode, so it can't be
+/// invalidated by a transition partway through.
+///
+/// In inline mode the cursor walks the entries in insertion order. In heap
+/// mode the entries are moved out of the table into a buffer when the cursor
+/// is created, without cloning or rehashing them; each entry the cursor moves
+/// past is inserted back, and dropping the cursor inserts the rest. The table
+/// keeps its allocation throughout. Leaking the cursor (with `mem::forget`)
+/// loses the entries that haven't been put back.
+pub struct SmallHashMapCursorMut<'a, K, V, const N: usize, S, E = DefaultEquivalence, P = NoPool>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    inner: CursorKind<'a, K, V, N, S, E, P>,
+}
+
+enum CursorKind<'a, K, V, const N: usize, S, E, P>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    InlineMap {
+        map: &'a mut InlineMap<K, V, N, E>,
+        index: usize,
+    },
+    HeapMap {
+        map: &'a mut HeapMap<E::Stored<K>, V, S, P>,
+        current: Option<(E::Stored<K>, V)>,
+        pending: vec::IntoIter<(E::Stored<K>, V)>,
+    },
+}
+
+impl<'a, K, V, const N: usize, S, E, P> SmallHashMapCursorMut<'a, K, V, N, S, E, P>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    pub(crate) fn new(inner: &'a mut MapKind<K, V, N, S, E, P>) -> Self {
+        let inner = match inner {
+            MapKind::InlineMap(map) => CursorKind::InlineMap { map, index: 0 },
+            MapKind::HeapMap(map) => {
+                let mut pending = map.drain().collect::<Vec<_>>().into_iter();
+                CursorKind::HeapMap {
+                    current: pending.next(),
+                    map,
+                    pending,
+                }
+            }
+        };
+        Self { inner }
+    }
+
+    /// Returns the entry under the cursor, or `None` past the end.
+    pub fn current(&mut self) -> Option<(&K, &mut V)> {
+        match &mut self.inner {
+            CursorKind::InlineMap { map, index } => map.get_index_mut(*index),
+            CursorKind::HeapMap { current, .. } => {
+                let (key, value) = current.as_mut()?;
+                Some((E::unwrap_ref(key), value))
+            }
+        }
+    }
+
+    /// Moves the cursor to the next entry.
+    pub fn move_next(&mut self) {
+        match &mut self.inner {
+            CursorKind::InlineMap { map, index } => {
+                if *index < map.len() {
+                    *index += 1;
+                }
+            }
+            CursorKind::HeapMap {
+                map,
+                current,
+                pending,
+            } => {
+                if let Some((key, value)) = current.take() {
+                    map.insert_unique_unchecked(key, value);
+                }
+                *current = pending.next();
+            }
+        }
+    }
+
+    /// Removes the entry under the cursor and returns it. The cursor moves
+    /// to the following entry.
+    pub fn remove_current(&mut self) -> Option<(K, V)> {
+        match &mut self.inner {
+            CursorKind::InlineMap { map, index } => {
+                (*index < map.len()).then(|| map.remove_index(*index))
+            }
+            CursorKind::HeapMap {
+                current, pending, ..
+            } => {
+                let (key, value) = current.take()?;
+                *current = pending.next();
+                Some((E::unwrap(key), value))
+            }
+        }
+    }
+
+    /// Replaces the value under the cursor, returning the old value, or
+    /// returns `Err(value)` past the end.
+    pub fn replace_value(&mut self, value: V) -> Result<V, V> {
+        match self.current() {
+            Some((_, existing)) => Ok(std::mem::replace(existing, value)),
+            None => Err(value),
+        }
+    }
+
+    /// Inserts an entry just before the cursor; the cursor stays on the same
+    /// entry.
+    ///
+    /// Only supported in inline mode, where entries are ordered. The entry is
+    /// handed back as `Err` if the map is on the heap, the inline storage is
+    /// full, or the key is already present.
+    pub fn insert_before(&mut self, key: K, value: V) -> Result<(), (K, V)> {
+        match &mut self.inner {
+            CursorKind::InlineMap { map, index } => {
+                if map.len() >= N || map.contains_key(&key) {
+                    return Err((key, value));
+                }
+                map.insert_at(*index, key, value);
+                *index += 1;
+                Ok(())
+            }
+            CursorKind::HeapMap { .. } => Err((key, value)),
+        }
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> Drop for SmallHashMapCursorMut<'_, K, V, N, S, E, P>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    fn drop(&mut self) {
+        if let CursorKind::HeapMap {
+            map,
+            current,
+            pending,
+        } = &mut self.inner
+        {
+            for (key, value) in current.take().into_iter().chain(pending) {
+                map.insert_unique_unchecked(key, value);
+            }
+        }
+    }
+}
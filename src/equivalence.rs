@@ -0,0 +1,324 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// How a key-equivalence strategy stores keys in heap mode.
+///
+/// There are exactly two storage forms: [`DefaultEquivalence`] stores keys as
+/// they are, and every [`CustomEquivalence`] strategy stores them as
+/// [`EquivKey`]s so the heap table hashes and compares them with the
+/// strategy. The trait can't be implemented directly; the open extension
+/// point is [`CustomEquivalence`], which any type may implement and which
+/// provides this trait through a blanket impl. The seal only keeps other
+/// storage forms out.
+pub trait Equivalence: sealed::Sealed {
+    /// The key type held by the heap table.
+    type Stored<K>;
+
+    /// Converts a key into its stored form.
+    fn wrap<K>(key: K) -> Self::Stored<K>;
+
+    /// Views a key as its stored form without copying it.
+    fn wrap_ref<K>(key: &K) -> &Self::Stored<K>;
+
+    /// Converts a stored key back into the key.
+    fn unwrap<K>(stored: Self::Stored<K>) -> K;
+
+    /// Views a stored key as the key.
+    fn unwrap_ref<K>(stored: &Self::Stored<K>) -> &K;
+}
+
+/// A strategy for deciding when two keys of type `K` are the same.
+///
+/// `SmallHashMap` takes the strategy as its `E` parameter and uses it for
+/// both inline scans and heap hashing. `hash` and `eq` must agree: keys that
+/// are equal under `eq` must produce the same hash.
+///
+/// Custom strategies are usually zero-sized marker types that implement
+/// [`CustomEquivalence`] alongside this trait.
+///
+/// # Example
+///
+/// ```
+/// use small_hash_map::{CustomEquivalence, KeyEquivalence, SmallHashMap};
+/// use std::collections::hash_map::RandomState;
+/// use std::hash::{Hash, Hasher};
+///
+/// /// Compares paths ignoring a trailing slash.
+/// struct IgnoreTrailingSlash;
+///
+/// impl CustomEquivalence for IgnoreTrailingSlash {}
+///
+/// impl KeyEquivalence<String> for IgnoreTrailingSlash {
+///     fn hash<H: Hasher>(key: &String, state: &mut H) {
+///         key.trim_end_matches('/').hash(state);
+///     }
+///
+///     fn eq(a: &String, b: &String) -> bool {
+///         a.trim_end_matches('/') == b.trim_end_matches('/')
+///     }
+/// }
+///
+/// let mut routes: SmallHashMap<String, u32, 4, RandomState, IgnoreTrailingSlash> =
+///     SmallHashMap::new();
+/// routes.insert("/users/".to_string(), 1);
+/// assert_eq!(routes.get(&"/users".to_string()), Some(&1));
+/// ```
+pub trait KeyEquivalence<K>: Equivalence<Stored<K>: Hash + Eq> {
+    /// Feeds the parts of `key` that matter for equality into `state`.
+    fn hash<H: Hasher>(key: &K, state: &mut H);
+
+    /// Returns `true` if `a` and `b` count as the same key.
+    fn eq(a: &K, b: &K) -> bool;
+}
+
+/// Marks a type as a user-defined [`KeyEquivalence`] strategy.
+///
+/// This is the extension point for strategies: implementing it makes the type
+/// an [`Equivalence`] whose heap keys are stored as [`EquivKey`]s.
+pub trait CustomEquivalence {}
+
+/// A [`KeyEquivalence`] strategy that can also order keys.
+///
+/// `cmp` must agree with the strategy's `eq`: it returns
+/// `Ordering::Equal` exactly when `eq` returns `true`. `SmallHashMap`
+/// implements `PartialOrd` and `Ord` for any strategy that implements this
+/// trait, comparing entries sorted with `cmp`.
+///
+/// # Example
+///
+/// ```
+/// use small_hash_map::{AsciiCaseInsensitive, SmallHashMap};
+/// use std::collections::hash_map::RandomState;
+///
+/// type Headers = SmallHashMap<&'static str, u32, 4, RandomState, AsciiCaseInsensitive>;
+///
+/// let a: Headers = [("Host", 1)].into();
+/// let b: Headers = [("host", 1)].into();
+/// let c: Headers = [("Accept", 1)].into();
+/// assert_eq!(a.cmp(&b), std::cmp::Ordering::Equal);
+/// assert!(c < a);
+/// ```
+pub trait KeyOrdering<K>: KeyEquivalence<K> {
+    /// Compares two keys under the strategy.
+    fn cmp(a: &K, b: &K) -> Ordering;
+}
+
+/// The strategy `SmallHashMap` uses by default: the key's own `Hash` and
+/// `Eq`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultEquivalence;
+
+impl sealed::Sealed for DefaultEquivalence {}
+
+impl Equivalence for DefaultEquivalence {
+    type Stored<K> = K;
+
+    fn wrap<K>(key: K) -> K {
+        key
+    }
+
+    fn wrap_ref<K>(key: &K) -> &K {
+        key
+    }
+
+    fn unwrap<K>(stored: K) -> K {
+        stored
+    }
+
+    fn unwrap_ref<K>(stored: &K) -> &K {
+        stored
+    }
+}
+
+impl<K: Hash + Eq> KeyEquivalence<K> for DefaultEquivalence {
+    fn hash<H: Hasher>(key: &K, state: &mut H) {
+        key.hash(state);
+    }
+
+    fn eq(a: &K, b: &K) -> bool {
+        a == b
+    }
+}
+
+impl<K: Hash + Ord> KeyOrdering<K> for DefaultEquivalence {
+    fn cmp(a: &K, b: &K) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+impl<E: CustomEquivalence> sealed::Sealed for E {}
+
+impl<E: CustomEquivalence> Equivalence for E {
+    type Stored<K> = EquivKey<K, E>;
+
+    fn wrap<K>(key: K) -> EquivKey<K, E> {
+        EquivKey::new(key)
+    }
+
+    fn wrap_ref<K>(key: &K) -> &EquivKey<K, E> {
+        EquivKey::from_ref(key)
+    }
+
+    fn unwrap<K>(stored: EquivKey<K, E>) -> K {
+        stored.into_inner()
+    }
+
+    fn unwrap_ref<K>(stored: &EquivKey<K, E>) -> &K {
+        stored.get()
+    }
+}
+
+/// Treats keys as equal if they match ignoring ASCII case.
+///
+/// Suited to HTTP header names and similar protocol identifiers.
+///
+/// # Example
+///
+/// ```
+/// use small_hash_map::{AsciiCaseInsensitive, SmallHashMap};
+/// use std::collections::hash_map::RandomState;
+///
+/// let mut headers: SmallHashMap<&str, &str, 8, RandomState, AsciiCaseInsensitive> =
+///     SmallHashMap::new();
+/// headers.insert("Content-Type", "text/html");
+///
+/// assert_eq!(headers.get(&"content-type"), Some(&"text/html"));
+/// assert_eq!(headers.get_key_value(&"CONTENT-TYPE"), Some((&"Content-Type", &"text/html")));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsciiCaseInsensitive;
+
+impl CustomEquivalence for AsciiCaseInsensitive {}
+
+impl<K: AsRef<str>> KeyEquivalence<K> for AsciiCaseInsensitive {
+    fn hash<H: Hasher>(key: &K, state: &mut H) {
+        for byte in key.as_ref().bytes() {
+            state.write_u8(byte.to_ascii_lowercase());
+        }
+        // Same terminator as `str`'s Hash, so "ab" + "c" != "a" + "bc".
+        state.write_u8(0xff);
+    }
+
+    fn eq(a: &K, b: &K) -> bool {
+        a.as_ref().eq_ignore_ascii_case(b.as_ref())
+    }
+}
+
+impl<K: AsRef<str>> KeyOrdering<K> for AsciiCaseInsensitive {
+    fn cmp(a: &K, b: &K) -> Ordering {
+        let a = a.as_ref().bytes().map(|byte| byte.to_ascii_lowercase());
+        let b = b.as_ref().bytes().map(|byte| byte.to_ascii_lowercase());
+        a.cmp(b)
+    }
+}
+
+/// Treats keys as equal if they match after trimming leading and trailing
+/// whitespace.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TrimWhitespace;
+
+impl CustomEquivalence for TrimWhitespace {}
+
+impl<K: AsRef<str>> KeyEquivalence<K> for TrimWhitespace {
+    fn hash<H: Hasher>(key: &K, state: &mut H) {
+        key.as_ref().trim().hash(state);
+    }
+
+    fn eq(a: &K, b: &K) -> bool {
+        a.as_ref().trim() == b.as_ref().trim()
+    }
+}
+
+impl<K: AsRef<str>> KeyOrdering<K> for TrimWhitespace {
+    fn cmp(a: &K, b: &K) -> Ordering {
+        a.as_ref().trim().cmp(b.as_ref().trim())
+    }
+}
+
+/// A key whose `Hash` and `Eq` are defined by the strategy `E`.
+///
+/// This is how a map with a [`CustomEquivalence`] strategy stores its keys
+/// in heap mode, and what [`SmallHashMap::as_heap`](crate::SmallHashMap::as_heap)
+/// exposes for such a map. It can also be used on its own as the key of any
+/// map.
+///
+/// The wrapper is `#[repr(transparent)]`, so an existing `&K` can be viewed
+/// as a key with [`EquivKey::from_ref`] for lookups.
+#[repr(transparent)]
+pub struct EquivKey<K, E> {
+    key: K,
+    equivalence: PhantomData<fn() -> E>,
+}
+
+impl<K, E> EquivKey<K, E> {
+    /// Wraps a key.
+    pub const fn new(key: K) -> Self {
+        Self {
+            key,
+            equivalence: PhantomData,
+        }
+    }
+
+    /// Views a borrowed key as an `EquivKey` without copying it.
+    pub fn from_ref(key: &K) -> &Self {
+        // SAFETY: EquivKey is repr(transparent) over K; the PhantomData field
+        // is zero-sized.
+        unsafe { &*(key as *const K as *const Self) }
+    }
+
+    /// Returns a reference to the wrapped key.
+    pub fn get(&self) -> &K {
+        &self.key
+    }
+
+    /// Consumes the wrapper and returns the key.
+    pub fn into_inner(self) -> K {
+        self.key
+    }
+}
+
+impl<K, E: KeyEquivalence<K> + CustomEquivalence> Hash for EquivKey<K, E> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        E::hash(&self.key, state);
+    }
+}
+
+impl<K, E: KeyEquivalence<K> + CustomEquivalence> PartialEq for EquivKey<K, E> {
+    fn eq(&self, other: &Self) -> bool {
+        E::eq(&self.key, &other.key)
+    }
+}
+
+impl<K, E: KeyEquivalence<K> + CustomEquivalence> Eq for EquivKey<K, E> {}
+
+impl<K, E> From<K> for EquivKey<K, E> {
+    fn from(key: K) -> Self {
+        Self::new(key)
+    }
+}
+
+impl<K: Clone, E> Clone for EquivKey<K, E> {
+    fn clone(&self) -> Self {
+        Self::new(self.key.clone())
+    }
+}
+
+impl<K: Copy, E> Copy for EquivKey<K, E> {}
+
+impl<K: fmt::Debug, E> fmt::Debug for EquivKey<K, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.key.fmt(f)
+    }
+}
+
+impl<K: fmt::Display, E> fmt::Display for EquivKey<K, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.key.fmt(f)
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::hash_map::OccupiedEntry;
+use std::error::Error;
+use std::fmt;
+
+use super::equivalence::{DefaultEquivalence, Equivalence};
+
+/// The error returned by `try_insert` when the key already exists.
+///
+/// Gives access to the existing entry and hands back the value that was not
+/// inserted. The map is left unchanged.
+pub struct OccupiedError<'a, K, V, E: Equivalence = DefaultEquivalence> {
+    existing: Existing<'a, K, V, E>,
+    value: V,
+}
+
+/// The occupied slot, borrowed from whichever storage holds it.
+enum Existing<'a, K, V, E: Equivalence> {
+    InlineMap(&'a K, &'a mut V),
+    HeapMap(OccupiedEntry<'a, E::Stored<K>, V>),
+}
+
+impl<'a, K, V, E: Equivalence> OccupiedError<'a, K, V, E> {
+    pub(crate) fn inline(key: &'a K, existing: &'a mut V, value: V) -> Self {
+        Self {
+            existing: Existing::InlineMap(key, existing),
+            value,
+        }
+    }
+
+    pub(crate) fn heap(entry: OccupiedEntry<'a, E::Stored<K>, V>, value: V) -> Self {
+        Self {
+            existing: Existing::HeapMap(entry),
+            value,
+        }
+    }
+
+    /// Returns a reference to the key already stored in the map.
+    pub fn key(&self) -> &K {
+        match &self.existing {
+            Existing::InlineMap(key, _) => key,
+            Existing::HeapMap(entry) => E::unwrap_ref(entry.key()),
+        }
+    }
+
+    /// Returns a reference to the value already stored in the map.
+    pub fn existing(&self) -> &V {
+        match &self.existing {
+            Existing::InlineMap(_, value) => value,
+            Existing::HeapMap(entry) => entry.get(),
+        }
+    }
+
+    /// Returns a mutable reference to the value already stored in the map.
+    pub fn existing_mut(&mut self) -> &mut V {
+        match &mut self.existing {
+            Existing::InlineMap(_, value) => value,
+            Existing::HeapMap(entry) => entry.get_mut(),
+        }
+    }
+
+    /// Converts the error into a mutable reference to the existing value,
+    /// with the lifetime of the map borrow.
+    pub fn into_existing_mut(self) -> &'a mut V {
+        match self.existing {
+            Existing::InlineMap(_, value) => value,
+            Existing::HeapMap(entry) => entry.into_mut(),
+        }
+    }
+
+    /// Returns a reference to the value that was not inserted.
+    pub fn value(&self) -> &V {
+        &self.value
+    }
+
+    /// Consumes the error and returns the value that was not inserted.
+    pub fn into_value(self) -> V {
+        self.value
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, E: Equivalence> fmt::Debug for OccupiedError<'_, K, V, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OccupiedError")
+            .field("key", self.key())
+            .field("old_value", self.existing())
+            .field("new_value", &self.value)
+            .finish()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, E: Equivalence> fmt::Display for OccupiedError<'_, K, V, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to insert {:?}, key {:?} already exists with value {:?}",
+            self.value,
+            self.key(),
+            self.existing(),
+        )
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, E: Equivalence> Error for OccupiedError<'_, K, V, E> {}
+
+/// The error returned by `try_from_iter` when the iterator yields a key more
+/// than once.
+///
+/// Holds the first key that was found to be a duplicate, as it was stored in
+/// the map by its earlier occurrence. With a custom key equivalence the two
+/// occurrences may differ; this is the one that was seen first.
+pub struct DuplicateKeyError<K> {
+    key: K,
+}
+
+impl<K> DuplicateKeyError<K> {
+    pub(crate) fn new(key: K) -> Self {
+        Self { key }
+    }
+
+    /// Returns a reference to the duplicate key.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Consumes the error and returns the duplicate key.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+}
+
+impl<K: fmt::Debug> fmt::Debug for DuplicateKeyError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DuplicateKeyError")
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+impl<K: fmt::Debug> fmt::Display for DuplicateKeyError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "duplicate key {:?}", self.key)
+    }
+}
+
+impl<K: fmt::Debug> Error for DuplicateKeyError<K> {}
@@ -1,91 +1,133 @@
-use std::collections::hash_map::RandomState;
+use std::borrow::Borrow;
+use std::collections::hash_map::{Entry, OccupiedEntry, RandomState};
 use std::collections::HashMap;
 use std::fmt;
 use std::hash::{BuildHasher, Hash};
+use std::mem;
+use std::ptr;
+
+use super::error::OccupiedError;
+use super::pool::{NoPool, TableEntries, TablePolicy};
 
 /// A HashMap wrapper that can use any hasher implementing `BuildHasher`.
 ///
 /// This is used internally by SmallHashMap after transitioning from
 /// stack-allocated storage. By default, it uses `RandomState` (the same
 /// default hasher as `std::collections::HashMap`).
-pub struct HeapMap<K, V, S = RandomState> {
-    map: HashMap<K, V, S>,
+///
+/// The table policy `P` decides where the table comes from and where it
+/// goes on drop; see [`TablePolicy`].
+pub struct HeapMap<K, V, S = RandomState, P: TablePolicy<K, V, S> = NoPool> {
+    map: P::Table,
 }
 
-impl<K: Clone, V: Clone, S: Clone> Clone for HeapMap<K, V, S> {
+impl<K: Clone, V: Clone, S: Clone, P: TablePolicy<K, V, S>> Clone for HeapMap<K, V, S, P> {
+    /// Clones the map into a newly allocated table.
     fn clone(&self) -> Self {
         Self {
-            map: self.map.clone(),
+            map: P::wrap((*self.map).clone()),
         }
     }
 }
 
-impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for HeapMap<K, V, S> {
+impl<K: fmt::Debug, V: fmt::Debug, S, P: TablePolicy<K, V, S>> fmt::Debug for HeapMap<K, V, S, P> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("HeapMap")
             .field("len", &self.map.len())
-            .field("map", &self.map)
+            .field("map", &*self.map)
             .finish()
     }
 }
 
-impl<K, V, S: Default> Default for HeapMap<K, V, S> {
+impl<K, V, S: Default, P: TablePolicy<K, V, S>> Default for HeapMap<K, V, S, P> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<K: Hash + Eq, V, S> IntoIterator for HeapMap<K, V, S> {
+impl<K: Hash + Eq, V, S, P: TablePolicy<K, V, S>> IntoIterator for HeapMap<K, V, S, P> {
     type Item = (K, V);
-    type IntoIter = std::collections::hash_map::IntoIter<K, V>;
+    type IntoIter = P::IntoIter;
 
     /// Consumes the map and returns an iterator over owned key-value pairs.
     fn into_iter(self) -> Self::IntoIter {
-        self.map.into_iter()
+        P::into_iter(self.map)
     }
 }
 
-impl<K: Hash + Eq, V, S: BuildHasher> Extend<(K, V)> for HeapMap<K, V, S> {
+impl<K: Hash + Eq, V, S: BuildHasher, P: TablePolicy<K, V, S>> Extend<(K, V)>
+    for HeapMap<K, V, S, P>
+{
     /// Extends the map with the contents of an iterator.
     fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
         self.map.extend(iter);
     }
 }
 
-impl<K, V, S: Default> HeapMap<K, V, S> {
+impl<K: Hash + Eq, V, S: BuildHasher + Default, P: TablePolicy<K, V, S>>
+    std::iter::FromIterator<(K, V)> for HeapMap<K, V, S, P>
+{
+    /// Creates a `HeapMap` from an iterator of key-value pairs.
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self::from_map(HashMap::from_iter(iter))
+    }
+}
+
+impl<K: Hash + Eq, V: PartialEq, S: BuildHasher, P: TablePolicy<K, V, S>> PartialEq
+    for HeapMap<K, V, S, P>
+{
+    fn eq(&self, other: &Self) -> bool {
+        *self.map == *other.map
+    }
+}
+
+impl<K: Hash + Eq, V: Eq, S: BuildHasher, P: TablePolicy<K, V, S>> Eq for HeapMap<K, V, S, P> {}
+
+impl<K, V, S> From<HashMap<K, V, S>> for HeapMap<K, V, S> {
+    /// Wraps an existing `HashMap` without rehashing.
+    fn from(map: HashMap<K, V, S>) -> Self {
+        Self::from_map(map)
+    }
+}
+
+impl<K, V, S, P: TablePolicy<K, V, S>> From<HeapMap<K, V, S, P>> for HashMap<K, V, S> {
+    /// Returns the wrapped `HashMap` without rehashing.
+    fn from(map: HeapMap<K, V, S, P>) -> Self {
+        map.into_inner()
+    }
+}
+
+impl<K, V, S: Default, P: TablePolicy<K, V, S>> HeapMap<K, V, S, P> {
     /// Creates a new empty HeapMap with the default hasher.
     pub fn new() -> Self {
-        Self {
-            map: HashMap::with_hasher(S::default()),
-        }
+        Self::with_capacity(0)
     }
 
     /// Creates a new HeapMap with the specified capacity and default hasher.
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
-            map: HashMap::with_capacity_and_hasher(capacity, S::default()),
+            map: P::new_table(capacity, S::default()),
         }
     }
 }
 
-impl<K, V, S> HeapMap<K, V, S> {
+impl<K, V, S, P: TablePolicy<K, V, S>> HeapMap<K, V, S, P> {
     /// Creates a new empty HeapMap with the specified hasher.
     pub fn with_hasher(hash_builder: S) -> Self
     where
         S: BuildHasher,
     {
-        Self {
-            map: HashMap::with_hasher(hash_builder),
-        }
+        Self::with_capacity_and_hasher(0, hash_builder)
     }
 
-    /// Creates a new HeapMap with the specified capacity and hasher.
+    /// Creates a new HeapMap with the specified capacity and hasher. Under
+    /// a pooling policy, the table is taken from the pool when it has one.
     pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self
     where
         S: BuildHasher,
     {
         Self {
-            map: HashMap::with_capacity_and_hasher(capacity, hash_builder),
+            map: P::new_table(capacity, hash_builder),
         }
     }
 
@@ -108,27 +150,69 @@ impl<K, V, S> HeapMap<K, V, S> {
     pub fn capacity(&self) -> usize {
         self.map.capacity()
     }
+
+    /// Returns a reference to the wrapped `HashMap`.
+    pub fn as_hash_map(&self) -> &HashMap<K, V, S> {
+        &self.map
+    }
+
+    /// Returns a mutable reference to the wrapped `HashMap`.
+    pub fn as_hash_map_mut(&mut self) -> &mut HashMap<K, V, S> {
+        &mut self.map
+    }
+
+    /// Takes ownership of an existing table, managed by the table policy from
+    /// now on.
+    pub(crate) fn from_map(map: HashMap<K, V, S>) -> Self {
+        Self { map: P::wrap(map) }
+    }
+
+    /// Consumes the wrapper and returns the `HashMap` without rehashing. The
+    /// table is no longer managed by the table policy.
+    pub fn into_inner(self) -> HashMap<K, V, S> {
+        P::into_map(self.map)
+    }
+
+    /// Consumes the map like `into_iter`, as an iterator type shared by all
+    /// table policies.
+    pub(crate) fn into_entries(self) -> TableEntries<K, V> {
+        P::into_entries(self.map)
+    }
 }
 
-impl<K: Hash + Eq, V, S: BuildHasher> HeapMap<K, V, S> {
+impl<K: Hash + Eq, V, S: BuildHasher, P: TablePolicy<K, V, S>> HeapMap<K, V, S, P> {
     /// Clears the map, removing all key-value pairs.
     pub fn clear(&mut self) {
         self.map.clear();
     }
 
     /// Returns a reference to the value corresponding to the key.
-    pub fn get(&self, key: &K) -> Option<&V> {
-        self.map.get(key)
+    ///
+    /// The key may be any borrowed form of the map's key type.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_key_value(key).map(|(_, value)| value)
     }
 
     /// Returns a mutable reference to the value corresponding to the key.
-    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         self.map.get_mut(key)
     }
 
     /// Returns references to both the key and value corresponding to the key.
-    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
-        self.map.get_key_value(key)
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        P::get_key_value(&self.map, key)
     }
 
     /// Inserts a key-value pair into the map.
@@ -140,15 +224,99 @@ impl<K: Hash + Eq, V, S: BuildHasher> HeapMap<K, V, S> {
         self.map.insert(key, value)
     }
 
+    /// Tries to insert a key-value pair into the map, and returns a mutable
+    /// reference to the value in the entry.
+    ///
+    /// If the map already had this key present, nothing is updated, and an
+    /// error containing the occupied entry and the value is returned.
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<&mut V, OccupiedError<'_, K, V>> {
+        match self.map.entry(key) {
+            Entry::Occupied(entry) => Err(OccupiedError::heap(entry, value)),
+            Entry::Vacant(entry) => Ok(entry.insert(value)),
+        }
+    }
+
+    /// Inserts a key-value pair that the caller guarantees is not already
+    /// present, and returns a mutable reference to the value.
+    ///
+    /// This costs one hash and probe, the same as `insert`, since `HashMap`
+    /// has no unchecked insert. The uniqueness promise is checked with a
+    /// debug assertion. In release builds a duplicate key keeps its existing
+    /// value and `value` is dropped, matching `InlineMap`, where lookups keep
+    /// finding the first copy of the key.
+    pub fn insert_unique_unchecked(&mut self, key: K, value: V) -> &mut V {
+        match self.map.entry(key) {
+            Entry::Vacant(entry) => entry.insert(value),
+            Entry::Occupied(entry) => {
+                debug_assert!(
+                    false,
+                    "insert_unique_unchecked called with a key that is already present"
+                );
+                entry.into_mut()
+            }
+        }
+    }
+
+    /// Gets the given key's corresponding entry in the map for in-place
+    /// manipulation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::HeapMap;
+    ///
+    /// let mut counts: HeapMap<&str, u32> = HeapMap::new();
+    /// for word in ["a", "b", "a"] {
+    ///     *counts.entry(word).or_insert(0) += 1;
+    /// }
+    /// assert_eq!(counts.get("a"), Some(&2));
+    /// ```
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        self.map.entry(key)
+    }
+
+    /// Reserves capacity for at least `additional` more elements.
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional);
+    }
+
+    /// Shrinks the capacity of the map as much as possible.
+    pub fn shrink_to_fit(&mut self) {
+        self.map.shrink_to_fit();
+    }
+
+    /// Shrinks the capacity of the map with a lower limit.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.map.shrink_to(min_capacity);
+    }
+
     /// Removes a key from the map, returning the value at the key if the key
     /// was previously in the map.
-    pub fn remove(&mut self, key: &K) -> Option<V> {
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         self.map.remove(key)
     }
 
+    /// Removes a key from the map, returning the stored key and value if the
+    /// key was previously in the map.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.remove_entry(key)
+    }
+
     /// Returns `true` if the map contains a value for the specified key.
-    pub fn contains_key(&self, key: &K) -> bool {
-        self.map.contains_key(key)
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_key_value(key).is_some()
     }
 
     /// Returns an iterator visiting all key-value pairs in arbitrary order.
@@ -178,6 +346,12 @@ impl<K: Hash + Eq, V, S: BuildHasher> HeapMap<K, V, S> {
         self.map.values_mut()
     }
 
+    /// Clears the map, returning all key-value pairs as an iterator. Keeps the
+    /// allocated memory for reuse.
+    pub fn drain(&mut self) -> std::collections::hash_map::Drain<'_, K, V> {
+        self.map.drain()
+    }
+
     /// Retains only the elements specified by the predicate.
     ///
     /// In other words, remove all pairs `(k, v)` for which `f(&k, &mut v)` returns `false`.
@@ -188,3 +362,47 @@ impl<K: Hash + Eq, V, S: BuildHasher> HeapMap<K, V, S> {
         self.map.retain(f);
     }
 }
+
+/// Moves the value out of an occupied entry and stores `f(key, value)` back
+/// in its place, removing the entry if `f` returns `None`. The key is hashed
+/// only once, by the `entry` call that produced `entry`.
+///
+/// If `f` panics, the entry is removed.
+pub(crate) fn alter_occupied<'a, K, V, F>(
+    mut entry: OccupiedEntry<'a, K, V>,
+    f: F,
+) -> Option<&'a mut V>
+where
+    F: FnOnce(&K, V) -> Option<V>,
+{
+    /// Removes the entry, whose value has been moved out, when dropped.
+    struct RemoveOnDrop<'a, K, V>(Option<OccupiedEntry<'a, K, V>>);
+
+    impl<K, V> Drop for RemoveOnDrop<'_, K, V> {
+        fn drop(&mut self) {
+            if let Some(entry) = self.0.take() {
+                let (key, value) = entry.remove_entry();
+                // The value was already moved out; these bits are a stale copy.
+                mem::forget(value);
+                drop(key);
+            }
+        }
+    }
+
+    let slot: *mut V = entry.get_mut();
+    let mut guard = RemoveOnDrop(Some(entry));
+    // SAFETY: `slot` points to the entry's live value. It's moved out here
+    // and either written back below or forgotten by the guard, so it's
+    // dropped exactly once.
+    let value = unsafe { ptr::read(slot) };
+    let entry = guard.0.as_ref().expect("entry is held until f returns");
+    match f(entry.key(), value) {
+        Some(value) => {
+            // SAFETY: The slot still belongs to the entry, and its old value
+            // was moved out above.
+            unsafe { ptr::write(slot, value) };
+            guard.0.take().map(OccupiedEntry::into_mut)
+        }
+        None => None,
+    }
+}
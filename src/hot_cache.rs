@@ -0,0 +1,228 @@
+use std::borrow::Borrow;
+use std::cell::Cell;
+use std::collections::hash_map::{self, RandomState};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+use super::equivalence::DefaultEquivalence;
+use super::pool::{sealed, TableEntries, TablePolicy};
+use super::small_hash_map::SmallHashMap;
+
+/// A [`TablePolicy`] that keeps a direct-mapped cache of recently looked-up
+/// entries next to the heap table.
+///
+/// A lookup first hashes the key with a cheap, unkeyed hasher to pick one of
+/// `SLOTS` cache slots, and compares the key against the entry cached there.
+/// On a hit, the map's own hasher is never run; on a miss, the table is
+/// searched as usual and the entry found replaces the one in the slot. This
+/// pays off when a few hot keys are looked up far more often than the rest
+/// and the map's hasher is expensive, such as `RandomState` with long string
+/// keys. A miss costs the cheap hash and at most one extra key comparison.
+///
+/// Each slot holds pointers to an entry's key and value in the table, so
+/// caching never clones a key. Every mutable access to the table empties
+/// the cache first, so `insert`, `remove`, `retain`, `iter_mut` and the rest
+/// never leave a stale entry behind.
+///
+/// The cache uses interior mutability, so a map with this policy is `Send`
+/// but not `Sync`. Since the slot hasher is unkeyed, crafted keys can make
+/// hot keys share a slot, which only turns hits into misses.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HotCache<const SLOTS: usize>;
+
+/// A `SmallHashMap` that, after spilling, caches up to `N` hot entries in
+/// the space its inline arrays used. See [`HotCache`].
+///
+/// The cache takes two pointers per slot. When a key and value together are
+/// at least that large, it fits in the inline space and the map is no
+/// larger than a plain `SmallHashMap`.
+///
+/// # Example
+///
+/// ```
+/// use small_hash_map::CachedSmallHashMap;
+///
+/// let mut map: CachedSmallHashMap<String, u32, 4> = CachedSmallHashMap::new();
+/// map.extend((0..100).map(|i| (i.to_string(), i)));
+/// assert!(!map.is_inline());
+///
+/// let hot = "42".to_string();
+/// for _ in 0..10 {
+///     // Found in the table once, then served from the cache.
+///     assert_eq!(map.get(&hot), Some(&42));
+/// }
+/// ```
+pub type CachedSmallHashMap<K, V, const N: usize, S = RandomState, E = DefaultEquivalence> =
+    SmallHashMap<K, V, N, S, E, HotCache<N>>;
+
+/// A table owned by a `HeapMap` under [`HotCache`], with its cache.
+pub struct CachedTable<K, V, S, const SLOTS: usize> {
+    map: HashMap<K, V, S>,
+    slots: [Cell<Option<CachedEntry<K, V>>>; SLOTS],
+}
+
+/// Where a cached entry lives in the table.
+struct CachedEntry<K, V> {
+    key: NonNull<K>,
+    value: NonNull<V>,
+}
+
+impl<K, V> Clone for CachedEntry<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for CachedEntry<K, V> {}
+
+// SAFETY: The cache only points into the table it sits next to, which moves
+// to the other thread along with it.
+unsafe impl<K: Send, V: Send, S: Send, const SLOTS: usize> Send for CachedTable<K, V, S, SLOTS> {}
+
+impl<K, V, S, const SLOTS: usize> CachedTable<K, V, S, SLOTS> {
+    fn clear_cache(&mut self) {
+        for slot in &mut self.slots {
+            *slot.get_mut() = None;
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S, const SLOTS: usize> fmt::Debug
+    for CachedTable<K, V, S, SLOTS>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachedTable")
+            .field("map", &self.map)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, V, S, const SLOTS: usize> Deref for CachedTable<K, V, S, SLOTS> {
+    type Target = HashMap<K, V, S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<K, V, S, const SLOTS: usize> DerefMut for CachedTable<K, V, S, SLOTS> {
+    /// Empties the cache, since the caller may move or remove any entry.
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.clear_cache();
+        &mut self.map
+    }
+}
+
+impl<const SLOTS: usize> sealed::Sealed for HotCache<SLOTS> {}
+
+impl<K, V, S, const SLOTS: usize> TablePolicy<K, V, S> for HotCache<SLOTS> {
+    type Table = CachedTable<K, V, S, SLOTS>;
+    type IntoIter = hash_map::IntoIter<K, V>;
+
+    fn new_table(capacity: usize, hash_builder: S) -> Self::Table {
+        Self::wrap(HashMap::with_capacity_and_hasher(capacity, hash_builder))
+    }
+
+    fn wrap(map: HashMap<K, V, S>) -> Self::Table {
+        CachedTable {
+            map,
+            slots: [const { Cell::new(None) }; SLOTS],
+        }
+    }
+
+    fn into_map(table: Self::Table) -> HashMap<K, V, S> {
+        table.map
+    }
+
+    fn into_iter(table: Self::Table) -> Self::IntoIter {
+        table.map.into_iter()
+    }
+
+    fn into_entries(table: Self::Table) -> TableEntries<K, V> {
+        TableEntries::Table(table.map.into_iter())
+    }
+
+    fn get_key_value<'a, Q>(table: &'a Self::Table, key: &Q) -> Option<(&'a K, &'a V)>
+    where
+        K: Borrow<Q> + Hash + Eq,
+        Q: Hash + Eq + ?Sized,
+        S: BuildHasher + 'a,
+    {
+        if SLOTS == 0 {
+            return table.map.get_key_value(key);
+        }
+        let mut hasher = SlotHasher::default();
+        key.hash(&mut hasher);
+        let slot = &table.slots[(hasher.finish() >> 32) as usize % SLOTS];
+        if let Some(entry) = slot.get() {
+            // SAFETY: Cached pointers come from entries of `table.map`, and
+            // are dropped whenever the table is borrowed mutably, so they
+            // still point at live entries for as long as `table` is borrowed.
+            let (cached_key, value) = unsafe { (entry.key.as_ref(), entry.value.as_ref()) };
+            if cached_key.borrow() == key {
+                return Some((cached_key, value));
+            }
+        }
+        let (found_key, value) = table.map.get_key_value(key)?;
+        slot.set(Some(CachedEntry {
+            key: NonNull::from(found_key),
+            value: NonNull::from(value),
+        }));
+        Some((found_key, value))
+    }
+}
+
+/// A multiply-rotate hasher that only picks cache slots. `K: Borrow<Q>`
+/// guarantees a key and its borrowed form feed it the same input.
+#[derive(Default)]
+struct SlotHasher(u64);
+
+impl SlotHasher {
+    fn add(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+}
+
+impl Hasher for SlotHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            let mut buf = [0; 8];
+            buf.copy_from_slice(word);
+            self.add(u64::from_le_bytes(buf));
+        }
+        let rest = words.remainder();
+        if !rest.is_empty() {
+            let mut buf = [0; 8];
+            buf[..rest.len()].copy_from_slice(rest);
+            self.add(u64::from_le_bytes(buf));
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add(i.into());
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.add(i.into());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.add(i.into());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add(i);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -1,7 +1,11 @@
 use std::fmt;
-use std::hash::Hash;
+use std::marker::PhantomData;
 use std::mem::MaybeUninit;
 
+use super::budget::StackBudget;
+use super::equivalence::{DefaultEquivalence, KeyEquivalence};
+use super::error::OccupiedError;
+
 /// A minimal map implementation optimized for small collections.
 ///
 /// Uses static arrays for both keys and values with no heap allocation.
@@ -9,31 +13,30 @@ use std::mem::MaybeUninit;
 ///
 /// Keys do not need to implement Default, using MaybeUninit for uninitialized
 /// storage.
-pub struct InlineMap<K, V, const N: usize> {
+///
+/// Keys are compared with the [`KeyEquivalence`] strategy `E`, which defaults
+/// to their own `Eq`.
+pub struct InlineMap<K, V, const N: usize, E = DefaultEquivalence> {
     keys: [MaybeUninit<K>; N],
     values: [MaybeUninit<V>; N],
     len: usize,
+    equivalence: PhantomData<fn() -> E>,
 }
 
-impl<K: Clone, V: Clone, const N: usize> Clone for InlineMap<K, V, N> {
+impl<K: Clone, V: Clone, const N: usize, E> Clone for InlineMap<K, V, N, E> {
     fn clone(&self) -> Self {
-        let mut keys = [(); N].map(|_| MaybeUninit::uninit());
-        let mut values = [(); N].map(|_| MaybeUninit::uninit());
-
-        for i in 0..self.len {
-            keys[i] = MaybeUninit::new(unsafe { self.keys[i].assume_init_ref() }.clone());
-            values[i] = MaybeUninit::new(unsafe { self.values[i].assume_init_ref() }.clone());
-        }
-
-        Self {
-            keys,
-            values,
-            len: self.len,
+        // Push one entry at a time so that, if a clone panics, the entries
+        // cloned so far are dropped with the partial map.
+        let mut map = Self::new();
+        let (keys, values) = self.as_slices();
+        for (key, value) in keys.iter().zip(values) {
+            map.push(key.clone(), value.clone());
         }
+        map
     }
 }
 
-impl<K: fmt::Debug, V: fmt::Debug, const N: usize> fmt::Debug for InlineMap<K, V, N> {
+impl<K: fmt::Debug, V: fmt::Debug, const N: usize, E> fmt::Debug for InlineMap<K, V, N, E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut map = f.debug_map();
         for i in 0..self.len {
@@ -45,7 +48,7 @@ impl<K: fmt::Debug, V: fmt::Debug, const N: usize> fmt::Debug for InlineMap<K, V
     }
 }
 
-impl<K, V, const N: usize> InlineMap<K, V, N> {
+impl<K, V, const N: usize, E> InlineMap<K, V, N, E> {
     /// Returns the maximum number of elements the map can hold.
     pub const fn capacity(&self) -> usize {
         N
@@ -65,19 +68,79 @@ impl<K, V, const N: usize> InlineMap<K, V, N> {
                 keys: std::mem::MaybeUninit::uninit().assume_init(),
                 values: std::mem::MaybeUninit::uninit().assume_init(),
                 len: 0,
+                equivalence: PhantomData,
             }
         }
     }
 
     /// Creates a new empty map.
+    ///
+    /// The arrays are left uninitialized rather than built element by
+    /// element, so no stack temporaries are created beyond the map itself.
     pub fn new() -> Self {
-        Self {
-            keys: [(); N].map(|_| MaybeUninit::uninit()),
-            values: [(); N].map(|_| MaybeUninit::uninit()),
-            len: 0,
+        Self::const_new()
+    }
+
+    /// Creates a new empty map, failing to compile if it takes more than
+    /// `BYTES` bytes.
+    ///
+    /// This guards a map created on the stack against growing unnoticed when
+    /// `N`, `K` or `V` change. The check is evaluated when the call is
+    /// compiled for concrete types, so it fires at the offending
+    /// instantiation. [`new_boxed`](Self::new_boxed) has no such limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::InlineMap;
+    ///
+    /// let map: InlineMap<u32, u32, 8> = InlineMap::new_within_budget::<128>();
+    /// assert!(map.is_empty());
+    /// ```
+    ///
+    /// ```compile_fail
+    /// use small_hash_map::InlineMap;
+    ///
+    /// let map: InlineMap<u64, [u8; 256], 64> = InlineMap::new_within_budget::<4096>();
+    /// ```
+    pub fn new_within_budget<const BYTES: usize>() -> Self {
+        let () = StackBudget::<Self, BYTES>::CHECK;
+        Self::const_new()
+    }
+
+    /// Creates a new empty map directly on the heap.
+    ///
+    /// Only `len` is written, so the map is never built on the stack. Useful
+    /// when `N` or the key and value types are large.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::InlineMap;
+    ///
+    /// let mut map: Box<InlineMap<u64, [u8; 256], 1024>> = InlineMap::new_boxed();
+    /// map.insert(1, [0; 256]);
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn new_boxed() -> Box<Self> {
+        let mut boxed = Box::<Self>::new_uninit();
+        // SAFETY: The key and value arrays are MaybeUninit, so they are valid
+        // uninitialized; writing len = 0 initializes the only other field.
+        unsafe {
+            Self::init_empty_in_place(boxed.as_mut_ptr());
+            boxed.assume_init()
         }
     }
 
+    /// Writes an empty map to `ptr` by setting only its length.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for writes and properly aligned.
+    pub(crate) unsafe fn init_empty_in_place(ptr: *mut Self) {
+        std::ptr::addr_of_mut!((*ptr).len).write(0);
+    }
+
     /// Creates a new map with the specified capacity hint.
     ///
     /// Note: Capacity is ignored since InlineMap uses fixed-size arrays.
@@ -94,27 +157,334 @@ impl<K, V, const N: usize> InlineMap<K, V, N> {
     pub fn is_empty(&self) -> bool {
         self.len == 0
     }
-}
 
-impl<K, V, const N: usize> Drop for InlineMap<K, V, N> {
-    fn drop(&mut self) {
-        // Drop all initialized elements
-        for i in 0..self.len {
-            unsafe {
-                std::ptr::drop_in_place(self.keys[i].as_mut_ptr());
-                std::ptr::drop_in_place(self.values[i].as_mut_ptr());
+    /// Returns the keys and values as two slices of equal length, in
+    /// insertion order; `keys[i]` maps to `values[i]`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::InlineMap;
+    ///
+    /// let mut map: InlineMap<&str, f32, 4> = InlineMap::new();
+    /// map.insert("x", 1.0);
+    /// map.insert("y", 2.0);
+    ///
+    /// let (keys, values) = map.as_slices();
+    /// assert_eq!(keys, ["x", "y"]);
+    /// assert_eq!(values.iter().sum::<f32>(), 3.0);
+    /// ```
+    pub fn as_slices(&self) -> (&[K], &[V]) {
+        // SAFETY: self.keys[0..self.len] and self.values[0..self.len] are
+        // guaranteed to be initialized, and the slices borrow self.
+        unsafe {
+            (
+                std::slice::from_raw_parts(self.keys.as_ptr() as *const K, self.len),
+                std::slice::from_raw_parts(self.values.as_ptr() as *const V, self.len),
+            )
+        }
+    }
+
+    /// Returns the values as a mutable slice, in insertion order.
+    ///
+    /// Keys stay immutable, so the map can't be corrupted through it.
+    pub fn values_mut_slice(&mut self) -> &mut [V] {
+        // SAFETY: self.values[0..self.len] is guaranteed to be initialized.
+        unsafe { std::slice::from_raw_parts_mut(self.values.as_mut_ptr() as *mut V, self.len) }
+    }
+
+    /// Consumes the map and returns its raw key and value arrays together
+    /// with the number of initialized slots.
+    ///
+    /// Slots `0..len` of both arrays are initialized; the caller takes over
+    /// responsibility for dropping them.
+    pub fn into_parts(self) -> ([MaybeUninit<K>; N], [MaybeUninit<V>; N], usize) {
+        let map = std::mem::ManuallyDrop::new(self);
+        // SAFETY: The map is never dropped, so ownership of the arrays moves
+        // to the caller exactly once.
+        unsafe {
+            (
+                std::ptr::read(&map.keys),
+                std::ptr::read(&map.values),
+                map.len,
+            )
+        }
+    }
+
+    /// Rebuilds a map from arrays returned by [`into_parts`](Self::into_parts)
+    /// or filled by the caller.
+    ///
+    /// # Safety
+    ///
+    /// - `len` must not exceed `N`.
+    /// - Slots `0..len` of both `keys` and `values` must be initialized; the
+    ///   map takes ownership of them and drops them.
+    /// - The keys in `0..len` must be pairwise distinct. Duplicates don't
+    ///   cause undefined behavior, but lookups and removal will misbehave.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::InlineMap;
+    ///
+    /// let mut map: InlineMap<u8, u32, 4> = InlineMap::new();
+    /// map.insert(1, 10);
+    ///
+    /// let (keys, mut values, len) = map.into_parts();
+    /// // Overwriting an initialized `u32` slot leaks nothing.
+    /// values[0].write(11);
+    /// // SAFETY: The arrays and len come from into_parts.
+    /// let map: InlineMap<u8, u32, 4> = unsafe { InlineMap::from_parts(keys, values, len) };
+    /// assert_eq!(map.get(&1), Some(&11));
+    /// ```
+    pub unsafe fn from_parts(
+        keys: [MaybeUninit<K>; N],
+        values: [MaybeUninit<V>; N],
+        len: usize,
+    ) -> Self {
+        debug_assert!(len <= N, "len exceeds the inline capacity");
+        Self {
+            keys,
+            values,
+            len,
+            equivalence: PhantomData,
+        }
+    }
+
+    /// Consumes the map and returns an iterator that moves the entries out in
+    /// insertion order without allocating.
+    pub(crate) fn into_entries(mut self) -> IntoEntries<K, V, N, E> {
+        let end = self.len;
+        // The iterator takes over ownership of slots 0..end; with len = 0 the
+        // map's own Drop will not touch them.
+        self.len = 0;
+        IntoEntries {
+            map: self,
+            next: 0,
+            end,
+        }
+    }
+
+    /// Moves the entries into an inline map with capacity `M`, keeping their
+    /// order.
+    ///
+    /// Returns `Err(self)` unchanged if the map holds more than `M` entries.
+    /// Keys are neither hashed nor compared.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::InlineMap;
+    ///
+    /// let mut small: InlineMap<&str, i32, 2> = InlineMap::new();
+    /// small.insert("a", 1);
+    ///
+    /// let large: InlineMap<&str, i32, 8> = small.into_capacity().unwrap();
+    /// assert_eq!(large.get(&"a"), Some(&1));
+    /// assert_eq!(large.capacity(), 8);
+    /// ```
+    pub fn into_capacity<const M: usize>(self) -> Result<InlineMap<K, V, M, E>, Self> {
+        if self.len > M {
+            return Err(self);
+        }
+        let mut result = InlineMap::new();
+        for (key, value) in self.into_entries() {
+            result.push(key, value);
+        }
+        Ok(result)
+    }
+
+    /// Removes the entry at `index`, shifting the following entries left.
+    pub(crate) fn remove_index(&mut self, index: usize) -> (K, V) {
+        assert!(index < self.len, "index out of bounds");
+        // Move the entry out; the slot is overwritten by the shift below.
+        let removed_key = unsafe { std::ptr::read(self.keys[index].as_ptr()) };
+        let removed_value = unsafe { std::ptr::read(self.values[index].as_ptr()) };
+
+        // Shift remaining elements left
+        for j in index..self.len - 1 {
+            self.keys[j] = MaybeUninit::new(unsafe { std::ptr::read(self.keys[j + 1].as_ptr()) });
+            self.values[j] =
+                MaybeUninit::new(unsafe { std::ptr::read(self.values[j + 1].as_ptr()) });
+        }
+
+        self.len -= 1;
+        (removed_key, removed_value)
+    }
+
+    /// Inserts an entry at `index`, shifting the following entries right.
+    /// The caller must ensure the key is not already present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map is full or `index > len`.
+    pub(crate) fn insert_at(&mut self, index: usize, key: K, value: V) {
+        assert!(index <= self.len, "index out of bounds");
+        self.assert_has_room();
+        // SAFETY: len < N, so there is room to shift 0..len one slot right;
+        // ptr::copy handles the overlap.
+        unsafe {
+            let tail = self.len - index;
+            std::ptr::copy(
+                self.keys.as_ptr().add(index),
+                self.keys.as_mut_ptr().add(index + 1),
+                tail,
+            );
+            std::ptr::copy(
+                self.values.as_ptr().add(index),
+                self.values.as_mut_ptr().add(index + 1),
+                tail,
+            );
+        }
+        self.keys[index] = MaybeUninit::new(key);
+        self.values[index] = MaybeUninit::new(value);
+        self.len += 1;
+    }
+
+    /// Drops the entries in slots `start..end`.
+    ///
+    /// If a destructor panics, the remaining entries in the range are still
+    /// dropped.
+    ///
+    /// # Safety
+    ///
+    /// The slots must be initialized and must no longer be counted by `len`
+    /// (or otherwise owned by anyone else).
+    unsafe fn drop_range(&mut self, start: usize, end: usize) {
+        /// Drops the values if dropping the keys unwinds.
+        struct DropValues<V>(*mut [V]);
+
+        impl<V> Drop for DropValues<V> {
+            fn drop(&mut self) {
+                // SAFETY: Upheld by the caller of drop_range.
+                unsafe { std::ptr::drop_in_place(self.0) };
             }
         }
+
+        let len = end - start;
+        let keys =
+            std::ptr::slice_from_raw_parts_mut(self.keys.as_mut_ptr().add(start) as *mut K, len);
+        let values =
+            std::ptr::slice_from_raw_parts_mut(self.values.as_mut_ptr().add(start) as *mut V, len);
+        let _values = DropValues(values);
+        // Dropping a slice keeps going past a panicking element.
+        std::ptr::drop_in_place(keys);
+    }
+
+    /// Appends an entry without looking for an existing key.
+    ///
+    /// Used when moving entries out of another map whose keys are already
+    /// known to be unique.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map is full.
+    pub(crate) fn push(&mut self, key: K, value: V) {
+        self.assert_has_room();
+        self.keys[self.len] = MaybeUninit::new(key);
+        self.values[self.len] = MaybeUninit::new(value);
+        self.len += 1;
+    }
+
+    /// Replaces the value at `index` with `f(old_value)`.
+    ///
+    /// If `f` panics, the entry at `index` is removed (its key is dropped and
+    /// the following entries shift left), so the map stays consistent.
+    pub(crate) fn replace_value_with<F>(&mut self, index: usize, f: F)
+    where
+        F: FnOnce(V) -> V,
+    {
+        self.alter_value_at(index, |value| Some(f(value)));
+    }
+
+    /// Replaces the value at `index` with `f(old_value)` in place, or removes
+    /// the entry if `f` returns `None`.
+    ///
+    /// If `f` panics, the entry at `index` is removed as well.
+    pub(crate) fn alter_value_at<F>(&mut self, index: usize, f: F)
+    where
+        F: FnOnce(V) -> Option<V>,
+    {
+        assert!(index < self.len, "index out of bounds");
+
+        /// Removes the moved-out entry if `f` unwinds or returns `None`.
+        struct RemoveOnUnwind<'a, K, V, const N: usize, E> {
+            map: &'a mut InlineMap<K, V, N, E>,
+            index: usize,
+        }
+
+        impl<K, V, const N: usize, E> Drop for RemoveOnUnwind<'_, K, V, N, E> {
+            fn drop(&mut self) {
+                let map = &mut *self.map;
+                let i = self.index;
+                // SAFETY: Slot i holds a live key and a moved-out value. Take
+                // the key, then close the gap over both arrays.
+                let key = unsafe { std::ptr::read(map.keys[i].as_ptr()) };
+                unsafe {
+                    let tail = map.len - i - 1;
+                    std::ptr::copy(
+                        map.keys.as_ptr().add(i + 1),
+                        map.keys.as_mut_ptr().add(i),
+                        tail,
+                    );
+                    std::ptr::copy(
+                        map.values.as_ptr().add(i + 1),
+                        map.values.as_mut_ptr().add(i),
+                        tail,
+                    );
+                }
+                map.len -= 1;
+                drop(key);
+            }
+        }
+
+        let guard = RemoveOnUnwind { map: self, index };
+        // SAFETY: index < len, so the slot is initialized. The value is moved
+        // out here and moved back in below; the guard covers the gap.
+        let old_value = unsafe { std::ptr::read(guard.map.values[index].as_ptr()) };
+        if let Some(new_value) = f(old_value) {
+            guard.map.values[index] = MaybeUninit::new(new_value);
+            std::mem::forget(guard);
+        }
+    }
+
+    /// Panics if there is no room for another entry.
+    #[inline]
+    #[track_caller]
+    fn assert_has_room(&self) {
+        if self.len >= N {
+            capacity_exceeded(N);
+        }
+    }
+}
+
+/// Reports an insert into a full `InlineMap`. Kept out of line so the
+/// insert paths stay small.
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn capacity_exceeded(capacity: usize) -> ! {
+    panic!(
+        "InlineMap is full, cannot insert more than {} elements",
+        capacity
+    );
+}
+
+impl<K, V, const N: usize, E> Drop for InlineMap<K, V, N, E> {
+    fn drop(&mut self) {
+        let len = std::mem::replace(&mut self.len, 0);
+        // SAFETY: Slots 0..len are initialized and no longer counted by len.
+        unsafe { self.drop_range(0, len) };
     }
 }
 
-impl<K: Default, V: Default, const N: usize> Default for InlineMap<K, V, N> {
+impl<K: Default, V: Default, const N: usize, E> Default for InlineMap<K, V, N, E> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<K: Hash + Eq, V, const N: usize> IntoIterator for InlineMap<K, V, N> {
+impl<K, V, const N: usize, E: KeyEquivalence<K>> IntoIterator for InlineMap<K, V, N, E> {
     type Item = (K, V);
     type IntoIter = std::vec::IntoIter<(K, V)>;
 
@@ -124,7 +494,7 @@ impl<K: Hash + Eq, V, const N: usize> IntoIterator for InlineMap<K, V, N> {
     }
 }
 
-impl<K: Hash + Eq, V, const N: usize> Extend<(K, V)> for InlineMap<K, V, N> {
+impl<K, V, const N: usize, E: KeyEquivalence<K>> Extend<(K, V)> for InlineMap<K, V, N, E> {
     /// Extends the map with the contents of an iterator.
     ///
     /// # Panics
@@ -137,24 +507,21 @@ impl<K: Hash + Eq, V, const N: usize> Extend<(K, V)> for InlineMap<K, V, N> {
     }
 }
 
-impl<K: Hash + Eq, V, const N: usize> InlineMap<K, V, N> {
+impl<K, V, const N: usize, E: KeyEquivalence<K>> InlineMap<K, V, N, E> {
     /// Clears the map, removing all key-value pairs.
     pub fn clear(&mut self) {
-        // Drop all initialized elements
-        for i in 0..self.len {
-            unsafe {
-                std::ptr::drop_in_place(self.keys[i].as_mut_ptr());
-                std::ptr::drop_in_place(self.values[i].as_mut_ptr());
-            }
-        }
-        self.len = 0;
+        // Reset len first, so a panicking destructor can't leave dropped
+        // slots counted as live.
+        let len = std::mem::replace(&mut self.len, 0);
+        // SAFETY: Slots 0..len are initialized and no longer counted by len.
+        unsafe { self.drop_range(0, len) };
     }
 
     /// Returns a reference to the value corresponding to the key.
     pub fn get(&self, key: &K) -> Option<&V> {
         for i in 0..self.len {
             // SAFETY: Index i < self.len, so this slot is initialized.
-            if unsafe { self.keys[i].assume_init_ref() } == key {
+            if E::eq(unsafe { self.keys[i].assume_init_ref() }, key) {
                 return Some(unsafe { self.values[i].assume_init_ref() });
             }
         }
@@ -165,7 +532,7 @@ impl<K: Hash + Eq, V, const N: usize> InlineMap<K, V, N> {
     pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
         for i in 0..self.len {
             // SAFETY: Index i < self.len, so this slot is initialized.
-            if unsafe { self.keys[i].assume_init_ref() } == key {
+            if E::eq(unsafe { self.keys[i].assume_init_ref() }, key) {
                 return Some(unsafe { self.values[i].assume_init_mut() });
             }
         }
@@ -177,7 +544,7 @@ impl<K: Hash + Eq, V, const N: usize> InlineMap<K, V, N> {
         for i in 0..self.len {
             // SAFETY: Index i < self.len, so this slot is initialized.
             let k = unsafe { self.keys[i].assume_init_ref() };
-            if k == key {
+            if E::eq(k, key) {
                 let v = unsafe { self.values[i].assume_init_ref() };
                 return Some((k, v));
             }
@@ -197,7 +564,7 @@ impl<K: Hash + Eq, V, const N: usize> InlineMap<K, V, N> {
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
         // Check if key already exists
         for i in 0..self.len {
-            if unsafe { self.keys[i].assume_init_ref() } == &key {
+            if E::eq(unsafe { self.keys[i].assume_init_ref() }, &key) {
                 let old_value = unsafe { std::ptr::read(self.values[i].as_ptr()) };
                 self.values[i] = MaybeUninit::new(value);
                 return Some(old_value);
@@ -205,9 +572,7 @@ impl<K: Hash + Eq, V, const N: usize> InlineMap<K, V, N> {
         }
 
         // Key doesn't exist, add at the end
-        if self.len >= N {
-            panic!("InlineMap is full, cannot insert more than {} elements", N);
-        }
+        self.assert_has_room();
 
         self.keys[self.len] = MaybeUninit::new(key);
         self.values[self.len] = MaybeUninit::new(value);
@@ -219,26 +584,23 @@ impl<K: Hash + Eq, V, const N: usize> InlineMap<K, V, N> {
     /// Removes a key from the map, returning the value at the key if the key
     /// was previously in the map.
     pub fn remove(&mut self, key: &K) -> Option<V> {
-        for i in 0..self.len {
-            if unsafe { self.keys[i].assume_init_ref() } == key {
-                // Read the value to return
-                let removed_value = unsafe { std::ptr::read(self.values[i].as_ptr()) };
-                // Drop the key
-                unsafe { std::ptr::drop_in_place(self.keys[i].as_mut_ptr()) };
-
-                // Shift remaining elements left
-                for j in i..self.len - 1 {
-                    self.keys[j] =
-                        MaybeUninit::new(unsafe { std::ptr::read(self.keys[j + 1].as_ptr()) });
-                    self.values[j] =
-                        MaybeUninit::new(unsafe { std::ptr::read(self.values[j + 1].as_ptr()) });
-                }
+        self.remove_entry(key).map(|(_, value)| value)
+    }
 
-                self.len -= 1;
-                return Some(removed_value);
-            }
-        }
-        None
+    /// Removes a key from the map, returning the stored key and value if the
+    /// key was previously in the map.
+    pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
+        let i = self.find_key_index(key)?;
+        Some(self.remove_index(i))
+    }
+
+    /// Replaces the key stored at `index` with `key`, returning the old key.
+    ///
+    /// The caller must ensure `key` is equal to the key being replaced.
+    pub(crate) fn replace_key_at(&mut self, index: usize, key: K) -> K {
+        assert!(index < self.len, "index out of bounds");
+        // SAFETY: index < self.len, so this slot is initialized.
+        std::mem::replace(unsafe { self.keys[index].assume_init_mut() }, key)
     }
 
     /// Returns `true` if the map contains a value for the specified key.
@@ -251,12 +613,42 @@ impl<K: Hash + Eq, V, const N: usize> InlineMap<K, V, N> {
     /// This is used internally to avoid duplicate key scans when checking
     /// for key existence and then inserting.
     pub fn find_key_index(&self, key: &K) -> Option<usize> {
-        for i in 0..self.len {
-            if unsafe { self.keys[i].assume_init_ref() } == key {
-                return Some(i);
-            }
+        self.keys().position(|k| E::eq(k, key))
+    }
+
+    /// Returns references to the key and value stored at `index`, or `None` if
+    /// `index >= len`.
+    ///
+    /// Indices follow insertion order and match those returned by
+    /// `find_key_index`.
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        if index < self.len {
+            // SAFETY: index < self.len, so this slot is initialized.
+            Some(unsafe {
+                (
+                    self.keys[index].assume_init_ref(),
+                    self.values[index].assume_init_ref(),
+                )
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the key and a mutable reference to the value stored at
+    /// `index`, or `None` if `index >= len`.
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&K, &mut V)> {
+        if index < self.len {
+            // SAFETY: index < self.len, so this slot is initialized.
+            Some(unsafe {
+                (
+                    self.keys[index].assume_init_ref(),
+                    self.values[index].assume_init_mut(),
+                )
+            })
+        } else {
+            None
         }
-        None
     }
 
     /// Inserts a key-value pair using a pre-computed key index hint.
@@ -277,18 +669,18 @@ impl<K: Hash + Eq, V, const N: usize> InlineMap<K, V, N> {
         existing_index: Option<usize>,
     ) -> Option<V> {
         if let Some(i) = existing_index {
-            // Key exists at index i, update the value
-            let old_value = unsafe { std::ptr::read(self.values[i].as_ptr()) };
-            self.values[i] = MaybeUninit::new(value);
-            // Drop the old key and replace with new one (in case K has interior data)
-            unsafe { std::ptr::drop_in_place(self.keys[i].as_mut_ptr()) };
-            self.keys[i] = MaybeUninit::new(key);
+            // Key exists at index i: swap in the new key and value (in case K
+            // has interior data). The old key is dropped only once the slot
+            // is consistent again, so a panicking destructor can't leave it
+            // half-replaced.
+            // SAFETY: The hint was produced by find_key_index, so i < self.len.
+            let old_value = std::mem::replace(unsafe { self.values[i].assume_init_mut() }, value);
+            let old_key = std::mem::replace(unsafe { self.keys[i].assume_init_mut() }, key);
+            drop(old_key);
             Some(old_value)
         } else {
             // Key doesn't exist, add at the end
-            if self.len >= N {
-                panic!("InlineMap is full, cannot insert more than {} elements", N);
-            }
+            self.assert_has_room();
             self.keys[self.len] = MaybeUninit::new(key);
             self.values[self.len] = MaybeUninit::new(value);
             self.len += 1;
@@ -296,6 +688,76 @@ impl<K: Hash + Eq, V, const N: usize> InlineMap<K, V, N> {
         }
     }
 
+    /// Tries to insert a key-value pair into the map, and returns a mutable
+    /// reference to the value in the entry.
+    ///
+    /// If the map already had this key present, nothing is updated, and an
+    /// error containing the occupied entry and the value is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map is full and the key doesn't already exist.
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<&mut V, OccupiedError<'_, K, V, E>> {
+        let existing_index = self.find_key_index(&key);
+        self.try_insert_with_hint(key, value, existing_index)
+    }
+
+    /// Tries to insert a key-value pair using a pre-computed key index hint.
+    ///
+    /// If `existing_index` is `Some(i)`, returns an error referring to the
+    /// entry at index `i`. If `existing_index` is `None`, inserts the key-value
+    /// pair at the end.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map is full and `existing_index` is `None`.
+    pub fn try_insert_with_hint(
+        &mut self,
+        key: K,
+        value: V,
+        existing_index: Option<usize>,
+    ) -> Result<&mut V, OccupiedError<'_, K, V, E>> {
+        if let Some(i) = existing_index {
+            // SAFETY: The hint was produced by find_key_index, so i < self.len.
+            let existing_key = unsafe { self.keys[i].assume_init_ref() };
+            let existing_value = unsafe { self.values[i].assume_init_mut() };
+            return Err(OccupiedError::inline(existing_key, existing_value, value));
+        }
+
+        self.assert_has_room();
+        let i = self.len;
+        self.keys[i] = MaybeUninit::new(key);
+        self.values[i] = MaybeUninit::new(value);
+        self.len += 1;
+        // SAFETY: Slot i was just initialized.
+        Ok(unsafe { self.values[i].assume_init_mut() })
+    }
+
+    /// Inserts a key-value pair without checking whether the key already
+    /// exists, and returns a mutable reference to the value.
+    ///
+    /// The caller must guarantee that the key is not already present. This
+    /// is checked with a debug assertion; in release builds a duplicate is
+    /// stored as a second copy of the key, and lookups keep finding the
+    /// first one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map is full.
+    pub fn insert_unique_unchecked(&mut self, key: K, value: V) -> &mut V {
+        debug_assert!(
+            self.find_key_index(&key).is_none(),
+            "insert_unique_unchecked called with a key that is already present"
+        );
+        self.assert_has_room();
+        let i = self.len;
+        self.keys[i] = MaybeUninit::new(key);
+        self.values[i] = MaybeUninit::new(value);
+        self.len += 1;
+        // SAFETY: Slot i was just initialized.
+        unsafe { self.values[i].assume_init_mut() }
+    }
+
     /// Returns an iterator visiting all key-value pairs in insertion order.
     pub fn iter(&self) -> std::iter::Zip<std::slice::Iter<'_, K>, std::slice::Iter<'_, V>> {
         // SAFETY: We create slices from the initialized portion of our arrays.
@@ -353,38 +815,101 @@ impl<K: Hash + Eq, V, const N: usize> InlineMap<K, V, N> {
     /// Retains only the elements specified by the predicate.
     ///
     /// In other words, remove all pairs `(k, v)` for which `f(&k, &mut v)` returns `false`.
-    pub fn retain<F>(&mut self, mut f: F)
+    pub fn retain<F>(&mut self, f: F)
     where
         F: FnMut(&K, &mut V) -> bool,
     {
-        let mut i = 0;
-        while i < self.len {
-            // SAFETY: i < self.len, so these slots are initialized.
-            let key = unsafe { self.keys[i].assume_init_ref() };
-            let value = unsafe { self.values[i].assume_init_mut() };
+        self.retain_with(f, |key, value| {
+            drop(key);
+            drop(value);
+        });
+    }
 
-            if f(key, value) {
-                i += 1;
-            } else {
-                // Remove this element by shifting remaining elements left
-                // SAFETY: Drop the key and value at index i.
-                unsafe {
-                    std::ptr::drop_in_place(self.keys[i].as_mut_ptr());
-                    std::ptr::drop_in_place(self.values[i].as_mut_ptr());
-                }
+    /// Like `retain`, but moves each removed entry into `removed` instead of
+    /// dropping it.
+    ///
+    /// If `f` or `removed` panics, the entries not yet visited stay in the
+    /// map.
+    pub(crate) fn retain_with<F, G>(&mut self, mut f: F, mut removed: G)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+        G: FnMut(K, V),
+    {
+        /// Closes the gap left by removed entries and restores `len`, also
+        /// when the predicate or a destructor panics.
+        struct BackshiftOnDrop<'a, K, V, const N: usize, E> {
+            map: &'a mut InlineMap<K, V, N, E>,
+            processed: usize,
+            deleted: usize,
+            original_len: usize,
+        }
 
-                // Shift remaining elements left
-                for j in i..self.len - 1 {
-                    // SAFETY: j+1 < self.len, so slot j+1 is initialized.
-                    // We move the value from j+1 to j.
-                    self.keys[j] =
-                        MaybeUninit::new(unsafe { std::ptr::read(self.keys[j + 1].as_ptr()) });
-                    self.values[j] =
-                        MaybeUninit::new(unsafe { std::ptr::read(self.values[j + 1].as_ptr()) });
+        impl<K, V, const N: usize, E> Drop for BackshiftOnDrop<'_, K, V, N, E> {
+            fn drop(&mut self) {
+                if self.deleted > 0 {
+                    // SAFETY: Slots processed..original_len are initialized
+                    // and unvisited; the deleted slots before them are
+                    // vacant. ptr::copy handles the overlap.
+                    unsafe {
+                        let tail = self.original_len - self.processed;
+                        let to = self.processed - self.deleted;
+                        std::ptr::copy(
+                            self.map.keys.as_ptr().add(self.processed),
+                            self.map.keys.as_mut_ptr().add(to),
+                            tail,
+                        );
+                        std::ptr::copy(
+                            self.map.values.as_ptr().add(self.processed),
+                            self.map.values.as_mut_ptr().add(to),
+                            tail,
+                        );
+                    }
                 }
+                self.map.len = self.original_len - self.deleted;
+            }
+        }
 
-                self.len -= 1;
-                // Don't increment i; we need to check the element that was shifted into position i
+        let original_len = std::mem::replace(&mut self.len, 0);
+        let mut guard = BackshiftOnDrop {
+            map: self,
+            processed: 0,
+            deleted: 0,
+            original_len,
+        };
+
+        while guard.processed < original_len {
+            let i = guard.processed;
+            // SAFETY: i < original_len and slot i hasn't been moved yet.
+            let keep = unsafe {
+                f(
+                    guard.map.keys[i].assume_init_ref(),
+                    guard.map.values[i].assume_init_mut(),
+                )
+            };
+            guard.processed += 1;
+            if keep {
+                if guard.deleted > 0 {
+                    let to = i - guard.deleted;
+                    // SAFETY: Slot `to` is vacant and slot i is initialized.
+                    unsafe {
+                        let key = std::ptr::read(guard.map.keys[i].as_ptr());
+                        let value = std::ptr::read(guard.map.values[i].as_ptr());
+                        guard.map.keys[to] = MaybeUninit::new(key);
+                        guard.map.values[to] = MaybeUninit::new(value);
+                    }
+                }
+            } else {
+                guard.deleted += 1;
+                // SAFETY: Slot i is initialized; counting it as deleted
+                // before moving it out means the guard treats it as vacant
+                // even if `removed` or a destructor panics.
+                let (key, value) = unsafe {
+                    (
+                        std::ptr::read(guard.map.keys[i].as_ptr()),
+                        std::ptr::read(guard.map.values[i].as_ptr()),
+                    )
+                };
+                removed(key, value);
             }
         }
     }
@@ -421,3 +946,45 @@ impl<K: Hash + Eq, V, const N: usize> InlineMap<K, V, N> {
         result
     }
 }
+
+/// An owning iterator over the entries of an `InlineMap`, in insertion order.
+///
+/// Unlike `InlineMap::into_iter`, this moves entries straight out of the
+/// inline arrays without collecting them into a `Vec` first.
+pub(crate) struct IntoEntries<K, V, const N: usize, E> {
+    map: InlineMap<K, V, N, E>,
+    next: usize,
+    end: usize,
+}
+
+impl<K, V, const N: usize, E> Iterator for IntoEntries<K, V, N, E> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == self.end {
+            return None;
+        }
+        let i = self.next;
+        self.next += 1;
+        // SAFETY: Slots next..end are initialized and owned by the iterator.
+        // Advancing `next` first hands ownership of slot i to the caller.
+        let key = unsafe { std::ptr::read(self.map.keys[i].as_ptr()) };
+        let value = unsafe { std::ptr::read(self.map.values[i].as_ptr()) };
+        Some((key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.end - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<K, V, const N: usize, E> Drop for IntoEntries<K, V, N, E> {
+    fn drop(&mut self) {
+        // Drop the entries that were not yielded
+        let next = std::mem::replace(&mut self.next, self.end);
+        // SAFETY: Slots next..end are initialized and owned by the iterator;
+        // the map's len is 0.
+        unsafe { self.map.drop_range(next, self.end) };
+    }
+}
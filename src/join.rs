@@ -0,0 +1,178 @@
+use std::hash::BuildHasher;
+
+use super::equivalence::{DefaultEquivalence, Equivalence, KeyEquivalence};
+use super::pool::{NoPool, TablePolicy};
+use super::small_hash_map::{SmallHashMap, SmallHashMapIter};
+
+/// Iterator over the keys present in both of two maps.
+///
+/// Created by `SmallHashMap::inner_join`.
+pub struct SmallHashMapInnerJoin<
+    'a,
+    K,
+    V,
+    V2,
+    const N: usize,
+    const M: usize,
+    T,
+    E: Equivalence = DefaultEquivalence,
+    Q: TablePolicy<E::Stored<K>, V2, T> = NoPool,
+> {
+    iter: SmallHashMapIter<'a, K, V, N, E>,
+    other: &'a SmallHashMap<K, V2, M, T, E, Q>,
+}
+
+impl<'a, K, V, V2, const N: usize, const M: usize, T, E, Q>
+    SmallHashMapInnerJoin<'a, K, V, V2, N, M, T, E, Q>
+where
+    E: Equivalence,
+    Q: TablePolicy<E::Stored<K>, V2, T>,
+{
+    pub(crate) fn new(
+        iter: SmallHashMapIter<'a, K, V, N, E>,
+        other: &'a SmallHashMap<K, V2, M, T, E, Q>,
+    ) -> Self {
+        Self { iter, other }
+    }
+}
+
+impl<'a, K, V, V2, const N: usize, const M: usize, T, E, Q> Iterator
+    for SmallHashMapInnerJoin<'a, K, V, V2, N, M, T, E, Q>
+where
+    E: KeyEquivalence<K>,
+    T: BuildHasher,
+    Q: TablePolicy<E::Stored<K>, V2, T>,
+{
+    type Item = (&'a K, &'a V, &'a V2);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let other = self.other;
+        self.iter
+            .by_ref()
+            .find_map(|(k, v)| other.get(k).map(|v2| (k, v, v2)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+/// Iterator over every entry of the left map, paired with the matching
+/// value of the right map, if any.
+///
+/// Created by `SmallHashMap::left_join`.
+pub struct SmallHashMapLeftJoin<
+    'a,
+    K,
+    V,
+    V2,
+    const N: usize,
+    const M: usize,
+    T,
+    E: Equivalence = DefaultEquivalence,
+    Q: TablePolicy<E::Stored<K>, V2, T> = NoPool,
+> {
+    iter: SmallHashMapIter<'a, K, V, N, E>,
+    other: &'a SmallHashMap<K, V2, M, T, E, Q>,
+}
+
+impl<'a, K, V, V2, const N: usize, const M: usize, T, E, Q>
+    SmallHashMapLeftJoin<'a, K, V, V2, N, M, T, E, Q>
+where
+    E: Equivalence,
+    Q: TablePolicy<E::Stored<K>, V2, T>,
+{
+    pub(crate) fn new(
+        iter: SmallHashMapIter<'a, K, V, N, E>,
+        other: &'a SmallHashMap<K, V2, M, T, E, Q>,
+    ) -> Self {
+        Self { iter, other }
+    }
+}
+
+impl<'a, K, V, V2, const N: usize, const M: usize, T, E, Q> Iterator
+    for SmallHashMapLeftJoin<'a, K, V, V2, N, M, T, E, Q>
+where
+    E: KeyEquivalence<K>,
+    T: BuildHasher,
+    Q: TablePolicy<E::Stored<K>, V2, T>,
+{
+    type Item = (&'a K, &'a V, Option<&'a V2>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (k, v) = self.iter.next()?;
+        Some((k, v, self.other.get(k)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// Iterator over the keys present in either of two maps.
+///
+/// Created by `SmallHashMap::full_outer_join`.
+pub struct SmallHashMapFullOuterJoin<
+    'a,
+    K,
+    V,
+    V2,
+    const N: usize,
+    const M: usize,
+    S,
+    T,
+    E: Equivalence = DefaultEquivalence,
+    P: TablePolicy<E::Stored<K>, V, S> = NoPool,
+    Q: TablePolicy<E::Stored<K>, V2, T> = NoPool,
+> {
+    left: &'a SmallHashMap<K, V, N, S, E, P>,
+    right: &'a SmallHashMap<K, V2, M, T, E, Q>,
+    left_iter: SmallHashMapIter<'a, K, V, N, E>,
+    right_iter: SmallHashMapIter<'a, K, V2, M, E>,
+}
+
+impl<'a, K, V, V2, const N: usize, const M: usize, S, T, E, P, Q>
+    SmallHashMapFullOuterJoin<'a, K, V, V2, N, M, S, T, E, P, Q>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher,
+    T: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+    Q: TablePolicy<E::Stored<K>, V2, T>,
+{
+    pub(crate) fn new(
+        left: &'a SmallHashMap<K, V, N, S, E, P>,
+        right: &'a SmallHashMap<K, V2, M, T, E, Q>,
+    ) -> Self {
+        Self {
+            left,
+            right,
+            left_iter: left.iter(),
+            right_iter: right.iter(),
+        }
+    }
+}
+
+impl<'a, K, V, V2, const N: usize, const M: usize, S, T, E, P, Q> Iterator
+    for SmallHashMapFullOuterJoin<'a, K, V, V2, N, M, S, T, E, P, Q>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher,
+    T: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+    Q: TablePolicy<E::Stored<K>, V2, T>,
+{
+    type Item = (&'a K, Option<&'a V>, Option<&'a V2>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // First every left entry, then the right entries the left lacks
+        if let Some((k, v)) = self.left_iter.next() {
+            return Some((k, Some(v), self.right.get(k)));
+        }
+        let left = self.left;
+        self.right_iter
+            .by_ref()
+            .find(|(k, _)| !left.contains_key(k))
+            .map(|(k, v2)| (k, None, Some(v2)))
+    }
+}
@@ -32,18 +32,89 @@
 //! assert_eq!(map.get(&"b"), Some(&2));
 //! ```
 
+mod arena;
+mod arena_small_hash_map;
+mod arena_table;
+mod budget;
+mod cursor;
+mod equivalence;
+mod error;
 mod heap_map;
+mod hot_cache;
 mod inline_map;
+mod join;
 mod map;
+mod pool;
 mod small_hash_map;
+mod small_hash_set;
+mod sorted;
+mod thin_small_hash_map;
 
+pub use arena::{BumpArena, MapAllocator};
+pub use arena_small_hash_map::{
+    ArenaEntry, ArenaOccupiedEntry, ArenaSmallHashMap, ArenaSmallHashMapIntoIter,
+    ArenaSmallHashMapIter, ArenaSmallHashMapIterMut, ArenaVacantEntry,
+};
+pub use budget::{inline_capacity, InlineBudget};
+pub use cursor::SmallHashMapCursorMut;
+pub use equivalence::{
+    AsciiCaseInsensitive, CustomEquivalence, DefaultEquivalence, EquivKey, Equivalence,
+    KeyEquivalence, KeyOrdering, TrimWhitespace,
+};
+pub use error::{DuplicateKeyError, OccupiedError};
 pub use heap_map::HeapMap;
+pub use hot_cache::{CachedSmallHashMap, HotCache};
 pub use inline_map::InlineMap;
+pub use join::{SmallHashMapFullOuterJoin, SmallHashMapInnerJoin, SmallHashMapLeftJoin};
+pub use map::{Storage, StorageMut};
+pub use pool::{NoPool, PooledSmallHashMap, TablePolicy, TablePool, TablePoolStats};
 pub use small_hash_map::{
     SmallHashMap, SmallHashMapIntoIter, SmallHashMapIter, SmallHashMapIterMut, SmallHashMapKeys,
     SmallHashMapValues, SmallHashMapValuesMut,
 };
+pub use small_hash_set::{
+    SmallHashSet, SmallHashSetDifference, SmallHashSetIntersection, SmallHashSetIntoIter,
+    SmallHashSetIter, SmallHashSetSymmetricDifference, SmallHashSetUnion,
+};
+pub use sorted::{SmallHashMapSortedIter, SmallHashMapSortedKeys, SortedDebug};
+pub use thin_small_hash_map::ThinSmallHashMap;
 
 #[cfg(test)]
 #[path = "tests/small_hash_map_tests.rs"]
 mod tests;
+
+#[cfg(test)]
+#[path = "tests/arena_tests.rs"]
+mod arena_tests;
+
+#[cfg(test)]
+#[path = "tests/budget_tests.rs"]
+mod budget_tests;
+
+#[cfg(test)]
+#[path = "tests/equivalence_tests.rs"]
+mod equivalence_tests;
+
+#[cfg(test)]
+#[path = "tests/heap_map_tests.rs"]
+mod heap_map_tests;
+
+#[cfg(test)]
+#[path = "tests/hot_cache_tests.rs"]
+mod hot_cache_tests;
+
+#[cfg(test)]
+#[path = "tests/panic_safety_tests.rs"]
+mod panic_safety_tests;
+
+#[cfg(test)]
+#[path = "tests/pool_tests.rs"]
+mod pool_tests;
+
+#[cfg(test)]
+#[path = "tests/shared_map_tests.rs"]
+mod shared_map_tests;
+
+#[cfg(test)]
+#[path = "tests/small_hash_set_tests.rs"]
+mod small_hash_set_tests;
@@ -1,20 +1,139 @@
 use std::collections::hash_map::RandomState;
+use std::mem::{size_of, MaybeUninit};
 
+use super::equivalence::{DefaultEquivalence, Equivalence};
 use super::heap_map::HeapMap;
 use super::inline_map::InlineMap;
+use super::pool::{NoPool, TablePolicy};
 
 /// An enum dispatch type that can hold either an `InlineMap` or `HeapMap`.
 ///
 /// This allows for runtime polymorphism between different map implementations
 /// while maintaining zero-cost abstraction through manual match dispatch.
 ///
-/// The hasher type `S` is passed through to `HeapMap` when using heap storage.
-pub enum MapKind<K, V, const N: usize, S = RandomState> {
-    InlineMap(InlineMap<K, V, N>),
-    HeapMap(HeapMap<K, V, S>),
+/// The hasher type `S` is passed through to `HeapMap` when using heap storage,
+/// which holds keys in the stored form of the equivalence strategy `E`.
+///
+/// The enum keeps the default layout, so when the inline storage is smaller
+/// than a `HashMap` the tag hides in the table's pointer niche and costs no
+/// space.
+pub enum MapKind<
+    K,
+    V,
+    const N: usize,
+    S = RandomState,
+    E: Equivalence = DefaultEquivalence,
+    P: TablePolicy<E::Stored<K>, V, S> = NoPool,
+> {
+    InlineMap(InlineMap<K, V, N, E>),
+    HeapMap(HeapMap<E::Stored<K>, V, S, P>),
+}
+
+/// How many bytes of a `MapKind` outside the `InlineMap` payload
+/// `InlineLayout` can hold.
+const MAX_OUTER_BYTES: usize = 64;
+
+/// Where the `InlineMap` payload of an empty `MapKind::InlineMap` sits, and
+/// the bytes around it: the tag, or the niche value that stands in for it,
+/// plus any padding.
+struct InlineLayout {
+    offset: usize,
+    outer: [MaybeUninit<u8>; MAX_OUTER_BYTES],
 }
 
-impl<K: Clone, V: Clone, const N: usize, S: Clone> Clone for MapKind<K, V, N, S> {
+impl<K, V, const N: usize, S, E: Equivalence, P: TablePolicy<E::Stored<K>, V, S>>
+    MapKind<K, V, N, S, E, P>
+{
+    /// The layout of the empty inline variant, read off a value built at
+    /// compile time, or `None` if more than `MAX_OUTER_BYTES` lie outside
+    /// the payload. That only happens when the heap variant is much larger
+    /// than the inline one, so the whole enum is small.
+    const INLINE_LAYOUT: Option<InlineLayout> = {
+        let empty = MaybeUninit::new(MapKind::<K, V, N, S, E, P>::InlineMap(
+            InlineMap::const_new(),
+        ));
+        let base = empty.as_ptr();
+        // SAFETY: `empty` was just initialized.
+        let payload = match unsafe { &*base } {
+            MapKind::InlineMap(map) => map as *const InlineMap<K, V, N, E>,
+            MapKind::HeapMap(_) => unreachable!(),
+        };
+        // SAFETY: The payload lies within `empty`.
+        let offset = unsafe { payload.cast::<u8>().offset_from(base.cast::<u8>()) } as usize;
+        let end = offset + size_of::<InlineMap<K, V, N, E>>();
+        let suffix = size_of::<Self>() - end;
+
+        if offset + suffix > MAX_OUTER_BYTES {
+            None
+        } else {
+            let bytes = base.cast::<MaybeUninit<u8>>();
+            let mut outer = [MaybeUninit::uninit(); MAX_OUTER_BYTES];
+            let mut i = 0;
+            while i < offset {
+                // SAFETY: i < offset, which is within `empty`.
+                outer[i] = unsafe { *bytes.add(i) };
+                i += 1;
+            }
+            let mut i = 0;
+            while i < suffix {
+                // SAFETY: end + i < size_of::<Self>().
+                outer[offset + i] = unsafe { *bytes.add(end + i) };
+                i += 1;
+            }
+            Some(InlineLayout { offset, outer })
+        }
+    };
+
+    /// Writes an empty `MapKind::InlineMap` to `ptr` without building the
+    /// inline storage on the stack.
+    ///
+    /// The enum's layout isn't defined, so the bytes outside the payload are
+    /// copied from `INLINE_LAYOUT` and the payload is initialized in place
+    /// with `InlineMap::init_empty_in_place`. The result matches the
+    /// constant byte for byte: an `InlineMap` has no niche, so the tag never
+    /// lives inside the payload. When the layout couldn't be captured the
+    /// variant is written by value, which is cheap because the enum is small.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for writes and properly aligned.
+    pub(crate) unsafe fn init_inline_in_place(ptr: *mut Self) {
+        let Some(layout) = &Self::INLINE_LAYOUT else {
+            Self::write_inline_by_value(ptr);
+            return;
+        };
+        let bytes = ptr.cast::<MaybeUninit<u8>>();
+        let end = layout.offset + size_of::<InlineMap<K, V, N, E>>();
+        let suffix = size_of::<Self>() - end;
+        std::ptr::copy_nonoverlapping(layout.outer.as_ptr(), bytes, layout.offset);
+        std::ptr::copy_nonoverlapping(
+            layout.outer.as_ptr().add(layout.offset),
+            bytes.add(end),
+            suffix,
+        );
+        InlineMap::<K, V, N, E>::init_empty_in_place(bytes.add(layout.offset).cast());
+    }
+
+    /// The fallback for `init_inline_in_place`, kept out of line so that the
+    /// by-value temporary isn't part of the caller's stack frame.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for writes and properly aligned.
+    #[inline(never)]
+    unsafe fn write_inline_by_value(ptr: *mut Self) {
+        ptr.write(MapKind::InlineMap(InlineMap::new()));
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> Clone for MapKind<K, V, N, S, E, P>
+where
+    K: Clone,
+    V: Clone,
+    S: Clone,
+    E: Equivalence<Stored<K>: Clone>,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
     fn clone(&self) -> Self {
         match self {
             MapKind::InlineMap(m) => MapKind::InlineMap(m.clone()),
@@ -23,8 +142,12 @@ impl<K: Clone, V: Clone, const N: usize, S: Clone> Clone for MapKind<K, V, N, S>
     }
 }
 
-impl<K: std::fmt::Debug, V: std::fmt::Debug, const N: usize, S> std::fmt::Debug
-    for MapKind<K, V, N, S>
+impl<K, V, const N: usize, S, E, P> std::fmt::Debug for MapKind<K, V, N, S, E, P>
+where
+    K: std::fmt::Debug,
+    V: std::fmt::Debug,
+    E: Equivalence<Stored<K>: std::fmt::Debug>,
+    P: TablePolicy<E::Stored<K>, V, S>,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -33,3 +156,79 @@ impl<K: std::fmt::Debug, V: std::fmt::Debug, const N: usize, S> std::fmt::Debug
         }
     }
 }
+
+/// A read-only view of a `SmallHashMap`'s current storage, returned by
+/// `SmallHashMap::storage`.
+pub enum Storage<
+    'a,
+    K,
+    V,
+    const N: usize,
+    S = RandomState,
+    E: Equivalence = DefaultEquivalence,
+    P: TablePolicy<E::Stored<K>, V, S> = NoPool,
+> {
+    InlineMap(&'a InlineMap<K, V, N, E>),
+    HeapMap(&'a HeapMap<E::Stored<K>, V, S, P>),
+}
+
+impl<K, V, const N: usize, S, E: Equivalence, P: TablePolicy<E::Stored<K>, V, S>> Clone
+    for Storage<'_, K, V, N, S, E, P>
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V, const N: usize, S, E: Equivalence, P: TablePolicy<E::Stored<K>, V, S>> Copy
+    for Storage<'_, K, V, N, S, E, P>
+{
+}
+
+impl<K, V, const N: usize, S, E, P> std::fmt::Debug for Storage<'_, K, V, N, S, E, P>
+where
+    K: std::fmt::Debug,
+    V: std::fmt::Debug,
+    E: Equivalence<Stored<K>: std::fmt::Debug>,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Storage::InlineMap(m) => std::fmt::Debug::fmt(m, f),
+            Storage::HeapMap(m) => std::fmt::Debug::fmt(m, f),
+        }
+    }
+}
+
+/// A mutable view of a `SmallHashMap`'s current storage, returned by
+/// `SmallHashMap::storage_mut`.
+///
+/// The storage mode itself can't be changed through this view: an inline map
+/// that fills up panics on insert instead of transitioning.
+pub enum StorageMut<
+    'a,
+    K,
+    V,
+    const N: usize,
+    S = RandomState,
+    E: Equivalence = DefaultEquivalence,
+    P: TablePolicy<E::Stored<K>, V, S> = NoPool,
+> {
+    InlineMap(&'a mut InlineMap<K, V, N, E>),
+    HeapMap(&'a mut HeapMap<E::Stored<K>, V, S, P>),
+}
+
+impl<K, V, const N: usize, S, E, P> std::fmt::Debug for StorageMut<'_, K, V, N, S, E, P>
+where
+    K: std::fmt::Debug,
+    V: std::fmt::Debug,
+    E: Equivalence<Stored<K>: std::fmt::Debug>,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageMut::InlineMap(m) => std::fmt::Debug::fmt(m, f),
+            StorageMut::HeapMap(m) => std::fmt::Debug::fmt(m, f),
+        }
+    }
+}
@@ -0,0 +1,409 @@
+use std::any::{Any, TypeId};
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::hash_map;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::vec;
+
+use super::equivalence::DefaultEquivalence;
+use super::small_hash_map::SmallHashMap;
+
+pub(crate) mod sealed {
+    pub trait Sealed {}
+}
+
+/// Decides how a [`HeapMap`](crate::HeapMap) holds its table: where the
+/// table comes from, where it goes when the map is done with it, and what
+/// lookups consult before it.
+///
+/// `HeapMap` and `SmallHashMap` take the policy as their last type
+/// parameter. [`NoPool`], the default, allocates every table and frees it on
+/// drop. [`TablePool`] recycles tables through a thread-local pool, and
+/// [`HotCache`](crate::HotCache) caches hot entries next to the table. The
+/// trait is sealed; these are the only policies.
+pub trait TablePolicy<K, V, S>: sealed::Sealed {
+    /// How the table is held inside a `HeapMap`.
+    type Table: DerefMut<Target = HashMap<K, V, S>>;
+
+    /// The iterator returned when a `HeapMap` is consumed.
+    type IntoIter: Iterator<Item = (K, V)>;
+
+    /// Returns an empty table with room for `capacity` elements that hashes
+    /// with `hash_builder`.
+    fn new_table(capacity: usize, hash_builder: S) -> Self::Table;
+
+    /// Takes ownership of an existing table.
+    fn wrap(map: HashMap<K, V, S>) -> Self::Table;
+
+    /// Hands the table back to the caller; it is no longer managed by the
+    /// policy.
+    fn into_map(table: Self::Table) -> HashMap<K, V, S>;
+
+    /// Consumes the table, yielding its entries.
+    fn into_iter(table: Self::Table) -> Self::IntoIter;
+
+    /// Consumes the table like `into_iter`, as a type shared by all
+    /// policies.
+    fn into_entries(table: Self::Table) -> TableEntries<K, V>;
+
+    /// Looks up `key`, by default in the table alone.
+    fn get_key_value<'a, Q>(table: &'a Self::Table, key: &Q) -> Option<(&'a K, &'a V)>
+    where
+        K: Borrow<Q> + Hash + Eq,
+        Q: Hash + Eq + ?Sized,
+        S: BuildHasher + 'a,
+    {
+        table.get_key_value(key)
+    }
+}
+
+/// The entries of a consumed table: either the table's own iterator or,
+/// when the table went back to a pool, a buffer they were drained into.
+pub enum TableEntries<K, V> {
+    Table(hash_map::IntoIter<K, V>),
+    Drained(vec::IntoIter<(K, V)>),
+}
+
+impl<K, V> Iterator for TableEntries<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            TableEntries::Table(iter) => iter.next(),
+            TableEntries::Drained(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            TableEntries::Table(iter) => iter.size_hint(),
+            TableEntries::Drained(iter) => iter.size_hint(),
+        }
+    }
+}
+
+/// The default [`TablePolicy`]: every table is allocated when the map
+/// spills and freed when it's dropped.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoPool;
+
+/// A table owned by a `HeapMap` under [`NoPool`].
+///
+/// It has no destructor of its own, so a map of borrowed keys may outlive
+/// the data its keys borrow up to the point where it's dropped, as with
+/// `HashMap`.
+#[derive(Debug)]
+pub struct PlainTable<K, V, S>(HashMap<K, V, S>);
+
+impl<K, V, S> Deref for PlainTable<K, V, S> {
+    type Target = HashMap<K, V, S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<K, V, S> DerefMut for PlainTable<K, V, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl sealed::Sealed for NoPool {}
+
+impl<K, V, S> TablePolicy<K, V, S> for NoPool {
+    type Table = PlainTable<K, V, S>;
+    type IntoIter = hash_map::IntoIter<K, V>;
+
+    fn new_table(capacity: usize, hash_builder: S) -> Self::Table {
+        PlainTable(HashMap::with_capacity_and_hasher(capacity, hash_builder))
+    }
+
+    fn wrap(map: HashMap<K, V, S>) -> Self::Table {
+        PlainTable(map)
+    }
+
+    fn into_map(table: Self::Table) -> HashMap<K, V, S> {
+        table.0
+    }
+
+    fn into_iter(table: Self::Table) -> Self::IntoIter {
+        table.0.into_iter()
+    }
+
+    fn into_entries(table: Self::Table) -> TableEntries<K, V> {
+        TableEntries::Table(table.0.into_iter())
+    }
+}
+
+/// A [`TablePolicy`] that recycles heap tables through a thread-local pool.
+///
+/// A map using this policy takes a cleared table from the pool when it
+/// spills, instead of allocating one, and hands its table back, cleared,
+/// when it's dropped or consumed by `into_iter`. Tables are kept per key,
+/// value and hasher type, in buckets by capacity (one bucket per power of
+/// two), with at most [`max_per_bucket`](Self::max_per_bucket) tables in
+/// each bucket.
+///
+/// A pooled table is only reused by a map whose hasher compares equal to
+/// the table's own, so a map never ends up hashing with another map's
+/// seed. This is why the hasher must implement `PartialEq`: stateless
+/// hashers such as `BuildHasherDefault` always match, while a seeded hasher
+/// only matches maps built with the same seed. `K`, `V` and `S` must be
+/// `'static`, since tables are pooled by type.
+///
+/// # Example
+///
+/// ```
+/// use small_hash_map::{PooledSmallHashMap, TablePool};
+/// use std::collections::hash_map::DefaultHasher;
+/// use std::hash::BuildHasherDefault;
+///
+/// type Hasher = BuildHasherDefault<DefaultHasher>;
+///
+/// for request in 0..10 {
+///     let mut map: PooledSmallHashMap<u32, u32, 4, Hasher> = PooledSmallHashMap::new();
+///     map.extend((0..8).map(|i| (i, request)));
+/// }
+/// let stats = TablePool::stats();
+/// assert_eq!(stats.misses, 1);
+/// assert_eq!(stats.hits, 9);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TablePool;
+
+/// A `SmallHashMap` whose heap table is recycled through the [`TablePool`].
+pub type PooledSmallHashMap<K, V, const N: usize, S, E = DefaultEquivalence> =
+    SmallHashMap<K, V, N, S, E, TablePool>;
+
+/// A table owned by a `HeapMap` under [`TablePool`]. Dropping it returns
+/// the table to the pool.
+#[derive(Debug)]
+pub struct PooledTable<K: 'static, V: 'static, S: 'static> {
+    map: ManuallyDrop<HashMap<K, V, S>>,
+}
+
+impl<K, V, S> PooledTable<K, V, S> {
+    /// Moves the table out without returning it to the pool.
+    fn into_map(self) -> HashMap<K, V, S> {
+        let mut this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never dropped, so the table is taken once.
+        unsafe { ManuallyDrop::take(&mut this.map) }
+    }
+}
+
+impl<K, V, S> Deref for PooledTable<K, V, S> {
+    type Target = HashMap<K, V, S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<K, V, S> DerefMut for PooledTable<K, V, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.map
+    }
+}
+
+impl<K: 'static, V: 'static, S: 'static> Drop for PooledTable<K, V, S> {
+    fn drop(&mut self) {
+        // SAFETY: The table is taken here, in the destructor, and never used
+        // again.
+        recycle(unsafe { ManuallyDrop::take(&mut self.map) });
+    }
+}
+
+impl sealed::Sealed for TablePool {}
+
+impl<K: 'static, V: 'static, S: PartialEq + 'static> TablePolicy<K, V, S> for TablePool {
+    type Table = PooledTable<K, V, S>;
+    type IntoIter = vec::IntoIter<(K, V)>;
+
+    fn new_table(capacity: usize, hash_builder: S) -> Self::Table {
+        let map = match take(capacity, &hash_builder) {
+            Some(map) => map,
+            None => HashMap::with_capacity_and_hasher(capacity, hash_builder),
+        };
+        Self::wrap(map)
+    }
+
+    fn wrap(map: HashMap<K, V, S>) -> Self::Table {
+        PooledTable {
+            map: ManuallyDrop::new(map),
+        }
+    }
+
+    fn into_map(table: Self::Table) -> HashMap<K, V, S> {
+        table.into_map()
+    }
+
+    /// Moves the entries into a `Vec` so the table can go back to the pool.
+    fn into_iter(mut table: Self::Table) -> Self::IntoIter {
+        let entries: Vec<_> = table.drain().collect();
+        drop(table);
+        entries.into_iter()
+    }
+
+    fn into_entries(table: Self::Table) -> TableEntries<K, V> {
+        TableEntries::Drained(Self::into_iter(table))
+    }
+}
+
+/// The per-thread state behind [`TablePool`].
+struct Pools {
+    buckets: HashMap<(TypeId, u32), Box<dyn Any>>,
+    max_per_bucket: usize,
+    stats: TablePoolStats,
+}
+
+/// Counters for the current thread's [`TablePool`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TablePoolStats {
+    /// Spills that reused a pooled table.
+    pub hits: u64,
+    /// Spills that found no suitable table and allocated a new one.
+    pub misses: u64,
+    /// Tables returned to the pool.
+    pub returned: u64,
+    /// Tables freed because their bucket was full.
+    pub discarded: u64,
+}
+
+thread_local! {
+    static POOL: RefCell<Pools> = RefCell::new(Pools {
+        buckets: HashMap::new(),
+        max_per_bucket: TablePool::DEFAULT_MAX_PER_BUCKET,
+        stats: TablePoolStats::default(),
+    });
+}
+
+/// Returns the bucket holding tables of `capacity`, rounded down to a
+/// power of two.
+fn bucket_of(capacity: usize) -> u32 {
+    usize::BITS - 1 - capacity.max(1).leading_zeros()
+}
+
+/// Removes a pooled table with room for `min_capacity` elements and a
+/// hasher equal to `hash_builder`, if this thread has one. Requests for no
+/// capacity don't allocate, so they never use the pool.
+fn take<K: 'static, V: 'static, S: PartialEq + 'static>(
+    min_capacity: usize,
+    hash_builder: &S,
+) -> Option<HashMap<K, V, S>> {
+    if min_capacity == 0 {
+        return None;
+    }
+    POOL.try_with(|pool| pool.borrow_mut().take(min_capacity, hash_builder))
+        .ok()
+        .flatten()
+}
+
+/// Clears `table` and pools it, or frees it if its bucket is full.
+fn recycle<K: 'static, V: 'static, S: 'static>(mut table: HashMap<K, V, S>) {
+    if table.capacity() == 0 {
+        return;
+    }
+    // Entries are dropped before the pool is borrowed, so a destructor that
+    // drops another pooled map doesn't find the pool already borrowed.
+    table.clear();
+    let rejected = POOL
+        .try_with(|pool| pool.borrow_mut().put(table))
+        .unwrap_or(None);
+    drop(rejected);
+}
+
+impl TablePool {
+    /// The default number of tables kept per bucket.
+    pub const DEFAULT_MAX_PER_BUCKET: usize = 8;
+
+    /// Returns this thread's pool statistics.
+    pub fn stats() -> TablePoolStats {
+        POOL.with(|pool| pool.borrow().stats)
+    }
+
+    /// Resets this thread's pool statistics to zero.
+    pub fn reset_stats() {
+        POOL.with(|pool| pool.borrow_mut().stats = TablePoolStats::default());
+    }
+
+    /// Returns the most tables this thread keeps in each bucket.
+    pub fn max_per_bucket() -> usize {
+        POOL.with(|pool| pool.borrow().max_per_bucket)
+    }
+
+    /// Sets the most tables this thread keeps in each bucket. Setting 0
+    /// disables pooling; tables already pooled are kept until taken or
+    /// [`clear`](Self::clear)ed.
+    pub fn set_max_per_bucket(max: usize) {
+        POOL.with(|pool| pool.borrow_mut().max_per_bucket = max);
+    }
+
+    /// Frees every table pooled on this thread.
+    pub fn clear() {
+        let buckets = POOL.with(|pool| std::mem::take(&mut pool.borrow_mut().buckets));
+        drop(buckets);
+    }
+}
+
+impl Pools {
+    /// Removes a table with room for `min_capacity` elements whose hasher
+    /// equals `hash_builder`, if one is pooled.
+    fn take<K: 'static, V: 'static, S: PartialEq + 'static>(
+        &mut self,
+        min_capacity: usize,
+        hash_builder: &S,
+    ) -> Option<HashMap<K, V, S>> {
+        let first = bucket_of(min_capacity);
+        // The first bucket may hold tables just below `min_capacity`, so the
+        // next two are searched as well; a table found there has less than
+        // eight times the capacity needed.
+        for bucket in first..first + 3 {
+            let tables = self
+                .buckets
+                .get_mut(&(TypeId::of::<HashMap<K, V, S>>(), bucket))
+                .and_then(|tables| tables.downcast_mut::<Vec<HashMap<K, V, S>>>());
+            let Some(tables) = tables else { continue };
+            let found = tables
+                .iter()
+                .position(|t| t.capacity() >= min_capacity && t.hasher() == hash_builder);
+            if let Some(index) = found {
+                self.stats.hits += 1;
+                return Some(tables.swap_remove(index));
+            }
+        }
+        self.stats.misses += 1;
+        None
+    }
+
+    /// Pools `table`, or hands it back if its bucket is full.
+    fn put<K: 'static, V: 'static, S: 'static>(
+        &mut self,
+        table: HashMap<K, V, S>,
+    ) -> Option<HashMap<K, V, S>> {
+        let max = self.max_per_bucket;
+        if max == 0 {
+            self.stats.discarded += 1;
+            return Some(table);
+        }
+        let tables = self
+            .buckets
+            .entry((
+                TypeId::of::<HashMap<K, V, S>>(),
+                bucket_of(table.capacity()),
+            ))
+            .or_insert_with(|| Box::new(Vec::<HashMap<K, V, S>>::with_capacity(max)))
+            .downcast_mut::<Vec<HashMap<K, V, S>>>()
+            .expect("pool bucket holds tables of its own type");
+        if tables.len() >= max {
+            self.stats.discarded += 1;
+            return Some(table);
+        }
+        tables.push(table);
+        self.stats.returned += 1;
+        None
+    }
+}
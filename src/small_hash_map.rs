@@ -1,12 +1,22 @@
+use std::cmp::Ordering;
 use std::collections::hash_map;
-use std::collections::hash_map::RandomState;
+use std::collections::hash_map::{Entry, RandomState};
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use std::hash::{BuildHasher, Hash};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::marker::PhantomData;
 use std::slice;
 
-use super::heap_map::HeapMap;
-use super::inline_map::InlineMap;
-use super::map::MapKind;
+use super::budget::StackBudget;
+use super::cursor::SmallHashMapCursorMut;
+use super::equivalence::{DefaultEquivalence, Equivalence, KeyEquivalence, KeyOrdering};
+use super::error::{DuplicateKeyError, OccupiedError};
+use super::heap_map::{alter_occupied, HeapMap};
+use super::inline_map::{InlineMap, IntoEntries};
+use super::join::{SmallHashMapFullOuterJoin, SmallHashMapInnerJoin, SmallHashMapLeftJoin};
+use super::map::{MapKind, Storage, StorageMut};
+use super::pool::{NoPool, TableEntries, TablePolicy};
+use super::sorted::{SmallHashMapSortedIter, SmallHashMapSortedKeys, SortedDebug};
 
 /// An adaptive map that starts with an `InlineMap` and transitions to
 /// `HeapMap` when it grows beyond a threshold.
@@ -21,6 +31,12 @@ use super::map::MapKind;
 /// - `V`: The value type
 /// - `N`: The inline capacity (stack-allocated storage size)
 /// - `S`: The hasher type, defaults to `RandomState` (same as `std::collections::HashMap`)
+/// - `E`: The [`KeyEquivalence`] strategy used to hash and compare keys in
+///   both storage modes, defaults to the keys' own `Hash` and `Eq`
+/// - `P`: The [`TablePolicy`] for the heap table, defaults to [`NoPool`];
+///   [`TablePool`](crate::TablePool) recycles tables through a thread-local
+///   pool, and [`HotCache`](crate::HotCache) caches hot entries in the
+///   inline space after a spill
 ///
 /// # Transition Threshold
 /// The map transitions from `InlineMap` to `HeapMap` when it exceeds the `N`
@@ -42,13 +58,27 @@ use super::map::MapKind;
 /// let map2: SmallHashMap<String, i32, 8, RandomState> =
 ///     SmallHashMap::with_hasher(RandomState::new());
 /// ```
-pub struct SmallHashMap<K, V, const N: usize, S = RandomState> {
-    inner: MapKind<K, V, N, S>,
+pub struct SmallHashMap<
+    K,
+    V,
+    const N: usize,
+    S = RandomState,
+    E: Equivalence = DefaultEquivalence,
+    P: TablePolicy<E::Stored<K>, V, S> = NoPool,
+> {
+    inner: MapKind<K, V, N, S, E, P>,
     transition_threshold: usize,
     hash_builder: S,
 }
 
-impl<K: Clone, V: Clone, const N: usize, S: Clone> Clone for SmallHashMap<K, V, N, S> {
+impl<K, V, const N: usize, S, E, P> Clone for SmallHashMap<K, V, N, S, E, P>
+where
+    K: Clone,
+    V: Clone,
+    S: Clone,
+    E: Equivalence<Stored<K>: Clone>,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
@@ -58,7 +88,13 @@ impl<K: Clone, V: Clone, const N: usize, S: Clone> Clone for SmallHashMap<K, V,
     }
 }
 
-impl<K: fmt::Debug, V: fmt::Debug, const N: usize, S> fmt::Debug for SmallHashMap<K, V, N, S> {
+impl<K, V, const N: usize, S, E, P> fmt::Debug for SmallHashMap<K, V, N, S, E, P>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+    E: Equivalence<Stored<K>: fmt::Debug>,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("SmallHashMap")
             .field("inner", &self.inner)
@@ -67,10 +103,11 @@ impl<K: fmt::Debug, V: fmt::Debug, const N: usize, S> fmt::Debug for SmallHashMa
     }
 }
 
-impl<K, V, const N: usize, S> SmallHashMap<K, V, N, S>
+impl<K, V, const N: usize, S, E, P> SmallHashMap<K, V, N, S, E, P>
 where
-    K: Hash + Eq,
+    E: KeyEquivalence<K>,
     S: BuildHasher + Default,
+    P: TablePolicy<E::Stored<K>, V, S>,
 {
     /// Creates a new `SmallHashMap` that starts with an `InlineMap`.
     ///
@@ -89,12 +126,71 @@ where
     pub fn new() -> Self {
         Self::with_hasher(S::default())
     }
+
+    /// Creates a new `SmallHashMap`, failing to compile if it takes more than
+    /// `BYTES` bytes.
+    ///
+    /// This guards a map created on the stack against growing unnoticed when
+    /// `N`, `K` or `V` change. The check is evaluated when the call is
+    /// compiled for concrete types, so it fires at the offending
+    /// instantiation. [`new_boxed`](Self::new_boxed) has no such limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let map: SmallHashMap<u32, u32, 8> = SmallHashMap::new_within_budget::<256>();
+    /// assert!(map.is_inline());
+    /// ```
+    ///
+    /// ```compile_fail
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let map: SmallHashMap<u64, [u8; 256], 64> = SmallHashMap::new_within_budget::<4096>();
+    /// ```
+    pub fn new_within_budget<const BYTES: usize>() -> Self {
+        let () = StackBudget::<Self, BYTES>::CHECK;
+        Self::new()
+    }
+
+    /// Creates a new `SmallHashMap` directly on the heap.
+    ///
+    /// The inline storage is never built on the stack, so this works for any
+    /// `N`. Lookups, insertion and removal don't create inline maps on the
+    /// stack either; operations that return a new map by value (such as
+    /// `clone` or `map_values`) still do.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut map: Box<SmallHashMap<u64, [u8; 1024], 128>> = SmallHashMap::new_boxed();
+    /// map.insert(1, [0; 1024]);
+    /// assert!(map.is_inline());
+    /// assert_eq!(map.capacity(), 128);
+    /// ```
+    pub fn new_boxed() -> Box<Self> {
+        let hash_builder = S::default();
+        let mut boxed = Box::<Self>::new_uninit();
+        let ptr = boxed.as_mut_ptr();
+        // SAFETY: Every field is written before `assume_init`; `inner` is
+        // initialized in place as an empty inline map.
+        unsafe {
+            MapKind::init_inline_in_place(std::ptr::addr_of_mut!((*ptr).inner));
+            std::ptr::addr_of_mut!((*ptr).transition_threshold).write(N);
+            std::ptr::addr_of_mut!((*ptr).hash_builder).write(hash_builder);
+            boxed.assume_init()
+        }
+    }
 }
 
-impl<K, V, const N: usize, S> SmallHashMap<K, V, N, S>
+impl<K, V, const N: usize, S, E, P> SmallHashMap<K, V, N, S, E, P>
 where
-    K: Hash + Eq,
+    E: KeyEquivalence<K>,
     S: BuildHasher + Default + Clone,
+    P: TablePolicy<E::Stored<K>, V, S>,
 {
     /// Creates a new `SmallHashMap` with the specified capacity hint.
     ///
@@ -103,12 +199,131 @@ where
     pub fn with_capacity(capacity: usize) -> Self {
         Self::with_capacity_and_hasher(capacity, S::default())
     }
+
+    /// Creates a `SmallHashMap` from an iterator, failing on the first
+    /// duplicate key.
+    ///
+    /// Unlike `collect`, which silently keeps the last value for a repeated
+    /// key, this returns a `DuplicateKeyError` holding the repeated key. Each
+    /// element costs one inline scan or one hash, and the map ends in inline
+    /// mode whenever its final length fits in `N`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let map: SmallHashMap<&str, i32, 4> =
+    ///     SmallHashMap::try_from_iter([("a", 1), ("b", 2)]).unwrap();
+    /// assert_eq!(map.len(), 2);
+    ///
+    /// let err = SmallHashMap::<&str, i32, 4>::try_from_iter([("a", 1), ("a", 2)]).unwrap_err();
+    /// assert_eq!(err.key(), &"a");
+    /// ```
+    pub fn try_from_iter<I>(iter: I) -> Result<Self, DuplicateKeyError<K>>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let iter = iter.into_iter();
+        let mut map = Self::for_size_hint(iter.size_hint());
+
+        for (key, value) in iter {
+            // The map is discarded on a duplicate, so the stored copy of the
+            // key is taken out of it; this keeps the lookup to a single probe
+            // in heap mode, where the rejected key isn't handed back.
+            let duplicate = match map.slot_of(&key) {
+                Slot::Inline(i) => match &mut map.inner {
+                    MapKind::InlineMap(inline_map) => inline_map.remove_index(i).0,
+                    MapKind::HeapMap(_) => unreachable!("slot_of reported inline storage"),
+                },
+                Slot::InlineVacant => {
+                    map.insert_absent(key, value);
+                    continue;
+                }
+                Slot::Heap => match &mut map.inner {
+                    MapKind::HeapMap(heap_map) => match heap_map.entry(E::wrap(key)) {
+                        Entry::Occupied(entry) => E::unwrap(entry.remove_entry().0),
+                        Entry::Vacant(entry) => {
+                            entry.insert(value);
+                            continue;
+                        }
+                    },
+                    MapKind::InlineMap(_) => unreachable!("slot_of reported heap storage"),
+                },
+            };
+            return Err(DuplicateKeyError::new(duplicate));
+        }
+
+        map.shrink_to_inline_if_fits();
+        Ok(map)
+    }
+
+    /// Creates a `SmallHashMap` from an iterator, resolving duplicate keys
+    /// with `merge`.
+    ///
+    /// The first value seen for a key is stored; each later value for the
+    /// same key is passed to `merge(key, existing, incoming)`. The map ends
+    /// in inline mode whenever its final length fits in `N`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let map: SmallHashMap<&str, i32, 4> = SmallHashMap::from_iter_with(
+    ///     [("a", 1), ("b", 2), ("a", 10)],
+    ///     |_key, existing, incoming| *existing += incoming,
+    /// );
+    /// assert_eq!(map.get(&"a"), Some(&11));
+    /// assert_eq!(map.get(&"b"), Some(&2));
+    /// ```
+    pub fn from_iter_with<I, F>(iter: I, mut merge: F) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        F: FnMut(&K, &mut V, V),
+    {
+        let iter = iter.into_iter();
+        let mut map = Self::for_size_hint(iter.size_hint());
+
+        for (key, value) in iter {
+            let existing = match &mut map.inner {
+                MapKind::InlineMap(inline_map) => inline_map
+                    .find_key_index(&key)
+                    .and_then(|i| inline_map.get_index_mut(i))
+                    .map(|(_, existing)| existing),
+                MapKind::HeapMap(heap_map) => heap_map.get_mut(E::wrap_ref(&key)),
+            };
+            match existing {
+                Some(existing) => merge(&key, existing, value),
+                None => {
+                    map.insert_absent(key, value);
+                }
+            }
+        }
+
+        map.shrink_to_inline_if_fits();
+        map
+    }
+
+    /// Creates an empty map sized for an iterator's `size_hint`.
+    ///
+    /// Uses heap storage up front when the hint exceeds `N`, so that a large
+    /// stream doesn't transition partway through.
+    fn for_size_hint((lower, upper): (usize, Option<usize>)) -> Self {
+        let capacity_hint = upper.unwrap_or(lower);
+        if capacity_hint > N {
+            Self::with_capacity(capacity_hint)
+        } else {
+            Self::new()
+        }
+    }
 }
 
-impl<K, V, const N: usize, S> SmallHashMap<K, V, N, S>
+impl<K, V, const N: usize, S, E, P> SmallHashMap<K, V, N, S, E, P>
 where
-    K: Hash + Eq,
+    E: KeyEquivalence<K>,
     S: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
 {
     /// Creates a new `SmallHashMap` with the specified hasher.
     ///
@@ -239,6 +454,100 @@ where
         matches!(&self.inner, MapKind::InlineMap(_))
     }
 
+    /// Returns a view of the current storage for matching on its concrete
+    /// representation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::{SmallHashMap, Storage};
+    ///
+    /// let map: SmallHashMap<u32, u32, 4> = [(1, 10), (2, 20)].into();
+    /// match map.storage() {
+    ///     Storage::InlineMap(inline) => assert_eq!(inline.len(), 2),
+    ///     Storage::HeapMap(_) => unreachable!(),
+    /// }
+    /// ```
+    pub fn storage(&self) -> Storage<'_, K, V, N, S, E, P> {
+        match &self.inner {
+            MapKind::InlineMap(map) => Storage::InlineMap(map),
+            MapKind::HeapMap(map) => Storage::HeapMap(map),
+        }
+    }
+
+    /// Returns a mutable view of the current storage.
+    ///
+    /// Changes made through the view can't switch the storage mode; use the
+    /// map's own methods when an insert may need to transition to the heap.
+    pub fn storage_mut(&mut self) -> StorageMut<'_, K, V, N, S, E, P> {
+        match &mut self.inner {
+            MapKind::InlineMap(map) => StorageMut::InlineMap(map),
+            MapKind::HeapMap(map) => StorageMut::HeapMap(map),
+        }
+    }
+
+    /// Returns the inline storage, or `None` if the map is on the heap.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let map: SmallHashMap<u32, u32, 4> = [(1, 10), (2, 20)].into();
+    /// let keys: Vec<u32> = map.as_inline().unwrap().keys().copied().collect();
+    /// assert_eq!(keys, [1, 2]);
+    /// assert!(map.as_heap().is_none());
+    /// ```
+    pub fn as_inline(&self) -> Option<&InlineMap<K, V, N, E>> {
+        match &self.inner {
+            MapKind::InlineMap(map) => Some(map),
+            MapKind::HeapMap(_) => None,
+        }
+    }
+
+    /// Returns the keys and values as two slices in inline mode, or `None`
+    /// if the map is on the heap. See [`InlineMap::as_slices`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let map: SmallHashMap<u8, f64, 4> = [(1, 0.5), (2, 1.5)].into();
+    /// let (_, values) = map.inline_slices().unwrap();
+    /// assert_eq!(values.iter().sum::<f64>(), 2.0);
+    /// ```
+    pub fn inline_slices(&self) -> Option<(&[K], &[V])> {
+        self.as_inline().map(InlineMap::as_slices)
+    }
+
+    /// Returns the heap storage, or `None` if the map is inline.
+    pub fn as_heap(&self) -> Option<&HeapMap<E::Stored<K>, V, S, P>> {
+        match &self.inner {
+            MapKind::InlineMap(_) => None,
+            MapKind::HeapMap(map) => Some(map),
+        }
+    }
+
+    /// Returns the inline storage mutably, or `None` if the map is on the heap.
+    ///
+    /// Inserting a new key into a full `InlineMap` panics; it doesn't
+    /// transition the map to the heap.
+    pub fn as_inline_mut(&mut self) -> Option<&mut InlineMap<K, V, N, E>> {
+        match &mut self.inner {
+            MapKind::InlineMap(map) => Some(map),
+            MapKind::HeapMap(_) => None,
+        }
+    }
+
+    /// Returns the heap storage mutably, or `None` if the map is inline.
+    pub fn as_heap_mut(&mut self) -> Option<&mut HeapMap<E::Stored<K>, V, S, P>> {
+        match &mut self.inner {
+            MapKind::InlineMap(_) => None,
+            MapKind::HeapMap(map) => Some(map),
+        }
+    }
+
     /// Clears the map, removing all key-value pairs.
     pub fn clear(&mut self) {
         match &mut self.inner {
@@ -263,7 +572,7 @@ where
     pub fn get(&self, key: &K) -> Option<&V> {
         match &self.inner {
             MapKind::InlineMap(map) => map.get(key),
-            MapKind::HeapMap(map) => map.get(key),
+            MapKind::HeapMap(map) => map.get(E::wrap_ref(key)),
         }
     }
 
@@ -271,7 +580,45 @@ where
     pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
         match &mut self.inner {
             MapKind::InlineMap(map) => map.get_mut(key),
-            MapKind::HeapMap(map) => map.get_mut(key),
+            MapKind::HeapMap(map) => map.get_mut(E::wrap_ref(key)),
+        }
+    }
+
+    /// Calls `f` on the value for `key`, if present, and returns whether it
+    /// was.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut map: SmallHashMap<&str, i32, 4> = [("a", 1)].into();
+    /// assert!(map.update(&"a", |value| *value += 1));
+    /// assert!(!map.update(&"b", |value| *value += 1));
+    /// assert_eq!(map.get(&"a"), Some(&2));
+    /// ```
+    pub fn update<F>(&mut self, key: &K, f: F) -> bool
+    where
+        F: FnOnce(&mut V),
+    {
+        match self.get_mut(key) {
+            Some(value) => {
+                f(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Finds where `key` is stored without holding a borrow, so callers can
+    /// take a mutable one afterwards or transition to the heap.
+    fn slot_of(&self, key: &K) -> Slot {
+        match &self.inner {
+            MapKind::InlineMap(map) => match map.find_key_index(key) {
+                Some(i) => Slot::Inline(i),
+                None => Slot::InlineVacant,
+            },
+            MapKind::HeapMap(_) => Slot::Heap,
         }
     }
 
@@ -295,7 +642,9 @@ where
     pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
         match &self.inner {
             MapKind::InlineMap(map) => map.get_key_value(key),
-            MapKind::HeapMap(map) => map.get_key_value(key),
+            MapKind::HeapMap(map) => map
+                .get_key_value(E::wrap_ref(key))
+                .map(|(k, v)| (E::unwrap_ref(k), v)),
         }
     }
 
@@ -303,7 +652,7 @@ where
     pub fn contains_key(&self, key: &K) -> bool {
         match &self.inner {
             MapKind::InlineMap(map) => map.contains_key(key),
-            MapKind::HeapMap(map) => map.contains_key(key),
+            MapKind::HeapMap(map) => map.contains_key(E::wrap_ref(key)),
         }
     }
 
@@ -312,7 +661,29 @@ where
     pub fn remove(&mut self, key: &K) -> Option<V> {
         match &mut self.inner {
             MapKind::InlineMap(map) => map.remove(key),
-            MapKind::HeapMap(map) => map.remove(key),
+            MapKind::HeapMap(map) => map.remove(E::wrap_ref(key)),
+        }
+    }
+
+    /// Removes a key from the map, returning the stored key and value if the
+    /// key was previously in the map.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut map: SmallHashMap<i32, &str, 8> = SmallHashMap::new();
+    /// map.insert(1, "one");
+    /// assert_eq!(map.remove_entry(&1), Some((1, "one")));
+    /// assert_eq!(map.remove_entry(&1), None);
+    /// ```
+    pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
+        match &mut self.inner {
+            MapKind::InlineMap(map) => map.remove_entry(key),
+            MapKind::HeapMap(map) => map
+                .remove_entry(E::wrap_ref(key))
+                .map(|(k, v)| (E::unwrap(k), v)),
         }
     }
 
@@ -320,7 +691,7 @@ where
     ///
     /// For `InlineMap`, the order is insertion order; for `HeapMap`, it's
     /// arbitrary.
-    pub fn iter(&self) -> SmallHashMapIter<'_, K, V, N> {
+    pub fn iter(&self) -> SmallHashMapIter<'_, K, V, N, E> {
         match &self.inner {
             MapKind::InlineMap(map) => SmallHashMapIter::InlineMap(map.iter()),
             MapKind::HeapMap(map) => SmallHashMapIter::HeapMap(map.iter()),
@@ -331,7 +702,7 @@ where
     ///
     /// For `InlineMap`, the order is insertion order; for `HeapMap`, it's
     /// arbitrary.
-    pub fn keys(&self) -> SmallHashMapKeys<'_, K, V, N> {
+    pub fn keys(&self) -> SmallHashMapKeys<'_, K, V, N, E> {
         match &self.inner {
             MapKind::InlineMap(map) => SmallHashMapKeys::InlineMap(map.keys()),
             MapKind::HeapMap(map) => SmallHashMapKeys::HeapMap(map.keys()),
@@ -342,7 +713,7 @@ where
     ///
     /// For `InlineMap`, the order is insertion order; for `HeapMap`, it's
     /// arbitrary.
-    pub fn values(&self) -> SmallHashMapValues<'_, K, V, N> {
+    pub fn values(&self) -> SmallHashMapValues<'_, K, V, N, E> {
         match &self.inner {
             MapKind::InlineMap(map) => SmallHashMapValues::InlineMap(map.values()),
             MapKind::HeapMap(map) => SmallHashMapValues::HeapMap(map.values()),
@@ -354,7 +725,7 @@ where
     /// Keys are immutable; only values can be modified.
     /// For `InlineMap`, the order is insertion order; for `HeapMap`, it's
     /// arbitrary.
-    pub fn iter_mut(&mut self) -> SmallHashMapIterMut<'_, K, V, N> {
+    pub fn iter_mut(&mut self) -> SmallHashMapIterMut<'_, K, V, N, E> {
         match &mut self.inner {
             MapKind::InlineMap(map) => SmallHashMapIterMut::InlineMap(map.iter_mut()),
             MapKind::HeapMap(map) => SmallHashMapIterMut::HeapMap(map.iter_mut()),
@@ -365,31 +736,162 @@ where
     ///
     /// For `InlineMap`, the order is insertion order; for `HeapMap`, it's
     /// arbitrary.
-    pub fn values_mut(&mut self) -> SmallHashMapValuesMut<'_, K, V, N> {
+    pub fn values_mut(&mut self) -> SmallHashMapValuesMut<'_, K, V, N, E> {
         match &mut self.inner {
             MapKind::InlineMap(map) => SmallHashMapValuesMut::InlineMap(map.values_mut()),
             MapKind::HeapMap(map) => SmallHashMapValuesMut::HeapMap(map.values_mut()),
         }
     }
 
+    /// Returns a cursor positioned at the first entry, for removing,
+    /// replacing or inserting entries in a single pass.
+    ///
+    /// The cursor never changes the storage mode. See
+    /// [`SmallHashMapCursorMut`] for how each mode is walked.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut map: SmallHashMap<&str, i32, 8> =
+    ///     [("a", 1), ("b", -2), ("c", 3)].into();
+    ///
+    /// let mut removed = Vec::new();
+    /// let mut cursor = map.cursor_mut();
+    /// while let Some((_, value)) = cursor.current() {
+    ///     if *value < 0 {
+    ///         removed.push(cursor.remove_current().unwrap());
+    ///     } else {
+    ///         cursor.move_next();
+    ///     }
+    /// }
+    /// drop(cursor);
+    ///
+    /// assert_eq!(removed, [("b", -2)]);
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn cursor_mut(&mut self) -> SmallHashMapCursorMut<'_, K, V, N, S, E, P> {
+        SmallHashMapCursorMut::new(&mut self.inner)
+    }
+
     /// Retains only the elements specified by the predicate.
     ///
     /// In other words, remove all pairs `(k, v)` for which `f(&k, &mut v)` returns `false`.
-    pub fn retain<F>(&mut self, f: F)
+    pub fn retain<F>(&mut self, mut f: F)
     where
         F: FnMut(&K, &mut V) -> bool,
     {
         match &mut self.inner {
             MapKind::InlineMap(map) => map.retain(f),
-            MapKind::HeapMap(map) => map.retain(f),
+            MapKind::HeapMap(map) => map.retain(|key, value| f(E::unwrap_ref(key), value)),
+        }
+    }
+
+    /// Converts the map into a `std::collections::HashMap`.
+    ///
+    /// In heap mode the inner table is returned as-is, without rehashing.
+    /// Inline entries are inserted into a new table that uses the map's
+    /// hasher. With a [`CustomEquivalence`](crate::CustomEquivalence)
+    /// strategy the table's keys are [`EquivKey`](crate::EquivKey)s, so it
+    /// keeps comparing them the same way.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut map: SmallHashMap<&str, i32, 4> = SmallHashMap::new();
+    /// map.insert("a", 1);
+    ///
+    /// let std_map: HashMap<&str, i32> = map.into_hash_map();
+    /// assert_eq!(std_map.get(&"a"), Some(&1));
+    /// ```
+    pub fn into_hash_map(self) -> HashMap<E::Stored<K>, V, S> {
+        match self.inner {
+            MapKind::InlineMap(map) => {
+                let mut result = HashMap::with_capacity_and_hasher(map.len(), self.hash_builder);
+                for (key, value) in map.into_entries() {
+                    result.insert(E::wrap(key), value);
+                }
+                result
+            }
+            MapKind::HeapMap(map) => map.into(),
+        }
+    }
+
+    /// Converts the map to use a different hasher.
+    ///
+    /// In inline mode this only swaps the stored hasher. In heap mode the
+    /// entries are moved into a new table built with `hash_builder`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    /// use std::collections::hash_map::{DefaultHasher, RandomState};
+    /// use std::hash::BuildHasherDefault;
+    ///
+    /// type Fixed = BuildHasherDefault<DefaultHasher>;
+    ///
+    /// let map: SmallHashMap<u32, u32, 2, Fixed> = (0..10).map(|i| (i, i)).collect();
+    /// let map: SmallHashMap<u32, u32, 2, RandomState> = map.rehash_with(RandomState::new());
+    /// assert_eq!(map.get(&7), Some(&7));
+    /// ```
+    pub fn rehash_with<S2>(self, hash_builder: S2) -> SmallHashMap<K, V, N, S2, E, P>
+    where
+        S2: BuildHasher + Clone,
+        P: TablePolicy<E::Stored<K>, V, S2>,
+    {
+        let inner = match self.inner {
+            MapKind::InlineMap(map) => MapKind::InlineMap(map),
+            MapKind::HeapMap(map) => {
+                let mut rehashed =
+                    HeapMap::with_capacity_and_hasher(map.len(), hash_builder.clone());
+                for (key, value) in map {
+                    rehashed.insert_unique_unchecked(key, value);
+                }
+                MapKind::HeapMap(rehashed)
+            }
+        };
+        SmallHashMap {
+            inner,
+            transition_threshold: N,
+            hash_builder,
+        }
+    }
+
+    /// Replaces the hasher with a fresh `S::default()`, rebuilding the table
+    /// in heap mode.
+    ///
+    /// With `RandomState` each call picks new random keys, which breaks up a
+    /// skewed or adversarial key distribution in a spilled map. In inline
+    /// mode the stored hasher is swapped and nothing else changes.
+    ///
+    /// Every key is hashed with the new hasher through a borrowed walk
+    /// before any entry is moved, so if hashing a key panics the map is left
+    /// as it was.
+    pub fn reseed(&mut self)
+    where
+        S: Default + Clone,
+    {
+        let hash_builder = S::default();
+        if let MapKind::HeapMap(map) = &self.inner {
+            for key in map.keys() {
+                hash_builder.hash_one(key);
+            }
         }
+        let map = std::mem::replace(self, SmallHashMap::with_hasher(S::default()));
+        *self = map.rehash_with(hash_builder);
     }
 }
 
-impl<K, V, const N: usize, S> SmallHashMap<K, V, N, S>
+impl<K, V, const N: usize, S, E, P> SmallHashMap<K, V, N, S, E, P>
 where
-    K: Hash + Eq,
+    E: KeyEquivalence<K>,
     S: BuildHasher + Clone,
+    P: TablePolicy<E::Stored<K>, V, S>,
 {
     /// Inserts a key-value pair into the map.
     ///
@@ -415,123 +917,1175 @@ where
     /// assert_eq!(map.get(&1), Some(&"ONE"));
     /// ```
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
-        // Check if we need to transition BEFORE inserting.
-        // We use find_key_index to avoid scanning twice (once for existence check,
-        // once for the actual insert).
-        let (should_transition, existing_index) = match &self.inner {
-            MapKind::InlineMap(inline_map) => {
-                let idx = inline_map.find_key_index(&key);
-                // Transition if key doesn't exist and we're at capacity
-                let should_transition =
-                    idx.is_none() && inline_map.len() >= self.transition_threshold;
-                (should_transition, idx)
+        // The inline scan result is reused for the insert itself, so the
+        // inline slots are scanned only once.
+        let existing_index = match self.slot_of(&key) {
+            Slot::Inline(i) => Some(i),
+            Slot::InlineVacant => {
+                self.insert_absent(key, value);
+                return None;
             }
-            MapKind::HeapMap(_) => (false, None),
+            Slot::Heap => None,
         };
 
-        if should_transition {
-            // We know it's InlineMap here, so this match is just to satisfy the borrow checker
-            if let MapKind::InlineMap(inline_map) = &mut self.inner {
-                // Move all elements from InlineMap to HeapMap (no cloning needed)
-                let mut heap_map = HeapMap::with_capacity_and_hasher(
-                    inline_map.len() * 2,
-                    self.hash_builder.clone(),
-                );
-                for (existing_key, existing_value) in inline_map.drain() {
-                    heap_map.insert(existing_key, existing_value);
-                }
-                self.inner = MapKind::HeapMap(heap_map);
-            }
-        }
-
-        // Now safely insert into either map
         match &mut self.inner {
             MapKind::InlineMap(map) => map.insert_with_hint(key, value, existing_index),
-            MapKind::HeapMap(map) => map.insert(key, value),
+            MapKind::HeapMap(map) => map.insert(E::wrap(key), value),
         }
     }
-}
-
-impl<K, V, const N: usize, S> Default for SmallHashMap<K, V, N, S>
-where
-    K: Hash + Eq,
-    S: BuildHasher + Default,
-{
-    fn default() -> Self {
-        Self::new()
-    }
-}
 
-impl<K, V, const N: usize, S> IntoIterator for SmallHashMap<K, V, N, S>
-where
-    K: Hash + Eq,
-    S: BuildHasher,
-{
-    type Item = (K, V);
-    type IntoIter = SmallHashMapIntoIter<K, V, N>;
+    /// Tries to insert a key-value pair into the map, and returns a mutable
+    /// reference to the value in the entry.
+    ///
+    /// If the map already had this key present, nothing is updated, and an
+    /// error containing the occupied entry and the value is returned. This
+    /// costs a single key scan (inline) or a single hash (heap), unlike
+    /// `contains_key` followed by `insert`.
+    ///
+    /// If inserting would exceed the inline capacity, the map automatically
+    /// transitions to heap storage.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut map: SmallHashMap<&str, i32, 4> = SmallHashMap::new();
+    /// assert_eq!(map.try_insert("a", 1).unwrap(), &1);
+    ///
+    /// let err = map.try_insert("a", 2).unwrap_err();
+    /// assert_eq!(err.key(), &"a");
+    /// assert_eq!(err.existing(), &1);
+    /// assert_eq!(err.into_value(), 2);
+    /// assert_eq!(map.get(&"a"), Some(&1));
+    /// ```
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<&mut V, OccupiedError<'_, K, V, E>> {
+        let existing_index = match self.slot_of(&key) {
+            Slot::Inline(i) => Some(i),
+            Slot::InlineVacant => return Ok(self.insert_absent(key, value)),
+            Slot::Heap => None,
+        };
 
-    /// Consumes the map and returns an iterator over owned key-value pairs.
-    fn into_iter(self) -> Self::IntoIter {
-        match self.inner {
-            MapKind::InlineMap(map) => SmallHashMapIntoIter::InlineMap(map.into_iter()),
-            MapKind::HeapMap(map) => SmallHashMapIntoIter::HeapMap(map.into_iter()),
+        match &mut self.inner {
+            MapKind::InlineMap(map) => map.try_insert_with_hint(key, value, existing_index),
+            MapKind::HeapMap(map) => match map.entry(E::wrap(key)) {
+                Entry::Occupied(entry) => Err(OccupiedError::heap(entry, value)),
+                Entry::Vacant(entry) => Ok(entry.insert(value)),
+            },
         }
     }
-}
 
-impl<K, V, const N: usize, S> Extend<(K, V)> for SmallHashMap<K, V, N, S>
-where
-    K: Hash + Eq,
-    S: BuildHasher + Clone,
-{
-    /// Extends the map with the contents of an iterator.
+    /// Inserts a key-value pair without checking whether the key already
+    /// exists, and returns a mutable reference to the value.
     ///
-    /// If the map transitions to heap storage during extension, subsequent
-    /// inserts will go to the heap map.
-    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
-        for (key, value) in iter {
-            self.insert(key, value);
-        }
-    }
+    /// This skips the duplicate scan in inline mode, which makes it the
+    /// fastest way to load data that is already known to be deduplicated.
+    /// It saves nothing in heap mode: `std::collections::HashMap` has no
+    /// unchecked insert, so the key is still hashed and probed once, exactly
+    /// as `insert` does.
+    ///
+    /// If inserting would exceed the inline capacity, the map automatically
+    /// transitions to heap storage.
+    ///
+    /// # Safety
+    ///
+    /// The key must not already be present in the map. Debug builds check
+    /// this and panic. In release builds, breaking the contract leaves the
+    /// map in an unspecified state: the value may be dropped or stored as an
+    /// unreachable duplicate, and later operations may return arbitrary
+    /// results or panic. It never causes memory unsafety by itself.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut map: SmallHashMap<i32, &str, 4> = SmallHashMap::new();
+    /// // SAFETY: The map is empty, so the key can't be present.
+    /// *unsafe { map.insert_unique_unchecked(1, "one") } = "uno";
+    /// assert_eq!(map.get(&1), Some(&"uno"));
+    /// ```
+    pub unsafe fn insert_unique_unchecked(&mut self, key: K, value: V) -> &mut V {
+        self.insert_absent(key, value)
+    }
+
+    /// Inserts a key that the caller has found to be absent, transitioning
+    /// to heap storage first if the inline storage is full.
+    ///
+    /// This is the single place where an insertion decides to spill.
+    fn insert_absent(&mut self, key: K, value: V) -> &mut V {
+        if let MapKind::InlineMap(inline_map) = &self.inner {
+            if inline_map.len() >= self.transition_threshold {
+                self.transition_to_heap(self.len() * 2);
+            }
+        }
+
+        match &mut self.inner {
+            MapKind::InlineMap(map) => map.insert_unique_unchecked(key, value),
+            MapKind::HeapMap(map) => map.insert_unique_unchecked(E::wrap(key), value),
+        }
+    }
+
+    /// Inserts, updates or removes the entry for `key` with a single call.
+    ///
+    /// `f` receives the current value (or `None`) and returns the new value
+    /// (or `None` to remove the entry). The key is looked up once in either
+    /// mode, and an existing value is updated in place, so an inline map
+    /// keeps the entry in its position. If the entry is new and the inline
+    /// storage is full, the map transitions to the heap.
+    ///
+    /// If `f` panics, the entry it was given is removed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut stock: SmallHashMap<&str, u32, 4> = [("apple", 1)].into();
+    /// let take = |count: Option<u32>| count.and_then(|c| c.checked_sub(1)).filter(|&c| c > 0);
+    ///
+    /// stock.alter("apple", take);
+    /// assert!(!stock.contains_key(&"apple"));
+    ///
+    /// stock.alter("pear", |count| Some(count.unwrap_or(0) + 5));
+    /// assert_eq!(stock.get(&"pear"), Some(&5));
+    /// ```
+    pub fn alter<F>(&mut self, key: K, f: F)
+    where
+        F: FnOnce(Option<V>) -> Option<V>,
+    {
+        match self.slot_of(&key) {
+            Slot::Inline(i) => {
+                if let MapKind::InlineMap(map) = &mut self.inner {
+                    map.alter_value_at(i, |value| f(Some(value)));
+                }
+            }
+            Slot::InlineVacant => {
+                if let Some(value) = f(None) {
+                    self.insert_absent(key, value);
+                }
+            }
+            Slot::Heap => {
+                if let MapKind::HeapMap(map) = &mut self.inner {
+                    match map.entry(E::wrap(key)) {
+                        Entry::Occupied(entry) => {
+                            alter_occupied(entry, |_, value| f(Some(value)));
+                        }
+                        Entry::Vacant(entry) => {
+                            if let Some(value) = f(None) {
+                                entry.insert(value);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns a mutable reference to the value for `key`, inserting the
+    /// result of `f` first if the key is absent.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut groups: SmallHashMap<bool, Vec<i32>, 2> = SmallHashMap::new();
+    /// for i in 0..5 {
+    ///     groups.get_or_insert_with(i % 2 == 0, Vec::new).push(i);
+    /// }
+    /// assert_eq!(groups.get(&true), Some(&vec![0, 2, 4]));
+    /// ```
+    pub fn get_or_insert_with<F>(&mut self, key: K, f: F) -> &mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self.slot_of(&key) {
+            Slot::Inline(i) => self.inline_value_at(i),
+            Slot::InlineVacant => self.insert_absent(key, f()),
+            Slot::Heap => match &mut self.inner {
+                MapKind::HeapMap(map) => map.entry(E::wrap(key)).or_insert_with(f),
+                MapKind::InlineMap(_) => unreachable!("slot_of reported heap storage"),
+            },
+        }
+    }
+
+    /// Returns a mutable reference to the value for `key`, inserting
+    /// `V::default()` first if the key is absent.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut counts: SmallHashMap<char, usize, 8> = SmallHashMap::new();
+    /// for c in "hello".chars() {
+    ///     *counts.get_or_default(c) += 1;
+    /// }
+    /// assert_eq!(counts.get(&'l'), Some(&2));
+    /// ```
+    pub fn get_or_default(&mut self, key: K) -> &mut V
+    where
+        V: Default,
+    {
+        self.get_or_insert_with(key, V::default)
+    }
+
+    /// Inserts `value` if `key` is absent, or calls `update` on the existing
+    /// value otherwise. Returns a mutable reference to the stored value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut seen: SmallHashMap<&str, u32, 4> = SmallHashMap::new();
+    /// seen.upsert("a", 1, |count| *count += 1);
+    /// seen.upsert("a", 1, |count| *count += 1);
+    /// assert_eq!(seen.get(&"a"), Some(&2));
+    /// ```
+    pub fn upsert<F>(&mut self, key: K, value: V, update: F) -> &mut V
+    where
+        F: FnOnce(&mut V),
+    {
+        match self.slot_of(&key) {
+            Slot::Inline(i) => {
+                let existing = self.inline_value_at(i);
+                update(existing);
+                existing
+            }
+            Slot::InlineVacant => self.insert_absent(key, value),
+            Slot::Heap => match &mut self.inner {
+                MapKind::HeapMap(map) => match map.entry(E::wrap(key)) {
+                    Entry::Occupied(entry) => {
+                        let existing = entry.into_mut();
+                        update(existing);
+                        existing
+                    }
+                    Entry::Vacant(entry) => entry.insert(value),
+                },
+                MapKind::InlineMap(_) => unreachable!("slot_of reported heap storage"),
+            },
+        }
+    }
+
+    /// Returns the value at `index` of the inline storage.
+    fn inline_value_at(&mut self, index: usize) -> &mut V {
+        match &mut self.inner {
+            MapKind::InlineMap(map) => map.get_index_mut(index).expect("index out of bounds").1,
+            MapKind::HeapMap(_) => unreachable!("slot_of reported inline storage"),
+        }
+    }
+
+    /// Extends the map with key-value pairs whose keys are guaranteed to be
+    /// distinct from each other and from the keys already in the map.
+    ///
+    /// Uses the iterator's `size_hint` to transition to heap storage at most
+    /// once, up front, and then inserts every element without a duplicate
+    /// scan.
+    ///
+    /// # Safety
+    ///
+    /// Every key must be absent from the map and distinct from the other
+    /// keys in `iter`; see `insert_unique_unchecked` for what happens if
+    /// this is broken.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut map: SmallHashMap<i32, i32, 4> = SmallHashMap::new();
+    /// // SAFETY: The map is empty and the range yields distinct keys.
+    /// unsafe { map.extend_unique_unchecked((0..10).map(|i| (i, i * i))) };
+    /// assert_eq!(map.len(), 10);
+    /// assert_eq!(map.get(&3), Some(&9));
+    /// ```
+    pub unsafe fn extend_unique_unchecked<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for (key, value) in iter {
+            self.insert_absent(key, value);
+        }
+    }
+
+    /// Reserves capacity for at least `additional` more elements.
+    ///
+    /// If the map is inline and `len + additional` exceeds `N`, it
+    /// transitions to heap storage now, sized for the total. Otherwise an
+    /// inline map is left untouched and a heap map reserves as `HashMap` does.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut map: SmallHashMap<i32, i32, 4> = SmallHashMap::new();
+    /// map.reserve(3);
+    /// assert!(map.is_inline());
+    ///
+    /// map.reserve(100);
+    /// assert!(!map.is_inline());
+    /// assert!(map.capacity() >= 100);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) {
+        let required = self.len().saturating_add(additional);
+        match &mut self.inner {
+            MapKind::InlineMap(_) => {
+                if required > self.transition_threshold {
+                    self.transition_to_heap(required);
+                }
+            }
+            MapKind::HeapMap(map) => map.reserve(additional),
+        }
+    }
+
+    /// Converts the map into one with inline capacity `M`.
+    ///
+    /// If the entries fit in `M` they are moved slot by slot into inline
+    /// storage, keeping insertion order when the source is inline. Otherwise
+    /// the result uses heap storage: an existing heap table is handed over
+    /// as-is without rehashing, and an inline map is moved into a new table.
+    ///
+    /// This is a method rather than a `From` impl because such an impl would
+    /// overlap with the blanket `From<T> for T` when `M == N`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut map: SmallHashMap<&str, i32, 4> = SmallHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    ///
+    /// let wider: SmallHashMap<&str, i32, 16> = map.into_capacity();
+    /// assert!(wider.is_inline());
+    ///
+    /// let narrower: SmallHashMap<&str, i32, 1> = wider.into_capacity();
+    /// assert!(!narrower.is_inline());
+    /// assert_eq!(narrower.get(&"b"), Some(&2));
+    /// ```
+    pub fn into_capacity<const M: usize>(self) -> SmallHashMap<K, V, M, S, E, P> {
+        let inner = match self.inner {
+            MapKind::InlineMap(map) => match map.into_capacity::<M>() {
+                Ok(map) => MapKind::InlineMap(map),
+                Err(map) => {
+                    let mut heap_map =
+                        HeapMap::with_capacity_and_hasher(map.len() * 2, self.hash_builder.clone());
+                    for (key, value) in map.into_entries() {
+                        heap_map.insert_unique_unchecked(E::wrap(key), value);
+                    }
+                    MapKind::HeapMap(heap_map)
+                }
+            },
+            MapKind::HeapMap(map) if map.len() <= M => {
+                let mut inline_map = InlineMap::new();
+                for (key, value) in map {
+                    inline_map.push(E::unwrap(key), value);
+                }
+                MapKind::InlineMap(inline_map)
+            }
+            MapKind::HeapMap(map) => MapKind::HeapMap(map),
+        };
+        SmallHashMap {
+            inner,
+            transition_threshold: M,
+            hash_builder: self.hash_builder,
+        }
+    }
+
+    /// Replaces the stored key equal to `key` with `key`, returning the old
+    /// key, or inserts `key` with `value` if absent.
+    ///
+    /// An inline map keeps the entry in its position. On the heap the key is
+    /// looked up once by reference and then inserted, so it's hashed twice:
+    /// `HashMap::entry` consumes the key, and std has no stable way to swap
+    /// the key stored in an occupied entry.
+    pub(crate) fn replace_key(&mut self, key: K, value: V) -> Option<K> {
+        match &mut self.inner {
+            MapKind::InlineMap(map) => match map.find_key_index(&key) {
+                Some(i) => Some(map.replace_key_at(i, key)),
+                None => {
+                    self.insert_absent(key, value);
+                    None
+                }
+            },
+            MapKind::HeapMap(map) => match map.remove_entry(E::wrap_ref(&key)) {
+                Some((old_key, old_value)) => {
+                    map.insert_unique_unchecked(E::wrap(key), old_value);
+                    Some(E::unwrap(old_key))
+                }
+                None => {
+                    map.insert_unique_unchecked(E::wrap(key), value);
+                    None
+                }
+            },
+        }
+    }
+
+    /// Moves the elements back into inline storage if the map is on the heap
+    /// but its length fits in `N`.
+    ///
+    /// Used at the end of bulk construction, where a size hint may have
+    /// chosen heap storage before duplicates collapsed the map.
+    fn shrink_to_inline_if_fits(&mut self) {
+        if self.is_inline() || self.len() > self.transition_threshold {
+            return;
+        }
+        let inner = std::mem::replace(&mut self.inner, MapKind::InlineMap(InlineMap::new()));
+        if let (MapKind::HeapMap(heap_map), MapKind::InlineMap(inline_map)) =
+            (inner, &mut self.inner)
+        {
+            for (key, value) in heap_map {
+                inline_map.insert_with_hint(E::unwrap(key), value, None);
+            }
+        }
+    }
+
+    /// Moves all elements from the `InlineMap` into a new `HeapMap` with room
+    /// for at least `capacity` elements. Does nothing if already on the heap.
+    ///
+    /// The table comes from the map's [`TablePolicy`], so a pooled map reuses
+    /// a recycled table here when one is available.
+    fn transition_to_heap(&mut self, capacity: usize) {
+        if let MapKind::InlineMap(inline_map) = &mut self.inner {
+            // Move all elements from InlineMap to HeapMap (no cloning needed)
+            let mut heap_map =
+                HeapMap::with_capacity_and_hasher(capacity, self.hash_builder.clone());
+            for (existing_key, existing_value) in inline_map.drain() {
+                heap_map.insert(E::wrap(existing_key), existing_value);
+            }
+            self.inner = MapKind::HeapMap(heap_map);
+        }
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> SmallHashMap<K, V, N, S, E, P>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    /// Combines `self` and `other` into a map holding every key of either,
+    /// resolving keys present in both with `merge(key, self_value, other_value)`.
+    ///
+    /// `other` may have a different inline capacity and hasher. The result
+    /// keeps `self`'s storage and transitions to heap storage at most once,
+    /// only if the combined entries don't fit in `N`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let defaults: SmallHashMap<&str, i32, 8> =
+    ///     [("timeout", 30), ("retries", 3)].into_iter().collect();
+    /// let request: SmallHashMap<&str, i32, 4> = [("timeout", 5)].into_iter().collect();
+    ///
+    /// let merged = defaults.union_with(request, |_key, _default, value| value);
+    /// assert_eq!(merged.get(&"timeout"), Some(&5));
+    /// assert_eq!(merged.get(&"retries"), Some(&3));
+    /// assert!(merged.is_inline());
+    /// ```
+    pub fn union_with<const M: usize, T, Q, F>(
+        mut self,
+        other: SmallHashMap<K, V, M, T, E, Q>,
+        mut merge: F,
+    ) -> Self
+    where
+        S: Clone,
+        T: BuildHasher,
+        F: FnMut(&K, V, V) -> V,
+        Q: TablePolicy<E::Stored<K>, V, T>,
+    {
+        self.reserve_for_merge(&other);
+
+        for (key, value) in other.into_entries() {
+            match &mut self.inner {
+                MapKind::InlineMap(map) => {
+                    if let Some(i) = map.find_key_index(&key) {
+                        map.replace_value_with(i, |existing| merge(&key, existing, value));
+                        continue;
+                    }
+                }
+                MapKind::HeapMap(map) => {
+                    match map.entry(E::wrap(key)) {
+                        Entry::Occupied(entry) => {
+                            alter_occupied(entry, |key, existing| {
+                                Some(merge(E::unwrap_ref(key), existing, value))
+                            });
+                        }
+                        Entry::Vacant(entry) => {
+                            entry.insert(value);
+                        }
+                    }
+                    continue;
+                }
+            }
+            self.insert_absent(key, value);
+        }
+        self
+    }
+
+    /// Moves all entries from `other` into `self`, leaving `other` empty.
+    ///
+    /// If a key from `other` is already present in `self`, its value is
+    /// overwritten with the one from `other`. No entries are cloned.
+    ///
+    /// `other` may have a different inline capacity and hasher. When both
+    /// maps are inline and the combined entries fit in `N`, nothing is
+    /// allocated; otherwise `self` transitions to heap storage at most once.
+    /// `other` keeps its storage mode (and its heap allocation, if any).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut a: SmallHashMap<&str, i32, 4> = SmallHashMap::new();
+    /// a.insert("x", 1);
+    /// let mut b: SmallHashMap<&str, i32, 2> = SmallHashMap::new();
+    /// b.insert("x", 10);
+    /// b.insert("y", 20);
+    ///
+    /// a.append(&mut b);
+    /// assert!(b.is_empty());
+    /// assert_eq!(a.get(&"x"), Some(&10));
+    /// assert_eq!(a.get(&"y"), Some(&20));
+    /// assert!(a.is_inline());
+    /// ```
+    pub fn append<const M: usize, T, Q>(&mut self, other: &mut SmallHashMap<K, V, M, T, E, Q>)
+    where
+        S: Clone,
+        T: BuildHasher,
+        Q: TablePolicy<E::Stored<K>, V, T>,
+    {
+        self.reserve_for_merge(other);
+
+        match &mut other.inner {
+            MapKind::InlineMap(map) => {
+                for (key, value) in std::mem::replace(map, InlineMap::new()).into_entries() {
+                    self.insert(key, value);
+                }
+            }
+            MapKind::HeapMap(map) => {
+                for (key, value) in map.drain() {
+                    self.insert(E::unwrap(key), value);
+                }
+            }
+        }
+    }
+
+    /// Moves all entries from `other` into `self`, resolving keys present in
+    /// both with `merge(key, existing, incoming)`.
+    ///
+    /// `other` may have a different inline capacity and hasher. When both
+    /// maps are inline and the combined entries fit in `N`, nothing is
+    /// allocated; otherwise `self` transitions to heap storage at most once.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut totals: SmallHashMap<&str, u64, 8> = SmallHashMap::new();
+    /// totals.insert("hits", 3);
+    ///
+    /// let mut per_thread: SmallHashMap<&str, u64, 4> = SmallHashMap::new();
+    /// per_thread.insert("hits", 2);
+    /// per_thread.insert("misses", 1);
+    ///
+    /// totals.merge_with(per_thread, |_key, existing, incoming| *existing += incoming);
+    /// assert_eq!(totals.get(&"hits"), Some(&5));
+    /// assert_eq!(totals.get(&"misses"), Some(&1));
+    /// ```
+    pub fn merge_with<const M: usize, T, Q, F>(
+        &mut self,
+        other: SmallHashMap<K, V, M, T, E, Q>,
+        mut merge: F,
+    ) where
+        S: Clone,
+        T: BuildHasher,
+        F: FnMut(&K, &mut V, V),
+        Q: TablePolicy<E::Stored<K>, V, T>,
+    {
+        self.reserve_for_merge(&other);
+
+        for (key, value) in other.into_entries() {
+            match self.get_mut(&key) {
+                Some(existing) => merge(&key, existing, value),
+                None => {
+                    self.insert_absent(key, value);
+                }
+            }
+        }
+    }
+
+    /// Builds a map holding only the keys present in both `self` and `other`,
+    /// with values produced by `combine(key, self_value, other_value)`.
+    ///
+    /// The result uses `self`'s inline capacity and hasher, and stays inline
+    /// whenever it fits in `N`. Entries follow `self`'s iteration order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let prices: SmallHashMap<&str, u32, 4> = [("apple", 3), ("pear", 4)].into_iter().collect();
+    /// let counts: SmallHashMap<&str, u32, 8> = [("apple", 2), ("plum", 7)].into_iter().collect();
+    ///
+    /// let totals = prices.intersection_with(counts, |_key, price, count| price * count);
+    /// assert_eq!(totals.len(), 1);
+    /// assert_eq!(totals.get(&"apple"), Some(&6));
+    /// ```
+    pub fn intersection_with<V2, R, const M: usize, T, Q, F>(
+        self,
+        mut other: SmallHashMap<K, V2, M, T, E, Q>,
+        mut combine: F,
+    ) -> SmallHashMap<K, R, N, S, E, P>
+    where
+        S: Clone,
+        T: BuildHasher,
+        F: FnMut(&K, V, V2) -> R,
+        Q: TablePolicy<E::Stored<K>, V2, T>,
+        P: TablePolicy<E::Stored<K>, R, S>,
+    {
+        let mut result = SmallHashMap::with_hasher(self.hash_builder.clone());
+        for (key, value) in self.into_entries() {
+            if let Some(other_value) = other.remove(&key) {
+                let combined = combine(&key, value, other_value);
+                result.insert_absent(key, combined);
+            }
+        }
+        result
+    }
+
+    /// Consumes the map and returns one with every value replaced by
+    /// `f(key, value)`.
+    ///
+    /// The storage mode is kept. Inline entries are moved slot by slot
+    /// without hashing or comparing keys; a heap map is rebuilt into a table
+    /// of the same size.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let sizes: SmallHashMap<&str, usize, 4> =
+    ///     [("a", 1), ("b", 2)].into_iter().collect();
+    /// let labels = sizes.map_values(|key, size| format!("{key}={size}"));
+    /// assert_eq!(labels.get(&"b").map(String::as_str), Some("b=2"));
+    /// assert!(labels.is_inline());
+    /// ```
+    pub fn map_values<U, F>(self, mut f: F) -> SmallHashMap<K, U, N, S, E, P>
+    where
+        S: Clone,
+        F: FnMut(&K, V) -> U,
+        P: TablePolicy<E::Stored<K>, U, S>,
+    {
+        let mut result = self.empty_like(self.len());
+        for (key, value) in self.into_entries() {
+            let value = f(&key, value);
+            result.push_unique(key, value);
+        }
+        result
+    }
+
+    /// Consumes the map and returns one holding `f(key, value)` for every
+    /// entry where it returns `Some`.
+    ///
+    /// The storage mode is kept, as with [`map_values`](Self::map_values).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let raw: SmallHashMap<&str, &str, 4> =
+    ///     [("port", "8080"), ("host", "localhost")].into_iter().collect();
+    /// let numeric = raw.filter_map_values(|_key, value| value.parse::<u16>().ok());
+    /// assert_eq!(numeric.len(), 1);
+    /// assert_eq!(numeric.get(&"port"), Some(&8080));
+    /// ```
+    pub fn filter_map_values<U, F>(self, mut f: F) -> SmallHashMap<K, U, N, S, E, P>
+    where
+        S: Clone,
+        F: FnMut(&K, V) -> Option<U>,
+        P: TablePolicy<E::Stored<K>, U, S>,
+    {
+        let mut result = self.empty_like(self.len());
+        for (key, value) in self.into_entries() {
+            if let Some(value) = f(&key, value) {
+                result.push_unique(key, value);
+            }
+        }
+        result
+    }
+
+    /// Consumes the map and splits it in two: the entries for which
+    /// `predicate` returns `true`, and the rest.
+    ///
+    /// Both maps keep the storage mode of `self`. Inline entries are moved
+    /// slot by slot without hashing or comparing keys.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let scores: SmallHashMap<&str, u32, 4> =
+    ///     [("ann", 90), ("bob", 40), ("cyd", 75)].into_iter().collect();
+    /// let (passed, failed) = scores.partition(|_name, score| *score >= 50);
+    /// assert_eq!(passed.len(), 2);
+    /// assert_eq!(failed.get(&"bob"), Some(&40));
+    /// ```
+    pub fn partition<F>(self, mut predicate: F) -> (Self, Self)
+    where
+        S: Clone,
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut matching = self.empty_like(0);
+        let mut rest = self.empty_like(0);
+        for (key, value) in self.into_entries() {
+            if predicate(&key, &value) {
+                matching.push_unique(key, value);
+            } else {
+                rest.push_unique(key, value);
+            }
+        }
+        (matching, rest)
+    }
+
+    /// Removes the entries for which `predicate` returns `true` and returns
+    /// them in a new map.
+    ///
+    /// Both maps keep the storage mode of `self`. Entries are moved out in
+    /// place, so if `predicate` panics, the entries it hasn't visited yet stay
+    /// in `self`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let mut headers: SmallHashMap<&str, &str, 4> =
+    ///     [("x-trace", "1"), ("accept", "*/*"), ("x-span", "2")].into_iter().collect();
+    /// let internal = headers.split_off(|name, _| name.starts_with("x-"));
+    /// assert_eq!(internal.len(), 2);
+    /// assert_eq!(headers.len(), 1);
+    /// assert!(headers.contains_key(&"accept"));
+    /// ```
+    pub fn split_off<F>(&mut self, mut predicate: F) -> Self
+    where
+        S: Clone,
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut split = self.empty_like(0);
+        match &mut self.inner {
+            MapKind::InlineMap(map) => map.retain_with(
+                |key, value| !predicate(key, value),
+                |key, value| split.push_unique(key, value),
+            ),
+            MapKind::HeapMap(map) => {
+                let extracted = map
+                    .as_hash_map_mut()
+                    .extract_if(|key, value| predicate(E::unwrap_ref(key), value));
+                for (key, value) in extracted {
+                    split.push_unique(E::unwrap(key), value);
+                }
+            }
+        }
+        split
+    }
+
+    /// Removes every entry whose key is present in `other`.
+    ///
+    /// `other`'s values are ignored, so it may hold a different value type.
+    /// The storage mode of `self` is kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let attrs: SmallHashMap<&str, i32, 4> =
+    ///     [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+    /// let hidden: SmallHashMap<&str, (), 2> = [("b", ())].into_iter().collect();
+    ///
+    /// let visible = attrs.difference(&hidden);
+    /// assert_eq!(visible.len(), 2);
+    /// assert!(!visible.contains_key(&"b"));
+    /// ```
+    pub fn difference<V2, const M: usize, T, Q>(
+        mut self,
+        other: &SmallHashMap<K, V2, M, T, E, Q>,
+    ) -> Self
+    where
+        T: BuildHasher,
+        Q: TablePolicy<E::Stored<K>, V2, T>,
+    {
+        self.retain(|key, _| !other.contains_key(key));
+        self
+    }
+
+    /// Returns `true` if every entry of `self` is also in `other` with an
+    /// equal value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let small: SmallHashMap<i32, i32, 2> = [(1, 10)].into_iter().collect();
+    /// let large: SmallHashMap<i32, i32, 8> = [(1, 10), (2, 20)].into_iter().collect();
+    ///
+    /// assert!(small.is_submap_of(&large));
+    /// assert!(!large.is_submap_of(&small));
+    /// ```
+    pub fn is_submap_of<const M: usize, T, Q>(&self, other: &SmallHashMap<K, V, M, T, E, Q>) -> bool
+    where
+        V: PartialEq,
+        T: BuildHasher,
+        Q: TablePolicy<E::Stored<K>, V, T>,
+    {
+        self.len() <= other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+
+    /// Returns an iterator over the keys present in both maps, yielding
+    /// `(key, self_value, other_value)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let names: SmallHashMap<u32, &str, 4> = [(1, "ann"), (2, "bob")].into_iter().collect();
+    /// let ages: SmallHashMap<u32, u8, 4> = [(2, 41), (3, 27)].into_iter().collect();
+    ///
+    /// let joined: Vec<_> = names.inner_join(&ages).collect();
+    /// assert_eq!(joined, [(&2, &"bob", &41)]);
+    /// ```
+    pub fn inner_join<'a, V2, const M: usize, T, Q>(
+        &'a self,
+        other: &'a SmallHashMap<K, V2, M, T, E, Q>,
+    ) -> SmallHashMapInnerJoin<'a, K, V, V2, N, M, T, E, Q>
+    where
+        T: BuildHasher,
+        Q: TablePolicy<E::Stored<K>, V2, T>,
+    {
+        SmallHashMapInnerJoin::new(self.iter(), other)
+    }
+
+    /// Returns an iterator over every entry of `self`, yielding
+    /// `(key, self_value, other_value)` where `other_value` is `None` if the
+    /// key is absent from `other`.
+    pub fn left_join<'a, V2, const M: usize, T, Q>(
+        &'a self,
+        other: &'a SmallHashMap<K, V2, M, T, E, Q>,
+    ) -> SmallHashMapLeftJoin<'a, K, V, V2, N, M, T, E, Q>
+    where
+        T: BuildHasher,
+        Q: TablePolicy<E::Stored<K>, V2, T>,
+    {
+        SmallHashMapLeftJoin::new(self.iter(), other)
+    }
+
+    /// Returns an iterator over the keys present in either map, yielding
+    /// `(key, self_value, other_value)` with `None` on the side that lacks
+    /// the key.
+    ///
+    /// Entries of `self` come first, followed by the keys only in `other`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let a: SmallHashMap<u32, char, 4> = [(1, 'a')].into_iter().collect();
+    /// let b: SmallHashMap<u32, bool, 4> = [(1, true), (2, false)].into_iter().collect();
+    ///
+    /// let joined: Vec<_> = a.full_outer_join(&b).collect();
+    /// assert_eq!(
+    ///     joined,
+    ///     [(&1, Some(&'a'), Some(&true)), (&2, None, Some(&false))]
+    /// );
+    /// ```
+    pub fn full_outer_join<'a, V2, const M: usize, T, Q>(
+        &'a self,
+        other: &'a SmallHashMap<K, V2, M, T, E, Q>,
+    ) -> SmallHashMapFullOuterJoin<'a, K, V, V2, N, M, S, T, E, P, Q>
+    where
+        T: BuildHasher,
+        Q: TablePolicy<E::Stored<K>, V2, T>,
+    {
+        SmallHashMapFullOuterJoin::new(self, other)
+    }
+
+    /// Prepares to move the entries of `other` into `self`, transitioning to
+    /// heap storage at most once.
+    ///
+    /// An inline map counts exactly how many of `other`'s keys are new, so it
+    /// only transitions if the combined entries don't fit in `N`.
+    fn reserve_for_merge<V2, const M: usize, T, Q>(
+        &mut self,
+        other: &SmallHashMap<K, V2, M, T, E, Q>,
+    ) where
+        S: Clone,
+        T: BuildHasher,
+        Q: TablePolicy<E::Stored<K>, V2, T>,
+    {
+        let additional = if self.is_inline() {
+            other.keys().filter(|key| !self.contains_key(key)).count()
+        } else {
+            other.len()
+        };
+        self.reserve(additional);
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> fmt::Display for SmallHashMap<K, V, N, S, E, P>
+where
+    K: Ord + fmt::Display,
+    E: KeyEquivalence<K>,
+    V: fmt::Display,
+    S: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    /// Formats the entries in ascending key order as `{key: value, ...}`.
+    ///
+    /// The output is the same in inline and heap mode.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let map: SmallHashMap<&str, i32, 1> = [("b", 2), ("a", 1)].into();
+    /// assert!(!map.is_inline());
+    /// assert_eq!(map.to_string(), "{a: 1, b: 2}");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("{")?;
+        for (i, (key, value)) in self.iter_sorted().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}: {}", key, value)?;
+        }
+        f.write_str("}")
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> SmallHashMap<K, V, N, S, E, P>
+where
+    K: Ord,
+    E: KeyEquivalence<K>,
+    S: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    /// Returns an iterator over the entries in ascending key order.
+    ///
+    /// The order doesn't depend on the storage mode or the hasher. Inline
+    /// mode doesn't allocate; heap mode collects and sorts the entry
+    /// references.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let map: SmallHashMap<&str, i32, 4> = [("b", 2), ("c", 3), ("a", 1)].into();
+    /// let entries: Vec<_> = map.iter_sorted().collect();
+    /// assert_eq!(entries, [(&"a", &1), (&"b", &2), (&"c", &3)]);
+    /// ```
+    pub fn iter_sorted(&self) -> SmallHashMapSortedIter<'_, K, V, N> {
+        SmallHashMapSortedIter::new(&self.inner)
+    }
+
+    /// Returns an iterator over the keys in ascending order.
+    pub fn keys_sorted(&self) -> SmallHashMapSortedKeys<'_, K, V, N> {
+        SmallHashMapSortedKeys::new(self.iter_sorted())
+    }
+
+    /// Returns a `Debug` view that prints the entries in key order, along
+    /// with the storage mode and capacity.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let map: SmallHashMap<&str, i32, 4> = [("b", 2), ("a", 1)].into();
+    /// assert_eq!(
+    ///     format!("{:?}", map.sorted_debug()),
+    ///     r#"SmallHashMap { storage: inline, capacity: 4, entries: {"a": 1, "b": 2} }"#
+    /// );
+    /// ```
+    pub fn sorted_debug(&self) -> SortedDebug<'_, K, V, N, S, E, P> {
+        SortedDebug::new(self)
+    }
+}
+
+impl<K, V, const N: usize, S, E: KeyEquivalence<K>, P> SmallHashMap<K, V, N, S, E, P>
+where
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    /// Creates an empty map in the same storage mode as `self`, using a clone
+    /// of its hasher. `capacity` only applies to heap storage.
+    fn empty_like<U>(&self, capacity: usize) -> SmallHashMap<K, U, N, S, E, P>
+    where
+        S: BuildHasher + Clone,
+        P: TablePolicy<E::Stored<K>, U, S>,
+    {
+        let inner = match &self.inner {
+            MapKind::InlineMap(_) => MapKind::InlineMap(InlineMap::new()),
+            MapKind::HeapMap(_) => MapKind::HeapMap(HeapMap::with_capacity_and_hasher(
+                capacity,
+                self.hash_builder.clone(),
+            )),
+        };
+        SmallHashMap {
+            inner,
+            transition_threshold: N,
+            hash_builder: self.hash_builder.clone(),
+        }
+    }
+
+    /// Adds an entry whose key is known to be absent, without transitioning.
+    ///
+    /// Only used on maps built by `empty_like` from a source with the same
+    /// `N`, so inline storage always has room.
+    fn push_unique(&mut self, key: K, value: V)
+    where
+        S: BuildHasher,
+    {
+        match &mut self.inner {
+            MapKind::InlineMap(map) => map.push(key, value),
+            MapKind::HeapMap(map) => {
+                map.insert_unique_unchecked(E::wrap(key), value);
+            }
+        }
+    }
+
+    /// Consumes the map and returns an iterator over owned entries that
+    /// doesn't allocate in inline mode.
+    fn into_entries(self) -> Entries<K, V, N, E> {
+        match self.inner {
+            MapKind::InlineMap(map) => Entries::InlineMap(map.into_entries()),
+            MapKind::HeapMap(map) => Entries::HeapMap(map.into_entries()),
+        }
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> Default for SmallHashMap<K, V, N, S, E, P>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher + Default,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> IntoIterator for SmallHashMap<K, V, N, S, E, P>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    type Item = (K, V);
+    type IntoIter = SmallHashMapIntoIter<K, V, N, E>;
+
+    /// Consumes the map and returns an iterator over owned key-value pairs.
+    fn into_iter(self) -> Self::IntoIter {
+        match self.inner {
+            MapKind::InlineMap(map) => SmallHashMapIntoIter::InlineMap(map.into_iter()),
+            MapKind::HeapMap(map) => match map.into_entries() {
+                TableEntries::Table(iter) => SmallHashMapIntoIter::HeapMap(iter),
+                TableEntries::Drained(iter) => SmallHashMapIntoIter::Drained(iter),
+            },
+        }
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> Extend<(K, V)> for SmallHashMap<K, V, N, S, E, P>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher + Clone,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    /// Extends the map with the contents of an iterator.
+    ///
+    /// The iterator's `size_hint` is used to decide on a single up-front
+    /// transition to heap storage, instead of transitioning partway through.
+    /// As with `HashMap`, only half the hint is counted when the map is
+    /// non-empty, since some keys may already be present. An inline map only
+    /// spills up front when the hint alone exceeds `N`; smaller batches may
+    /// repeat keys and fit inline, so they spill only if they actually
+    /// overflow.
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        let (lower, _) = iter.size_hint();
+        let additional = if self.is_empty() {
+            lower
+        } else {
+            lower.div_ceil(2)
+        };
+        if !self.is_inline() || additional > N {
+            self.reserve(additional);
+        }
+
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
 }
 
-impl<K, V, const N: usize, S> std::iter::FromIterator<(K, V)> for SmallHashMap<K, V, N, S>
+impl<K, V, const N: usize, S, E, P> std::iter::FromIterator<(K, V)>
+    for SmallHashMap<K, V, N, S, E, P>
 where
-    K: Hash + Eq,
+    E: KeyEquivalence<K>,
     S: BuildHasher + Default + Clone,
+    P: TablePolicy<E::Stored<K>, V, S>,
 {
     /// Creates a `SmallHashMap` from an iterator of key-value pairs.
     ///
     /// If the iterator yields more than `N` elements with unique keys,
-    /// the map will automatically transition to heap storage.
+    /// the map will automatically transition to heap storage. Duplicate keys
+    /// keep the last value; if they collapse the map to `N` elements or
+    /// fewer, it ends in inline mode.
     fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
         let iter = iter.into_iter();
-        let (lower, upper) = iter.size_hint();
 
         // Use capacity hint to potentially skip inline storage
-        let capacity_hint = upper.unwrap_or(lower);
-        let mut map = if capacity_hint > N {
-            Self::with_capacity(capacity_hint)
-        } else {
-            Self::new()
-        };
+        let mut map = Self::for_size_hint(iter.size_hint());
 
         map.extend(iter);
+        map.shrink_to_inline_if_fits();
         map
     }
 }
 
-impl<K, V, const N: usize, const M: usize, S, T> PartialEq<SmallHashMap<K, V, M, T>>
-    for SmallHashMap<K, V, N, S>
+impl<K, V, const N: usize, const M: usize, S, T, E, P, Q> PartialEq<SmallHashMap<K, V, M, T, E, Q>>
+    for SmallHashMap<K, V, N, S, E, P>
 where
-    K: Hash + Eq,
+    E: KeyEquivalence<K>,
     V: PartialEq,
     S: BuildHasher,
     T: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+    Q: TablePolicy<E::Stored<K>, V, T>,
 {
     /// Two maps are equal if they contain the same key-value pairs,
     /// regardless of internal storage mode, capacity parameter, or hasher type.
-    fn eq(&self, other: &SmallHashMap<K, V, M, T>) -> bool {
+    fn eq(&self, other: &SmallHashMap<K, V, M, T, E, Q>) -> bool {
         if self.len() != other.len() {
             return false;
         }
@@ -539,68 +2093,355 @@ where
     }
 }
 
-impl<K, V, const N: usize, S> Eq for SmallHashMap<K, V, N, S>
+impl<K, V, const N: usize, S, E, P> Eq for SmallHashMap<K, V, N, S, E, P>
 where
-    K: Hash + Eq,
+    E: KeyEquivalence<K>,
     V: Eq,
     S: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+}
+
+impl<K, V, const N: usize, S, E, P> Hash for SmallHashMap<K, V, N, S, E, P>
+where
+    E: KeyEquivalence<K>,
+    V: Hash,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    /// Hashes the entries independently of their order, so equal maps hash
+    /// the same regardless of storage mode, capacity parameter, or hasher.
+    ///
+    /// Each entry is hashed on its own with a fixed-key hasher and the results
+    /// are combined with a wrapping sum, which is then fed to `state` along
+    /// with the length. Keys are hashed with the map's equivalence strategy,
+    /// so maps that are equal under it hash the same.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut sum: u64 = 0;
+        let mut len: usize = 0;
+        let mut add = |key: &K, value: &V| {
+            // DefaultHasher::new() always uses the same keys.
+            let mut hasher = hash_map::DefaultHasher::new();
+            E::hash(key, &mut hasher);
+            value.hash(&mut hasher);
+            sum = sum.wrapping_add(hasher.finish());
+            len += 1;
+        };
+        match &self.inner {
+            MapKind::InlineMap(map) => {
+                let (keys, values) = map.as_slices();
+                keys.iter().zip(values).for_each(|(k, v)| add(k, v));
+            }
+            MapKind::HeapMap(map) => map
+                .as_hash_map()
+                .iter()
+                .for_each(|(k, v)| add(E::unwrap_ref(k), v)),
+        }
+        state.write_usize(len);
+        state.write_u64(sum);
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> SmallHashMap<K, V, N, S, E, P>
+where
+    E: KeyOrdering<K>,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    /// Returns the entries sorted with the strategy's ordering, with keys
+    /// wrapped so that comparing them uses it too.
+    fn entries_by_key_ordering(&self) -> impl Iterator<Item = (ByKeyOrdering<'_, K, E>, &V)> {
+        SmallHashMapSortedIter::new_by(&self.inner, E::cmp)
+            .map(|(key, value)| (ByKeyOrdering::new(key), value))
+    }
+}
+
+impl<K, V, const N: usize, const M: usize, S, T, E, P, Q> PartialOrd<SmallHashMap<K, V, M, T, E, Q>>
+    for SmallHashMap<K, V, N, S, E, P>
+where
+    E: KeyOrdering<K>,
+    V: PartialOrd,
+    S: BuildHasher,
+    T: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+    Q: TablePolicy<E::Stored<K>, V, T>,
+{
+    /// Compares the entries of both maps as sequences sorted by key, so the
+    /// result agrees with `PartialEq` across storage modes, capacity
+    /// parameters and hashers.
+    ///
+    /// Keys are ordered with the map's [`KeyOrdering`] strategy, which for
+    /// the default strategy is the keys' own `Ord`.
+    fn partial_cmp(&self, other: &SmallHashMap<K, V, M, T, E, Q>) -> Option<Ordering> {
+        self.entries_by_key_ordering()
+            .partial_cmp(other.entries_by_key_ordering())
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> Ord for SmallHashMap<K, V, N, S, E, P>
+where
+    E: KeyOrdering<K>,
+    V: Ord,
+    S: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.entries_by_key_ordering()
+            .cmp(other.entries_by_key_ordering())
+    }
+}
+
+/// A borrowed key compared with the strategy `E`.
+struct ByKeyOrdering<'a, K, E> {
+    key: &'a K,
+    equivalence: PhantomData<fn() -> E>,
+}
+
+impl<'a, K, E> ByKeyOrdering<'a, K, E> {
+    fn new(key: &'a K) -> Self {
+        Self {
+            key,
+            equivalence: PhantomData,
+        }
+    }
+}
+
+impl<K, E: KeyOrdering<K>> PartialEq for ByKeyOrdering<'_, K, E> {
+    fn eq(&self, other: &Self) -> bool {
+        E::eq(self.key, other.key)
+    }
+}
+
+impl<K, E: KeyOrdering<K>> Eq for ByKeyOrdering<'_, K, E> {}
+
+impl<K, E: KeyOrdering<K>> PartialOrd for ByKeyOrdering<'_, K, E> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K, E: KeyOrdering<K>> Ord for ByKeyOrdering<'_, K, E> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        E::cmp(self.key, other.key)
+    }
+}
+
+impl<K, V, const N: usize, S, T, E, P> PartialEq<HashMap<E::Stored<K>, V, T>>
+    for SmallHashMap<K, V, N, S, E, P>
+where
+    E: KeyEquivalence<K>,
+    V: PartialEq,
+    S: BuildHasher,
+    T: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    /// A map equals a `HashMap` holding the same key-value pairs, regardless
+    /// of storage mode or hasher type.
+    ///
+    /// With a custom strategy the `HashMap` holds [`EquivKey`](crate::EquivKey)s, the same
+    /// form `into_hash_map` produces, so both sides compare keys the same
+    /// way.
+    fn eq(&self, other: &HashMap<E::Stored<K>, V, T>) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        self.iter()
+            .all(|(k, v)| other.get(E::wrap_ref(k)) == Some(v))
+    }
+}
+
+impl<K, V, const N: usize, S, T, E, P> PartialEq<SmallHashMap<K, V, N, S, E, P>>
+    for HashMap<E::Stored<K>, V, T>
+where
+    E: KeyEquivalence<K>,
+    V: PartialEq,
+    S: BuildHasher,
+    T: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    fn eq(&self, other: &SmallHashMap<K, V, N, S, E, P>) -> bool {
+        other == self
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> From<HashMap<E::Stored<K>, V, S>>
+    for SmallHashMap<K, V, N, S, E, P>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher + Clone,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    /// Adopts the table as heap storage without rehashing, or moves the
+    /// entries inline if there are at most `N` of them.
+    fn from(map: HashMap<E::Stored<K>, V, S>) -> Self {
+        let hash_builder = map.hasher().clone();
+        let inner = if map.len() <= N {
+            let mut inline_map = InlineMap::new();
+            for (key, value) in map {
+                inline_map.push(E::unwrap(key), value);
+            }
+            MapKind::InlineMap(inline_map)
+        } else {
+            MapKind::HeapMap(HeapMap::from_map(map))
+        };
+        Self {
+            inner,
+            transition_threshold: N,
+            hash_builder,
+        }
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> From<SmallHashMap<K, V, N, S, E, P>>
+    for HashMap<E::Stored<K>, V, S>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    /// See [`SmallHashMap::into_hash_map`].
+    fn from(map: SmallHashMap<K, V, N, S, E, P>) -> Self {
+        map.into_hash_map()
+    }
+}
+
+impl<K, V, const N: usize, const M: usize, S, E, P> From<[(K, V); M]>
+    for SmallHashMap<K, V, N, S, E, P>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher + Default + Clone,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    /// Creates a map from an array of key-value pairs. Duplicate keys keep
+    /// the last value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashMap;
+    ///
+    /// let map: SmallHashMap<&str, i32, 4> = [("a", 1), ("b", 2)].into();
+    /// assert!(map.is_inline());
+    /// assert_eq!(map.get(&"b"), Some(&2));
+    /// ```
+    fn from(entries: [(K, V); M]) -> Self {
+        entries.into_iter().collect()
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> From<Vec<(K, V)>> for SmallHashMap<K, V, N, S, E, P>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher + Default + Clone,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    /// Creates a map from a vector of key-value pairs. Duplicate keys keep
+    /// the last value.
+    fn from(entries: Vec<(K, V)>) -> Self {
+        entries.into_iter().collect()
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> From<SmallHashMap<K, V, N, S, E, P>> for Vec<(K, V)>
+where
+    E: KeyEquivalence<K>,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    /// Moves the entries into a vector, in insertion order for an inline map
+    /// and in arbitrary order for a heap map.
+    fn from(map: SmallHashMap<K, V, N, S, E, P>) -> Self {
+        map.into_entries().collect()
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> From<BTreeMap<K, V>> for SmallHashMap<K, V, N, S, E, P>
+where
+    E: KeyEquivalence<K>,
+    S: BuildHasher + Default + Clone,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    fn from(map: BTreeMap<K, V>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> From<SmallHashMap<K, V, N, S, E, P>> for BTreeMap<K, V>
+where
+    K: Ord,
+    E: KeyEquivalence<K>,
+    P: TablePolicy<E::Stored<K>, V, S>,
 {
+    fn from(map: SmallHashMap<K, V, N, S, E, P>) -> Self {
+        map.into_entries().collect()
+    }
 }
 
 /// Iterator type for SmallHashMap that can handle both InlineMap and HeapMap
 /// iterators.
-pub enum SmallHashMapIter<'a, K, V, const N: usize>
+pub enum SmallHashMapIter<'a, K, V, const N: usize, E: Equivalence = DefaultEquivalence>
 where
     K: 'a,
     V: 'a,
 {
     InlineMap(std::iter::Zip<slice::Iter<'a, K>, slice::Iter<'a, V>>),
-    HeapMap(hash_map::Iter<'a, K, V>),
+    HeapMap(hash_map::Iter<'a, E::Stored<K>, V>),
 }
 
-impl<'a, K, V, const N: usize> Iterator for SmallHashMapIter<'a, K, V, N> {
+impl<'a, K, V, const N: usize, E: Equivalence> Iterator for SmallHashMapIter<'a, K, V, N, E> {
     type Item = (&'a K, &'a V);
 
     fn next(&mut self) -> Option<Self::Item> {
         match self {
             SmallHashMapIter::InlineMap(iter) => iter.next(),
-            SmallHashMapIter::HeapMap(iter) => iter.next(),
+            SmallHashMapIter::HeapMap(iter) => iter.next().map(|(k, v)| (E::unwrap_ref(k), v)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            SmallHashMapIter::InlineMap(iter) => iter.size_hint(),
+            SmallHashMapIter::HeapMap(iter) => iter.size_hint(),
         }
     }
 }
 
 /// Iterator over keys of a SmallHashMap.
-pub enum SmallHashMapKeys<'a, K, V, const N: usize>
+pub enum SmallHashMapKeys<'a, K, V, const N: usize, E: Equivalence = DefaultEquivalence>
 where
     K: 'a,
     V: 'a,
 {
     InlineMap(slice::Iter<'a, K>),
-    HeapMap(hash_map::Keys<'a, K, V>),
+    HeapMap(hash_map::Keys<'a, E::Stored<K>, V>),
 }
 
-impl<'a, K, V, const N: usize> Iterator for SmallHashMapKeys<'a, K, V, N> {
+impl<'a, K, V, const N: usize, E: Equivalence> Iterator for SmallHashMapKeys<'a, K, V, N, E> {
     type Item = &'a K;
 
     fn next(&mut self) -> Option<Self::Item> {
         match self {
             SmallHashMapKeys::InlineMap(iter) => iter.next(),
-            SmallHashMapKeys::HeapMap(iter) => iter.next(),
+            SmallHashMapKeys::HeapMap(iter) => iter.next().map(E::unwrap_ref),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            SmallHashMapKeys::InlineMap(iter) => iter.size_hint(),
+            SmallHashMapKeys::HeapMap(iter) => iter.size_hint(),
         }
     }
 }
 
 /// Iterator over values of a SmallHashMap.
-pub enum SmallHashMapValues<'a, K, V, const N: usize>
+pub enum SmallHashMapValues<'a, K, V, const N: usize, E: Equivalence = DefaultEquivalence>
 where
     K: 'a,
     V: 'a,
 {
     InlineMap(slice::Iter<'a, V>),
-    HeapMap(hash_map::Values<'a, K, V>),
+    HeapMap(hash_map::Values<'a, E::Stored<K>, V>),
 }
 
-impl<'a, K, V, const N: usize> Iterator for SmallHashMapValues<'a, K, V, N> {
+impl<'a, K, V, const N: usize, E: Equivalence> Iterator for SmallHashMapValues<'a, K, V, N, E> {
     type Item = &'a V;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -609,40 +2450,54 @@ impl<'a, K, V, const N: usize> Iterator for SmallHashMapValues<'a, K, V, N> {
             SmallHashMapValues::HeapMap(iter) => iter.next(),
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            SmallHashMapValues::InlineMap(iter) => iter.size_hint(),
+            SmallHashMapValues::HeapMap(iter) => iter.size_hint(),
+        }
+    }
 }
 
 /// Mutable iterator over key-value pairs of a SmallHashMap.
-pub enum SmallHashMapIterMut<'a, K, V, const N: usize>
+pub enum SmallHashMapIterMut<'a, K, V, const N: usize, E: Equivalence = DefaultEquivalence>
 where
     K: 'a,
     V: 'a,
 {
     InlineMap(std::iter::Zip<slice::Iter<'a, K>, slice::IterMut<'a, V>>),
-    HeapMap(hash_map::IterMut<'a, K, V>),
+    HeapMap(hash_map::IterMut<'a, E::Stored<K>, V>),
 }
 
-impl<'a, K, V, const N: usize> Iterator for SmallHashMapIterMut<'a, K, V, N> {
+impl<'a, K, V, const N: usize, E: Equivalence> Iterator for SmallHashMapIterMut<'a, K, V, N, E> {
     type Item = (&'a K, &'a mut V);
 
     fn next(&mut self) -> Option<Self::Item> {
         match self {
             SmallHashMapIterMut::InlineMap(iter) => iter.next(),
-            SmallHashMapIterMut::HeapMap(iter) => iter.next(),
+            SmallHashMapIterMut::HeapMap(iter) => iter.next().map(|(k, v)| (E::unwrap_ref(k), v)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            SmallHashMapIterMut::InlineMap(iter) => iter.size_hint(),
+            SmallHashMapIterMut::HeapMap(iter) => iter.size_hint(),
         }
     }
 }
 
 /// Mutable iterator over values of a SmallHashMap.
-pub enum SmallHashMapValuesMut<'a, K, V, const N: usize>
+pub enum SmallHashMapValuesMut<'a, K, V, const N: usize, E: Equivalence = DefaultEquivalence>
 where
     K: 'a,
     V: 'a,
 {
     InlineMap(slice::IterMut<'a, V>),
-    HeapMap(hash_map::ValuesMut<'a, K, V>),
+    HeapMap(hash_map::ValuesMut<'a, E::Stored<K>, V>),
 }
 
-impl<'a, K, V, const N: usize> Iterator for SmallHashMapValuesMut<'a, K, V, N> {
+impl<'a, K, V, const N: usize, E: Equivalence> Iterator for SmallHashMapValuesMut<'a, K, V, N, E> {
     type Item = &'a mut V;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -651,21 +2506,74 @@ impl<'a, K, V, const N: usize> Iterator for SmallHashMapValuesMut<'a, K, V, N> {
             SmallHashMapValuesMut::HeapMap(iter) => iter.next(),
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            SmallHashMapValuesMut::InlineMap(iter) => iter.size_hint(),
+            SmallHashMapValuesMut::HeapMap(iter) => iter.size_hint(),
+        }
+    }
 }
 
 /// Consuming iterator over key-value pairs of a SmallHashMap.
-pub enum SmallHashMapIntoIter<K, V, const N: usize> {
+pub enum SmallHashMapIntoIter<K, V, const N: usize, E: Equivalence = DefaultEquivalence> {
     InlineMap(std::vec::IntoIter<(K, V)>),
-    HeapMap(hash_map::IntoIter<K, V>),
+    HeapMap(hash_map::IntoIter<E::Stored<K>, V>),
+    /// Entries drained out of a pooled table, which went back to the pool.
+    Drained(std::vec::IntoIter<(E::Stored<K>, V)>),
 }
 
-impl<K, V, const N: usize> Iterator for SmallHashMapIntoIter<K, V, N> {
+impl<K, V, const N: usize, E: Equivalence> Iterator for SmallHashMapIntoIter<K, V, N, E> {
     type Item = (K, V);
 
     fn next(&mut self) -> Option<Self::Item> {
         match self {
             SmallHashMapIntoIter::InlineMap(iter) => iter.next(),
-            SmallHashMapIntoIter::HeapMap(iter) => iter.next(),
+            SmallHashMapIntoIter::HeapMap(iter) => iter.next().map(|(k, v)| (E::unwrap(k), v)),
+            SmallHashMapIntoIter::Drained(iter) => iter.next().map(|(k, v)| (E::unwrap(k), v)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            SmallHashMapIntoIter::InlineMap(iter) => iter.size_hint(),
+            SmallHashMapIntoIter::HeapMap(iter) => iter.size_hint(),
+            SmallHashMapIntoIter::Drained(iter) => iter.size_hint(),
+        }
+    }
+}
+
+/// Where a key lives, as reported by `SmallHashMap::slot_of`.
+enum Slot {
+    /// Found in inline storage at this index.
+    Inline(usize),
+    /// Absent, with the map in inline mode.
+    InlineVacant,
+    /// The map is on the heap; the table is searched separately.
+    Heap,
+}
+
+/// Owning iterator used internally to move entries between maps without
+/// allocating in inline mode.
+enum Entries<K, V, const N: usize, E: Equivalence> {
+    InlineMap(IntoEntries<K, V, N, E>),
+    HeapMap(TableEntries<E::Stored<K>, V>),
+}
+
+impl<K, V, const N: usize, E: Equivalence> Iterator for Entries<K, V, N, E> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Entries::InlineMap(iter) => iter.next(),
+            Entries::HeapMap(iter) => iter.next().map(|(k, v)| (E::unwrap(k), v)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Entries::InlineMap(iter) => iter.size_hint(),
+            Entries::HeapMap(iter) => iter.size_hint(),
         }
     }
 }
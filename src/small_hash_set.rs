@@ -0,0 +1,547 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+
+use super::equivalence::{DefaultEquivalence, Equivalence, KeyEquivalence};
+use super::small_hash_map::{SmallHashMap, SmallHashMapIntoIter, SmallHashMapKeys};
+
+/// An adaptive set that starts with inline storage and transitions to a heap
+/// hash table when it grows beyond `N` elements.
+///
+/// `SmallHashSet` is a thin wrapper around `SmallHashMap<T, (), N, S>` and
+/// shares its storage machinery. Because `()` is zero-sized, the inline tier's
+/// values array occupies no space: an inline set stores only its elements and
+/// a length.
+///
+/// # Type Parameters
+///
+/// - `T`: The element type
+/// - `N`: The inline capacity (stack-allocated storage size)
+/// - `S`: The hasher type, defaults to `RandomState` (same as `std::collections::HashSet`)
+/// - `E`: The [`KeyEquivalence`] strategy used to hash and compare elements,
+///   defaults to the elements' own `Hash` and `Eq`
+///
+/// # Example
+///
+/// ```
+/// use small_hash_map::SmallHashSet;
+///
+/// let mut set: SmallHashSet<&str, 4> = SmallHashSet::new();
+/// assert!(set.insert("a"));
+/// assert!(!set.insert("a"));
+/// assert!(set.contains(&"a"));
+/// assert!(set.is_inline());
+/// ```
+pub struct SmallHashSet<T, const N: usize, S = RandomState, E: Equivalence = DefaultEquivalence> {
+    map: SmallHashMap<T, (), N, S, E>,
+}
+
+impl<T, const N: usize, S, E> Clone for SmallHashSet<T, N, S, E>
+where
+    T: Clone,
+    S: Clone,
+    E: Equivalence<Stored<T>: Clone>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<T, const N: usize, S, E> fmt::Debug for SmallHashSet<T, N, S, E>
+where
+    T: fmt::Debug,
+    E: KeyEquivalence<T>,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T, const N: usize, S, E> SmallHashSet<T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher + Default,
+{
+    /// Creates a new `SmallHashSet` that starts with inline storage.
+    pub fn new() -> Self {
+        Self {
+            map: SmallHashMap::new(),
+        }
+    }
+}
+
+impl<T, const N: usize, S, E> SmallHashSet<T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher + Default + Clone,
+{
+    /// Creates a new `SmallHashSet` with the specified capacity hint.
+    ///
+    /// If the capacity is greater than `N`, it starts with heap storage.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            map: SmallHashMap::with_capacity(capacity),
+        }
+    }
+}
+
+impl<T, const N: usize, S, E> SmallHashSet<T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher,
+{
+    /// Creates a new `SmallHashSet` with the specified hasher.
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            map: SmallHashMap::with_hasher(hash_builder),
+        }
+    }
+
+    /// Creates a new `SmallHashSet` with the specified capacity and hasher.
+    ///
+    /// If the capacity is greater than `N`, it starts with heap storage.
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self
+    where
+        S: Clone,
+    {
+        Self {
+            map: SmallHashMap::with_capacity_and_hasher(capacity, hash_builder),
+        }
+    }
+
+    /// Returns a reference to the set's hasher.
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
+    /// Returns the number of elements in the set.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the set contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the number of elements the set can hold without reallocating
+    /// or transitioning.
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    /// Returns `true` if the set is currently using inline (stack) storage.
+    pub fn is_inline(&self) -> bool {
+        self.map.is_inline()
+    }
+
+    /// Clears the set, removing all elements.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Returns `true` if the set contains the value.
+    pub fn contains(&self, value: &T) -> bool {
+        self.map.contains_key(value)
+    }
+
+    /// Returns a reference to the stored value equal to `value`, if any.
+    pub fn get(&self, value: &T) -> Option<&T> {
+        self.map.get_key_value(value).map(|(k, _)| k)
+    }
+
+    /// Removes a value from the set. Returns whether the value was present.
+    pub fn remove(&mut self, value: &T) -> bool {
+        self.map.remove(value).is_some()
+    }
+
+    /// Removes and returns the stored value equal to `value`, if any.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashSet;
+    ///
+    /// let mut set: SmallHashSet<String, 4> = SmallHashSet::new();
+    /// set.insert("a".to_string());
+    /// assert_eq!(set.take(&"a".to_string()), Some("a".to_string()));
+    /// assert!(set.is_empty());
+    /// ```
+    pub fn take(&mut self, value: &T) -> Option<T> {
+        self.map.remove_entry(value).map(|(k, _)| k)
+    }
+
+    /// Retains only the elements specified by the predicate.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.map.retain(|k, _| f(k));
+    }
+
+    /// Returns an iterator visiting all elements.
+    ///
+    /// For inline storage, the order is insertion order; for heap storage,
+    /// it's arbitrary.
+    pub fn iter(&self) -> SmallHashSetIter<'_, T, N, E> {
+        SmallHashSetIter {
+            iter: self.map.keys(),
+        }
+    }
+
+    /// Visits the values in `self` or `other`, without duplicates.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashSet;
+    ///
+    /// let a: SmallHashSet<i32, 4> = [1, 2, 3].into_iter().collect();
+    /// let b: SmallHashSet<i32, 4> = [2, 3, 4].into_iter().collect();
+    ///
+    /// let mut union: Vec<_> = a.union(&b).copied().collect();
+    /// union.sort();
+    /// assert_eq!(union, [1, 2, 3, 4]);
+    /// ```
+    pub fn union<'a>(&'a self, other: &'a Self) -> SmallHashSetUnion<'a, T, N, S, E> {
+        let (larger, smaller) = if self.len() >= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        SmallHashSetUnion {
+            iter: larger.iter().chain(smaller.difference(larger)),
+        }
+    }
+
+    /// Visits the values in both `self` and `other`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashSet;
+    ///
+    /// let a: SmallHashSet<i32, 4> = [1, 2, 3].into_iter().collect();
+    /// let b: SmallHashSet<i32, 4> = [2, 3, 4].into_iter().collect();
+    ///
+    /// let mut both: Vec<_> = a.intersection(&b).copied().collect();
+    /// both.sort();
+    /// assert_eq!(both, [2, 3]);
+    /// ```
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> SmallHashSetIntersection<'a, T, N, S, E> {
+        let (smaller, larger) = if self.len() <= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        SmallHashSetIntersection {
+            iter: smaller.iter(),
+            other: larger,
+        }
+    }
+
+    /// Visits the values in `self` but not in `other`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashSet;
+    ///
+    /// let a: SmallHashSet<i32, 4> = [1, 2, 3].into_iter().collect();
+    /// let b: SmallHashSet<i32, 4> = [2, 3, 4].into_iter().collect();
+    ///
+    /// assert_eq!(a.difference(&b).collect::<Vec<_>>(), [&1]);
+    /// ```
+    pub fn difference<'a>(&'a self, other: &'a Self) -> SmallHashSetDifference<'a, T, N, S, E> {
+        SmallHashSetDifference {
+            iter: self.iter(),
+            other,
+        }
+    }
+
+    /// Visits the values in `self` or `other`, but not in both.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashSet;
+    ///
+    /// let a: SmallHashSet<i32, 4> = [1, 2, 3].into_iter().collect();
+    /// let b: SmallHashSet<i32, 4> = [2, 3, 4].into_iter().collect();
+    ///
+    /// let mut either: Vec<_> = a.symmetric_difference(&b).copied().collect();
+    /// either.sort();
+    /// assert_eq!(either, [1, 4]);
+    /// ```
+    pub fn symmetric_difference<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> SmallHashSetSymmetricDifference<'a, T, N, S, E> {
+        SmallHashSetSymmetricDifference {
+            iter: self.difference(other).chain(other.difference(self)),
+        }
+    }
+
+    /// Returns `true` if every value in `self` is also in `other`.
+    pub fn is_subset<const M: usize, U>(&self, other: &SmallHashSet<T, M, U, E>) -> bool
+    where
+        U: BuildHasher,
+    {
+        self.len() <= other.len() && self.iter().all(|v| other.contains(v))
+    }
+
+    /// Returns `true` if every value in `other` is also in `self`.
+    pub fn is_superset<const M: usize, U>(&self, other: &SmallHashSet<T, M, U, E>) -> bool
+    where
+        U: BuildHasher,
+    {
+        other.is_subset(self)
+    }
+
+    /// Returns `true` if `self` and `other` have no values in common.
+    pub fn is_disjoint<const M: usize, U>(&self, other: &SmallHashSet<T, M, U, E>) -> bool
+    where
+        U: BuildHasher,
+    {
+        if self.len() <= other.len() {
+            self.iter().all(|v| !other.contains(v))
+        } else {
+            other.iter().all(|v| !self.contains(v))
+        }
+    }
+}
+
+impl<T, const N: usize, S, E> SmallHashSet<T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher + Clone,
+{
+    /// Adds a value to the set. Returns whether the value was newly inserted.
+    ///
+    /// If inserting would exceed the inline capacity, the set automatically
+    /// transitions to heap storage.
+    pub fn insert(&mut self, value: T) -> bool {
+        self.map.try_insert(value, ()).is_ok()
+    }
+
+    /// Adds a value to the set, replacing the existing equal value, if any.
+    /// Returns the replaced value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use small_hash_map::SmallHashSet;
+    ///
+    /// let mut set: SmallHashSet<String, 4> = SmallHashSet::new();
+    /// assert_eq!(set.replace("a".to_string()), None);
+    /// assert_eq!(set.replace("a".to_string()), Some("a".to_string()));
+    /// assert_eq!(set.len(), 1);
+    /// ```
+    pub fn replace(&mut self, value: T) -> Option<T> {
+        self.map.replace_key(value, ())
+    }
+
+    /// Reserves capacity for at least `additional` more elements.
+    ///
+    /// An inline set transitions to heap storage if `len + additional`
+    /// exceeds `N`.
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional);
+    }
+}
+
+impl<T, const N: usize, S, E> Default for SmallHashSet<T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize, const M: usize, S, U, E> PartialEq<SmallHashSet<T, M, U, E>>
+    for SmallHashSet<T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher,
+    U: BuildHasher,
+{
+    /// Two sets are equal if they contain the same values, regardless of
+    /// internal storage mode, capacity parameter, or hasher type.
+    fn eq(&self, other: &SmallHashSet<T, M, U, E>) -> bool {
+        self.len() == other.len() && self.is_subset(other)
+    }
+}
+
+impl<T, const N: usize, S, E> Eq for SmallHashSet<T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher,
+{
+}
+
+impl<T, const N: usize, S, E> IntoIterator for SmallHashSet<T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher,
+{
+    type Item = T;
+    type IntoIter = SmallHashSetIntoIter<T, N, E>;
+
+    /// Consumes the set and returns an iterator over owned values.
+    fn into_iter(self) -> Self::IntoIter {
+        SmallHashSetIntoIter {
+            iter: self.map.into_iter(),
+        }
+    }
+}
+
+impl<'a, T, const N: usize, S, E> IntoIterator for &'a SmallHashSet<T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher,
+{
+    type Item = &'a T;
+    type IntoIter = SmallHashSetIter<'a, T, N, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, const N: usize, S, E> Extend<T> for SmallHashSet<T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher + Clone,
+{
+    /// Extends the set with the contents of an iterator.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.map.extend(iter.into_iter().map(|value| (value, ())));
+    }
+}
+
+impl<T, const N: usize, S, E> std::iter::FromIterator<T> for SmallHashSet<T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher + Default + Clone,
+{
+    /// Creates a `SmallHashSet` from an iterator of values.
+    ///
+    /// The set ends in inline mode whenever its final length fits in `N`.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            map: iter.into_iter().map(|value| (value, ())).collect(),
+        }
+    }
+}
+
+/// Iterator over the values of a SmallHashSet.
+pub struct SmallHashSetIter<'a, T, const N: usize, E: Equivalence = DefaultEquivalence> {
+    iter: SmallHashMapKeys<'a, T, (), N, E>,
+}
+
+impl<'a, T, const N: usize, E: Equivalence> Iterator for SmallHashSetIter<'a, T, N, E> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+/// Consuming iterator over the values of a SmallHashSet.
+pub struct SmallHashSetIntoIter<T, const N: usize, E: Equivalence = DefaultEquivalence> {
+    iter: SmallHashMapIntoIter<T, (), N, E>,
+}
+
+impl<T, const N: usize, E: Equivalence> Iterator for SmallHashSetIntoIter<T, N, E> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(value, ())| value)
+    }
+}
+
+/// Iterator over the union of two SmallHashSets.
+pub struct SmallHashSetUnion<'a, T, const N: usize, S, E: Equivalence = DefaultEquivalence> {
+    iter: std::iter::Chain<SmallHashSetIter<'a, T, N, E>, SmallHashSetDifference<'a, T, N, S, E>>,
+}
+
+impl<'a, T, const N: usize, S, E> Iterator for SmallHashSetUnion<'a, T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+/// Iterator over the intersection of two SmallHashSets.
+pub struct SmallHashSetIntersection<'a, T, const N: usize, S, E: Equivalence = DefaultEquivalence> {
+    iter: SmallHashSetIter<'a, T, N, E>,
+    other: &'a SmallHashSet<T, N, S, E>,
+}
+
+impl<'a, T, const N: usize, S, E> Iterator for SmallHashSetIntersection<'a, T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let other = self.other;
+        self.iter.by_ref().find(|value| other.contains(value))
+    }
+}
+
+/// Iterator over the difference of two SmallHashSets.
+pub struct SmallHashSetDifference<'a, T, const N: usize, S, E: Equivalence = DefaultEquivalence> {
+    iter: SmallHashSetIter<'a, T, N, E>,
+    other: &'a SmallHashSet<T, N, S, E>,
+}
+
+impl<'a, T, const N: usize, S, E> Iterator for SmallHashSetDifference<'a, T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let other = self.other;
+        self.iter.by_ref().find(|value| !other.contains(value))
+    }
+}
+
+/// Iterator over the symmetric difference of two SmallHashSets.
+pub struct SmallHashSetSymmetricDifference<
+    'a,
+    T,
+    const N: usize,
+    S,
+    E: Equivalence = DefaultEquivalence,
+> {
+    iter: std::iter::Chain<
+        SmallHashSetDifference<'a, T, N, S, E>,
+        SmallHashSetDifference<'a, T, N, S, E>,
+    >,
+}
+
+impl<'a, T, const N: usize, S, E> Iterator for SmallHashSetSymmetricDifference<'a, T, N, S, E>
+where
+    E: KeyEquivalence<T>,
+    S: BuildHasher,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
@@ -0,0 +1,199 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::vec;
+
+use super::equivalence::{DefaultEquivalence, Equivalence, KeyEquivalence};
+use super::map::MapKind;
+use super::pool::{NoPool, TablePolicy};
+use super::small_hash_map::SmallHashMap;
+
+/// Iterator over the entries of a `SmallHashMap` in ascending key order.
+///
+/// Created by `SmallHashMap::iter_sorted`. In inline mode the order is kept
+/// in a fixed-size index array, so no allocation takes place; in heap mode
+/// the entry references are collected into a sorted `Vec`.
+pub struct SmallHashMapSortedIter<'a, K, V, const N: usize> {
+    inner: SortedKind<'a, K, V, N>,
+}
+
+enum SortedKind<'a, K, V, const N: usize> {
+    InlineMap {
+        keys: &'a [K],
+        values: &'a [V],
+        order: [usize; N],
+        next: usize,
+    },
+    HeapMap(vec::IntoIter<(&'a K, &'a V)>),
+}
+
+impl<'a, K, V, const N: usize> SmallHashMapSortedIter<'a, K, V, N> {
+    pub(crate) fn new<S, E, P>(inner: &'a MapKind<K, V, N, S, E, P>) -> Self
+    where
+        K: Ord,
+        E: Equivalence,
+        P: TablePolicy<E::Stored<K>, V, S>,
+    {
+        Self::new_by(inner, K::cmp)
+    }
+
+    /// Sorts the entries with `cmp` instead of the keys' own `Ord`.
+    pub(crate) fn new_by<S, E, P, F>(inner: &'a MapKind<K, V, N, S, E, P>, cmp: F) -> Self
+    where
+        E: Equivalence,
+        P: TablePolicy<E::Stored<K>, V, S>,
+        F: Fn(&K, &K) -> Ordering,
+    {
+        let inner = match inner {
+            MapKind::InlineMap(map) => {
+                let (keys, values) = map.as_slices();
+                let mut order: [usize; N] = std::array::from_fn(|i| i);
+                order[..keys.len()].sort_unstable_by(|&a, &b| cmp(&keys[a], &keys[b]));
+                SortedKind::InlineMap {
+                    keys,
+                    values,
+                    order,
+                    next: 0,
+                }
+            }
+            MapKind::HeapMap(map) => {
+                let mut entries: Vec<_> = map
+                    .as_hash_map()
+                    .iter()
+                    .map(|(k, v)| (E::unwrap_ref(k), v))
+                    .collect();
+                entries.sort_unstable_by(|a, b| cmp(a.0, b.0));
+                SortedKind::HeapMap(entries.into_iter())
+            }
+        };
+        Self { inner }
+    }
+}
+
+impl<'a, K, V, const N: usize> Iterator for SmallHashMapSortedIter<'a, K, V, N> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            SortedKind::InlineMap {
+                keys,
+                values,
+                order,
+                next,
+            } => {
+                let keys: &'a [K] = keys;
+                let values: &'a [V] = values;
+                let i = *order[..keys.len()].get(*next)?;
+                *next += 1;
+                Some((&keys[i], &values[i]))
+            }
+            SortedKind::HeapMap(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = match &self.inner {
+            SortedKind::InlineMap { keys, next, .. } => keys.len() - next,
+            SortedKind::HeapMap(iter) => iter.len(),
+        };
+        (remaining, Some(remaining))
+    }
+}
+
+impl<K, V, const N: usize> ExactSizeIterator for SmallHashMapSortedIter<'_, K, V, N> {}
+
+/// Iterator over the keys of a `SmallHashMap` in ascending order.
+///
+/// Created by `SmallHashMap::keys_sorted`.
+pub struct SmallHashMapSortedKeys<'a, K, V, const N: usize> {
+    iter: SmallHashMapSortedIter<'a, K, V, N>,
+}
+
+impl<'a, K, V, const N: usize> SmallHashMapSortedKeys<'a, K, V, N> {
+    pub(crate) fn new(iter: SmallHashMapSortedIter<'a, K, V, N>) -> Self {
+        Self { iter }
+    }
+}
+
+impl<'a, K, V, const N: usize> Iterator for SmallHashMapSortedKeys<'a, K, V, N> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K, V, const N: usize> ExactSizeIterator for SmallHashMapSortedKeys<'_, K, V, N> {}
+
+/// A `Debug` view of a `SmallHashMap` that prints its entries in key order,
+/// along with its storage mode and capacity.
+///
+/// Created by `SmallHashMap::sorted_debug`. Unlike the map's own `Debug`
+/// output, this is stable from run to run, which makes it suitable for
+/// snapshot tests.
+pub struct SortedDebug<
+    'a,
+    K,
+    V,
+    const N: usize,
+    S,
+    E: Equivalence = DefaultEquivalence,
+    P: TablePolicy<E::Stored<K>, V, S> = NoPool,
+> {
+    map: &'a SmallHashMap<K, V, N, S, E, P>,
+}
+
+impl<'a, K, V, const N: usize, S, E, P> SortedDebug<'a, K, V, N, S, E, P>
+where
+    E: Equivalence,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    pub(crate) fn new(map: &'a SmallHashMap<K, V, N, S, E, P>) -> Self {
+        Self { map }
+    }
+}
+
+impl<K, V, const N: usize, S, E, P> fmt::Debug for SortedDebug<'_, K, V, N, S, E, P>
+where
+    K: Ord + fmt::Debug,
+    E: KeyEquivalence<K>,
+    V: fmt::Debug,
+    S: BuildHasher,
+    P: TablePolicy<E::Stored<K>, V, S>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        /// Prints the entries as a map in key order.
+        struct Entries<'a, K, V, const N: usize, S, E, P>(&'a SmallHashMap<K, V, N, S, E, P>)
+        where
+            E: Equivalence,
+            P: TablePolicy<E::Stored<K>, V, S>;
+
+        impl<K, V, const N: usize, S, E, P> fmt::Debug for Entries<'_, K, V, N, S, E, P>
+        where
+            K: Ord + fmt::Debug,
+            E: KeyEquivalence<K>,
+            V: fmt::Debug,
+            S: BuildHasher,
+            P: TablePolicy<E::Stored<K>, V, S>,
+        {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_map().entries(self.0.iter_sorted()).finish()
+            }
+        }
+
+        let storage = if self.map.is_inline() {
+            "inline"
+        } else {
+            "heap"
+        };
+        f.debug_struct("SmallHashMap")
+            .field("storage", &format_args!("{}", storage))
+            .field("capacity", &self.map.capacity())
+            .field("entries", &Entries(self.map))
+            .finish()
+    }
+}
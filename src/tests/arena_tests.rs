@@ -0,0 +1,301 @@
+use crate::{ArenaSmallHashMap, BumpArena, MapAllocator};
+use std::alloc::Layout;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+/// Hashes every key to the same value, so all entries share a probe run.
+#[derive(Default)]
+struct CollidingBuildHasher;
+
+struct CollidingHasher;
+
+impl Hasher for CollidingHasher {
+    fn finish(&self) -> u64 {
+        7
+    }
+
+    fn write(&mut self, _bytes: &[u8]) {}
+}
+
+impl BuildHasher for CollidingBuildHasher {
+    type Hasher = CollidingHasher;
+
+    fn build_hasher(&self) -> CollidingHasher {
+        CollidingHasher
+    }
+}
+
+/// Panics when asked to hash the key 13.
+#[derive(Default)]
+struct UnluckyBuildHasher;
+
+struct UnluckyHasher(u64);
+
+impl Hasher for UnluckyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        assert_ne!(bytes, 13u32.to_ne_bytes(), "unlucky key");
+        for &byte in bytes {
+            self.0 = self.0.wrapping_mul(31).wrapping_add(u64::from(byte));
+        }
+    }
+}
+
+impl BuildHasher for UnluckyBuildHasher {
+    type Hasher = UnluckyHasher;
+
+    fn build_hasher(&self) -> UnluckyHasher {
+        UnluckyHasher(0)
+    }
+}
+
+/// Forwards to a `BumpArena` and counts outstanding allocations.
+#[derive(Default)]
+struct CountingAllocator {
+    arena: BumpArena,
+    live: Cell<isize>,
+}
+
+unsafe impl MapAllocator for CountingAllocator {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        self.live.set(self.live.get() + 1);
+        self.arena.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.live.set(self.live.get() - 1);
+        self.arena.deallocate(ptr, layout);
+    }
+}
+
+#[test]
+fn test_arena_map_spills_into_arena() {
+    let arena = BumpArena::new();
+    let mut map: ArenaSmallHashMap<u32, u32, 4> = ArenaSmallHashMap::new_in(&arena);
+    for i in 0..4 {
+        map.insert(i, i * 10);
+    }
+    assert!(map.is_inline());
+    assert_eq!(arena.allocated_bytes(), 0);
+
+    for i in 4..1000 {
+        assert_eq!(map.insert(i, i * 10), None);
+    }
+    assert!(!map.is_inline());
+    assert!(arena.allocated_bytes() > 0);
+    assert_eq!(map.len(), 1000);
+    assert_eq!(map.insert(500, 0), Some(5000));
+    assert!((0..1000).all(|i| map.contains_key(&i)));
+    assert_eq!(map.iter().count(), 1000);
+}
+
+#[test]
+fn test_arena_map_matches_hash_map_with_collisions() {
+    let arena = BumpArena::with_chunk_size(256);
+    let mut map: ArenaSmallHashMap<u32, u32, 2, BumpArena, CollidingBuildHasher> =
+        ArenaSmallHashMap::new_in(&arena);
+    let mut expected = HashMap::new();
+    for i in 0..200u32 {
+        let key = (i * 7919) % 64;
+        if i % 3 == 0 {
+            assert_eq!(map.remove(&key), expected.remove(&key));
+        } else {
+            assert_eq!(map.insert(key, i), expected.insert(key, i));
+        }
+        assert_eq!(map.len(), expected.len());
+    }
+    for key in 0..64 {
+        assert_eq!(map.get(&key), expected.get(&key));
+    }
+    if let Some(value) = map.get_mut(&1) {
+        *value = 0;
+    }
+    assert!(map.get(&1).is_none_or(|v| *v == 0));
+}
+
+#[test]
+fn test_arena_map_drops_entries_and_returns_memory() {
+    let alloc = CountingAllocator::default();
+    let token = Rc::new(());
+    {
+        let mut map: ArenaSmallHashMap<u32, Rc<()>, 2, CountingAllocator> =
+            ArenaSmallHashMap::with_capacity_in(3, &alloc);
+        assert!(!map.is_inline());
+        map.extend((0..100).map(|i| (i, token.clone())));
+        assert_eq!(Rc::strong_count(&token), 101);
+        assert_eq!(alloc.live.get(), 1);
+
+        map.remove(&0);
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(Rc::strong_count(&token), 1);
+        map.insert(1, token.clone());
+    }
+    assert_eq!(Rc::strong_count(&token), 1);
+    assert_eq!(alloc.live.get(), 0);
+}
+
+#[test]
+fn test_bump_arena_alignment_and_reset() {
+    let mut arena = BumpArena::with_chunk_size(64);
+    for align in [1, 2, 8, 16, 64] {
+        let layout = Layout::from_size_align(24, align).unwrap();
+        let ptr = arena.allocate(layout).unwrap();
+        assert_eq!(ptr.as_ptr() as usize % align, 0);
+    }
+    let large = arena.allocate(Layout::new::<[u64; 100]>()).unwrap();
+    assert_eq!(large.as_ptr() as usize % 8, 0);
+    assert!(arena.chunk_count() > 1);
+
+    arena.reset();
+    assert_eq!(arena.chunk_count(), 1);
+    assert_eq!(arena.allocated_bytes(), 0);
+
+    let map: ArenaSmallHashMap<u8, u8, 1> =
+        (0..10).fold(ArenaSmallHashMap::new_in(&arena), |mut map, i| {
+            map.insert(i, i);
+            map
+        });
+    assert_eq!(map.len(), 10);
+}
+
+#[test]
+fn test_arena_map_keeps_entries_when_hasher_panics_on_spill() {
+    let arena = BumpArena::new();
+    let mut map: ArenaSmallHashMap<u32, String, 4, BumpArena, UnluckyBuildHasher> =
+        ArenaSmallHashMap::new_in(&arena);
+    for key in [1, 13, 2, 3] {
+        map.insert(key, key.to_string());
+    }
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| map.insert(4, "4".to_string())));
+    assert!(result.is_err());
+    assert!(map.is_inline());
+    assert_eq!(map.len(), 4);
+    for key in [1, 13, 2, 3] {
+        assert_eq!(map.get(&key), Some(&key.to_string()));
+    }
+}
+
+#[test]
+fn test_arena_map_entry_api_in_both_modes() {
+    let arena = BumpArena::new();
+    let mut map: ArenaSmallHashMap<u32, u32, 2> = ArenaSmallHashMap::new_in(&arena);
+    *map.entry(1).or_insert(10) += 1;
+    map.entry(2).or_default();
+    assert!(map.is_inline());
+
+    // The vacant insert that spills returns a reference into the table.
+    *map.entry(3).or_insert_with(|| 30) += 1;
+    assert!(!map.is_inline());
+    map.entry(1).and_modify(|v| *v *= 2).or_insert(0);
+    assert_eq!(map.get(&1), Some(&22));
+    assert_eq!(map.get(&3), Some(&31));
+
+    match map.entry(2) {
+        crate::ArenaEntry::Occupied(mut entry) => {
+            assert_eq!(entry.insert(5), 0);
+            assert_eq!(entry.remove_entry(), (2, 5));
+        }
+        crate::ArenaEntry::Vacant(_) => panic!("key 2 should be present"),
+    }
+    match map.entry(2) {
+        crate::ArenaEntry::Vacant(entry) => assert_eq!(entry.into_key(), 2),
+        crate::ArenaEntry::Occupied(_) => panic!("key 2 should be gone"),
+    }
+    assert_eq!(map.len(), 2);
+}
+
+#[test]
+fn test_arena_map_retain_and_iter_mut_with_collisions() {
+    let arena = BumpArena::new();
+    let mut map: ArenaSmallHashMap<u32, u32, 2, BumpArena, CollidingBuildHasher> =
+        ArenaSmallHashMap::new_in(&arena);
+    map.extend((0..50).map(|i| (i, i)));
+    for (_, value) in map.iter_mut() {
+        *value *= 10;
+    }
+
+    let mut visited = Vec::new();
+    map.retain(|&key, _| {
+        visited.push(key);
+        key % 3 == 0
+    });
+    visited.sort_unstable();
+    assert_eq!(visited, (0..50).collect::<Vec<_>>());
+    assert_eq!(map.len(), 17);
+    assert!((0..50).all(|i| map.get(&i) == (i % 3 == 0).then_some(&(i * 10))));
+
+    assert_eq!(map.remove_entry(&3), Some((3, 30)));
+    assert_eq!(map.remove_entry(&3), None);
+    for (_, value) in &mut map {
+        *value += 1;
+    }
+    assert_eq!(map.get(&6), Some(&61));
+}
+
+#[test]
+fn test_arena_map_into_iter_clone_and_eq() {
+    let alloc = CountingAllocator::default();
+    let token = Rc::new(());
+    let mut map: ArenaSmallHashMap<u32, Rc<()>, 2, CountingAllocator> =
+        ArenaSmallHashMap::new_in(&alloc);
+    map.extend((0..20).map(|i| (i, token.clone())));
+
+    let copy = map.clone();
+    assert_eq!(copy, map);
+    assert_eq!(alloc.live.get(), 2);
+    assert_eq!(Rc::strong_count(&token), 41);
+
+    // Dropping a partly consumed iterator drops the rest of the entries.
+    let mut iter = copy.into_iter();
+    assert_eq!(iter.len(), 20);
+    assert!(iter.next().is_some());
+    drop(iter);
+    assert_eq!(Rc::strong_count(&token), 21);
+    assert_eq!(alloc.live.get(), 1);
+
+    let mut keys: Vec<_> = map.into_iter().map(|(key, _)| key).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, (0..20).collect::<Vec<_>>());
+    assert_eq!(Rc::strong_count(&token), 1);
+
+    let arena = BumpArena::new();
+    let mut inline: ArenaSmallHashMap<u32, u32, 4> = ArenaSmallHashMap::new_in(&arena);
+    inline.extend([(1, 1), (2, 2)]);
+    let mut spilled: ArenaSmallHashMap<u32, u32, 1> = ArenaSmallHashMap::new_in(&arena);
+    spilled.extend([(2, 2), (1, 1)]);
+    assert_eq!(inline, spilled);
+    spilled.insert(1, 0);
+    assert_ne!(inline, spilled);
+    assert_eq!(
+        inline.clone().into_iter().collect::<Vec<_>>(),
+        [(1, 1), (2, 2)]
+    );
+}
+
+#[test]
+fn test_arena_map_reserve_allocates_once() {
+    let alloc = CountingAllocator::default();
+    let mut map: ArenaSmallHashMap<u32, u32, 4, CountingAllocator> =
+        ArenaSmallHashMap::new_in(&alloc);
+    map.extend([(1, 1), (2, 2)]);
+    map.reserve(2);
+    assert!(map.is_inline());
+
+    map.reserve(100);
+    assert!(!map.is_inline());
+    assert!(map.capacity() >= 102);
+    let allocated = alloc.arena.allocated_bytes();
+    map.extend((0..102).map(|i| (i, i)));
+    assert_eq!(map.len(), 102);
+    assert_eq!(alloc.arena.allocated_bytes(), allocated);
+    assert_eq!(alloc.live.get(), 1);
+}
@@ -0,0 +1,70 @@
+use crate::{inline_capacity, InlineBudget, InlineMap, SmallHashMap};
+use std::mem::size_of;
+
+/// Checks that the computed capacity fits the budget and that one more
+/// entry wouldn't.
+macro_rules! assert_tight {
+    ($k:ty, $v:ty, $budget:expr) => {{
+        const CAP: usize = inline_capacity::<$k, $v>($budget);
+        assert!(
+            size_of::<InlineMap<$k, $v, CAP>>() <= $budget,
+            "{} x {} in {} bytes",
+            stringify!($k),
+            stringify!($v),
+            $budget
+        );
+        assert!(
+            size_of::<InlineMap<$k, $v, { CAP + 1 }>>() > $budget,
+            "{} x {} in {} bytes",
+            stringify!($k),
+            stringify!($v),
+            $budget
+        );
+        CAP
+    }};
+}
+
+// The expected capacities assume a 64-bit `usize`.
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn test_inline_capacity_matrix() {
+    assert_eq!(assert_tight!(u8, u8, 64), 28);
+    assert_eq!(assert_tight!(u32, u32, 64), 7);
+    assert_eq!(assert_tight!(u64, u64, 64), 3);
+    assert_eq!(assert_tight!(u8, u64, 64), 6);
+    assert_eq!(assert_tight!(u32, u64, 128), 10);
+    assert_eq!(assert_tight!(u64, String, 128), 3);
+    assert_eq!(assert_tight!(String, String, 256), 5);
+    assert_eq!(assert_tight!(&str, u32, 128), 6);
+    assert_eq!(assert_tight!(u128, u8, 256), 14);
+    assert_eq!(assert_tight!(String, [u8; 256], 4096), 14);
+    assert_eq!(assert_tight!(u16, [u8; 3], 100), 17);
+}
+
+#[test]
+fn test_inline_capacity_edge_cases() {
+    // Too small for even the length field.
+    assert_eq!(inline_capacity::<u64, u64>(0), 0);
+    assert_eq!(inline_capacity::<u64, u64>(size_of::<usize>() - 1), 0);
+    // Room for the length but no entries.
+    assert_eq!(inline_capacity::<u64, u64>(size_of::<usize>()), 0);
+    assert_eq!(inline_capacity::<(), ()>(size_of::<usize>()), usize::MAX);
+    assert_eq!(
+        inline_capacity::<u8, ()>(2 * size_of::<usize>()),
+        size_of::<usize>()
+    );
+}
+
+#[test]
+fn test_inline_budget_capacity_drives_map() {
+    type Budget = InlineBudget<u32, u32, 128>;
+    assert_eq!(Budget::CAPACITY, inline_capacity::<u32, u32>(128));
+
+    let mut map: SmallHashMap<u32, u32, { Budget::CAPACITY }> = SmallHashMap::new();
+    for i in 0..Budget::CAPACITY as u32 {
+        map.insert(i, i);
+    }
+    assert!(map.is_inline());
+    map.insert(u32::MAX, 0);
+    assert!(!map.is_inline());
+}
@@ -0,0 +1,205 @@
+use crate::{
+    AsciiCaseInsensitive, CustomEquivalence, EquivKey, KeyEquivalence, SmallHashMap, SmallHashSet,
+    TrimWhitespace,
+};
+use std::cmp::Ordering;
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+type CaseInsensitiveMap<V, const N: usize> =
+    SmallHashMap<String, V, N, RandomState, AsciiCaseInsensitive>;
+
+fn hash_of<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn test_ascii_case_insensitive_in_both_modes() {
+    let mut headers: CaseInsensitiveMap<u32, 2> = SmallHashMap::new();
+    headers.insert("Content-Type".to_string(), 1);
+    headers.insert("content-type".to_string(), 2);
+    assert!(headers.is_inline());
+    assert_eq!(headers.len(), 1);
+
+    headers.insert("Accept".to_string(), 3);
+    headers.insert("Host".to_string(), 4);
+    assert!(!headers.is_inline());
+    assert_eq!(headers.get(&"CONTENT-TYPE".to_string()), Some(&2));
+    assert_eq!(headers.get(&"host".to_string()), Some(&4));
+
+    let (stored, _) = headers.get_key_value(&"accept".to_string()).unwrap();
+    assert_eq!(stored, "Accept");
+    assert_eq!(
+        headers.remove_entry(&"HOST".to_string()),
+        Some(("Host".to_string(), 4))
+    );
+
+    let mut keys: Vec<_> = headers.keys().cloned().collect();
+    keys.sort();
+    // The inline insert of "content-type" replaced the stored key.
+    assert_eq!(keys, ["Accept", "content-type"]);
+}
+
+#[test]
+fn test_strategy_applies_to_entry_api_and_transitions() {
+    let mut map: SmallHashMap<&str, i32, 2, RandomState, TrimWhitespace> = SmallHashMap::new();
+    map.insert("  name ", 1);
+    assert_eq!(map.insert("name", 2), Some(1));
+    *map.get_or_insert_with("\tname\n", || 0) += 10;
+    assert_eq!(map.get(&"name"), Some(&12));
+    assert_eq!(map.get(&"na me"), None);
+
+    // Spill and shrink back, keeping the strategy on both sides.
+    map.extend([("a", 1), ("b", 2), (" a", 3)]);
+    assert!(!map.is_inline());
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.try_insert(" b ", 0).unwrap_err().key(), &"b");
+    map.alter("a ", |value| value.map(|v| v + 1));
+    assert_eq!(map.get(&"a"), Some(&4));
+
+    let map: SmallHashMap<&str, i32, 4, RandomState, TrimWhitespace> = map.into_capacity();
+    assert!(map.is_inline());
+    assert_eq!(map.get(&" b"), Some(&2));
+}
+
+#[test]
+fn test_maps_equal_under_strategy_hash_the_same() {
+    let a: CaseInsensitiveMap<i32, 1> = [("X-Id".to_string(), 1), ("Host".to_string(), 2)].into();
+    let b: CaseInsensitiveMap<i32, 4> = [("host".to_string(), 2), ("x-id".to_string(), 1)].into();
+    assert!(!a.is_inline());
+    assert!(b.is_inline());
+    assert_eq!(a, b);
+
+    let b: CaseInsensitiveMap<i32, 1> = b.into_capacity();
+    assert_eq!(hash_of(&a), hash_of(&b));
+}
+
+#[test]
+fn test_try_from_iter_reports_first_spelling_in_both_modes() {
+    let pairs = [("Host", 1), ("HOST", 2)].map(|(k, v)| (k.to_string(), v));
+    let err = CaseInsensitiveMap::<u32, 4>::try_from_iter(pairs).unwrap_err();
+    assert_eq!(err.key(), "Host");
+
+    let pairs = ["A", "B", "C", "Host", "host"].map(|k| (k.to_string(), 0));
+    let err = CaseInsensitiveMap::<u32, 2>::try_from_iter(pairs).unwrap_err();
+    assert_eq!(err.into_key(), "Host");
+}
+
+#[test]
+fn test_cursor_yields_stored_keys() {
+    let mut headers: CaseInsensitiveMap<u32, 2> = SmallHashMap::new();
+    for (i, name) in ["Host", "Accept", "X-Trace"].into_iter().enumerate() {
+        headers.insert(name.to_string(), i as u32);
+    }
+    assert!(!headers.is_inline());
+
+    let mut names = Vec::new();
+    let mut cursor = headers.cursor_mut();
+    while let Some((name, _)) = cursor.current() {
+        names.push(name.clone());
+        cursor.move_next();
+    }
+    drop(cursor);
+    names.sort();
+    assert_eq!(names, ["Accept", "Host", "X-Trace"]);
+    assert_eq!(headers.len(), 3);
+}
+
+#[test]
+fn test_set_uses_strategy() {
+    let mut set: SmallHashSet<&str, 2, RandomState, TrimWhitespace> = SmallHashSet::new();
+    assert!(set.insert("a"));
+    assert!(!set.insert(" a "));
+    assert!(set.insert("b"));
+    assert!(set.insert("c"));
+    assert!(!set.is_inline());
+    assert!(set.contains(&"c  "));
+    assert_eq!(set.get(&" b"), Some(&"b"));
+
+    let other: SmallHashSet<&str, 4, RandomState, TrimWhitespace> =
+        ["a ", " b", "c"].into_iter().collect();
+    assert!(other.is_inline());
+    assert_eq!(set, other);
+}
+
+#[test]
+fn test_ordering_uses_strategy() {
+    let a: CaseInsensitiveMap<u32, 2> = [("Host", 1), ("Accept", 2), ("X-Id", 3)]
+        .map(|(k, v)| (k.to_string(), v))
+        .into();
+    let b: CaseInsensitiveMap<u32, 2> = [("x-id", 3), ("HOST", 1), ("accept", 2)]
+        .map(|(k, v)| (k.to_string(), v))
+        .into();
+    assert_eq!(a, b);
+    assert_eq!(a.cmp(&b), Ordering::Equal);
+
+    // Case is folded before comparing, although b'B' < b'a'.
+    let lower: CaseInsensitiveMap<u32, 4> = [("a".to_string(), 0)].into();
+    let upper: CaseInsensitiveMap<u32, 4> = [("B".to_string(), 0)].into();
+    assert!(lower < upper);
+}
+
+#[test]
+fn test_eq_with_hash_map_of_stored_keys() {
+    let map: CaseInsensitiveMap<u32, 2> = [("Host", 1), ("Accept", 2), ("X-Id", 3)]
+        .map(|(k, v)| (k.to_string(), v))
+        .into();
+    let table: HashMap<EquivKey<String, AsciiCaseInsensitive>, u32> =
+        [("host", 1), ("ACCEPT", 2), ("x-id", 3)]
+            .map(|(k, v)| (EquivKey::new(k.to_string()), v))
+            .into();
+    assert!(map == table);
+    assert!(table == map);
+}
+
+#[test]
+fn test_custom_strategy() {
+    /// Compares numbers by their last digit.
+    struct LastDigit;
+
+    impl CustomEquivalence for LastDigit {}
+
+    impl KeyEquivalence<u32> for LastDigit {
+        fn hash<H: Hasher>(key: &u32, state: &mut H) {
+            (key % 10).hash(state);
+        }
+
+        fn eq(a: &u32, b: &u32) -> bool {
+            a % 10 == b % 10
+        }
+    }
+
+    let mut map: SmallHashMap<u32, &str, 2, RandomState, LastDigit> = SmallHashMap::new();
+    for (key, value) in [(1, "a"), (12, "b"), (21, "c"), (3, "d")] {
+        map.insert(key, value);
+    }
+    assert!(!map.is_inline());
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(&41), Some(&"c"));
+    assert_eq!(
+        map.as_heap().unwrap().get(EquivKey::from_ref(&2)),
+        Some(&"b")
+    );
+}
+
+#[test]
+fn test_strategies_keep_hash_consistent_with_eq() {
+    let a: EquivKey<&str, AsciiCaseInsensitive> = EquivKey::new("X-Request-Id");
+    let b = EquivKey::new("x-request-id");
+    assert_eq!(a, b);
+    assert_eq!(hash_of(&a), hash_of(&b));
+    assert_ne!(a, EquivKey::new("x-request"));
+
+    let c: EquivKey<&str, TrimWhitespace> = EquivKey::new(" id ");
+    let d = EquivKey::new("id");
+    assert_eq!(c, d);
+    assert_eq!(hash_of(&c), hash_of(&d));
+
+    assert!(<AsciiCaseInsensitive as KeyEquivalence<&str>>::eq(
+        &"ab", &"AB"
+    ));
+    assert_eq!(format!("{:?}", a), "\"X-Request-Id\"");
+}
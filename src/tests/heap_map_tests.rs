@@ -0,0 +1,66 @@
+use crate::HeapMap;
+use std::collections::HashMap;
+
+#[test]
+fn test_heap_map_borrowed_lookups() {
+    let mut map: HeapMap<String, i32> = HeapMap::new();
+    map.insert("one".to_string(), 1);
+    map.insert("two".to_string(), 2);
+
+    assert_eq!(map.get("one"), Some(&1));
+    assert!(map.contains_key("two"));
+    *map.get_mut("two").unwrap() += 20;
+    assert_eq!(map.get_key_value("two"), Some((&"two".to_string(), &22)));
+    assert_eq!(map.remove_entry("one"), Some(("one".to_string(), 1)));
+    assert_eq!(map.remove("two"), Some(22));
+    assert!(map.is_empty());
+}
+
+#[test]
+fn test_heap_map_entry_and_capacity() {
+    let mut map: HeapMap<i32, Vec<i32>> = HeapMap::new();
+    for i in 0..20 {
+        map.entry(i % 3).or_default().push(i);
+    }
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(&0).map(Vec::len), Some(7));
+
+    map.reserve(100);
+    assert!(map.capacity() >= 103);
+    map.shrink_to(10);
+    assert!(map.capacity() >= 10 && map.capacity() < 103);
+    map.shrink_to_fit();
+    assert!(map.capacity() >= 3);
+
+    let drained: HashMap<i32, Vec<i32>> = map.drain().collect();
+    assert_eq!(drained.len(), 3);
+    assert!(map.is_empty());
+}
+
+#[test]
+fn test_heap_map_hash_map_round_trip() {
+    let std_map: HashMap<i32, i32> = (0..10).map(|i| (i, i * i)).collect();
+    let capacity = std_map.capacity();
+
+    let mut map = HeapMap::from(std_map);
+    assert_eq!(map.capacity(), capacity);
+    assert_eq!(map.as_hash_map().get(&3), Some(&9));
+    map.as_hash_map_mut().insert(10, 100);
+
+    let collected: HeapMap<i32, i32> = (0..11).map(|i| (i, i * i)).collect();
+    assert_eq!(map, collected);
+
+    let std_map: HashMap<i32, i32> = map.into();
+    assert_eq!(std_map.len(), 11);
+    assert_eq!(HeapMap::from(std_map).into_inner().get(&10), Some(&100));
+}
+
+#[test]
+fn test_heap_map_may_outlive_borrowed_keys() {
+    // HeapMap has no destructor of its own, so, as with `HashMap`, the
+    // borrow checker lets the keys be dropped first.
+    let mut map: HeapMap<&String, i32> = HeapMap::new();
+    let key = String::from("key");
+    map.insert(&key, 1);
+    assert_eq!(map.get(&&key), Some(&1));
+}
@@ -0,0 +1,198 @@
+use crate::{CachedSmallHashMap, SmallHashMap};
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::mem::size_of;
+use std::rc::Rc;
+
+/// Counts how many keys have been hashed by the maps built from it.
+#[derive(Clone, Default)]
+struct CountingBuildHasher {
+    hashes: Rc<Cell<usize>>,
+}
+
+impl BuildHasher for CountingBuildHasher {
+    type Hasher = DefaultHasher;
+
+    fn build_hasher(&self) -> DefaultHasher {
+        self.hashes.set(self.hashes.get() + 1);
+        DefaultHasher::new()
+    }
+}
+
+fn spilled_map() -> (
+    CachedSmallHashMap<u32, u32, 4, CountingBuildHasher>,
+    Rc<Cell<usize>>,
+) {
+    let hasher = CountingBuildHasher::default();
+    let hashes = hasher.hashes.clone();
+    let mut map = CachedSmallHashMap::with_hasher(hasher);
+    map.extend((0..100).map(|i| (i, i * 10)));
+    assert!(!map.is_inline());
+    (map, hashes)
+}
+
+#[test]
+fn test_cached_map_hot_lookups_skip_hashing() {
+    let (map, hashes) = spilled_map();
+    let before = hashes.get();
+    for _ in 0..10 {
+        assert_eq!(map.get(&42), Some(&420));
+        assert!(map.contains_key(&42));
+    }
+    assert_eq!(hashes.get() - before, 1);
+
+    // Misses aren't cached.
+    assert_eq!(map.get(&1000), None);
+    assert_eq!(map.get(&1000), None);
+    assert_eq!(hashes.get() - before, 3);
+}
+
+#[test]
+fn test_cached_map_keys_sharing_a_slot_evict_each_other() {
+    let hasher = CountingBuildHasher::default();
+    let hashes = hasher.hashes.clone();
+    let mut map: CachedSmallHashMap<u32, u32, 1, CountingBuildHasher> =
+        CachedSmallHashMap::with_hasher(hasher);
+    map.extend([(1, 10), (2, 20)]);
+    assert!(!map.is_inline());
+
+    let before = hashes.get();
+    for _ in 0..3 {
+        assert_eq!(map.get(&1), Some(&10));
+        assert_eq!(map.get(&2), Some(&20));
+    }
+    assert_eq!(hashes.get() - before, 6);
+    assert_eq!(map.get(&2), Some(&20));
+    assert_eq!(hashes.get() - before, 6);
+}
+
+#[test]
+fn test_cached_map_is_no_larger_than_plain_map() {
+    assert_eq!(
+        size_of::<CachedSmallHashMap<String, String, 4>>(),
+        size_of::<SmallHashMap<String, String, 4>>()
+    );
+}
+
+#[test]
+fn test_cached_map_stays_coherent_through_mutation() {
+    let (mut map, _) = spilled_map();
+    let hot = [1, 2, 3, 4];
+    let warm = |map: &CachedSmallHashMap<u32, u32, 4, CountingBuildHasher>| {
+        hot.iter()
+            .map(|key| map.get(key).copied())
+            .collect::<Vec<_>>()
+    };
+
+    warm(&map);
+    map.insert(1, 0);
+    map.remove(&2);
+    assert_eq!(warm(&map), [Some(0), None, Some(30), Some(40)]);
+
+    map.retain(|key, _| *key != 3);
+    assert_eq!(warm(&map), [Some(0), None, None, Some(40)]);
+
+    for (_, value) in map.iter_mut() {
+        *value += 1;
+    }
+    *map.get_mut(&4).unwrap() = 7;
+    assert_eq!(warm(&map), [Some(1), None, None, Some(7)]);
+
+    // Growing the table moves every value.
+    map.extend((100..1000).map(|i| (i, i)));
+    assert_eq!(warm(&map), [Some(1), None, None, Some(7)]);
+
+    map.clear();
+    assert_eq!(warm(&map), [None; 4]);
+}
+
+#[test]
+fn test_cached_map_matches_hash_map() {
+    let mut map: CachedSmallHashMap<String, Rc<u32>, 2> = CachedSmallHashMap::new();
+    let mut expected = HashMap::new();
+    for i in 0..300u32 {
+        let key = ((i * 7919) % 37).to_string();
+        match i % 4 {
+            0 => assert_eq!(map.remove(&key), expected.remove(&key)),
+            _ => assert_eq!(
+                map.insert(key.clone(), Rc::new(i)),
+                expected.insert(key, Rc::new(i))
+            ),
+        }
+        for probe in ["0", "5", "11", "36"] {
+            let probe = probe.to_string();
+            assert_eq!(map.get(&probe), expected.get(&probe));
+        }
+        assert_eq!(map.len(), expected.len());
+    }
+    let mut entries: Vec<_> = map.iter().map(|(k, v)| (k.clone(), **v)).collect();
+    let mut expected: Vec<_> = expected.into_iter().map(|(k, v)| (k, *v)).collect();
+    entries.sort();
+    expected.sort();
+    assert_eq!(entries, expected);
+}
+
+#[test]
+fn test_cached_map_in_place_updates_clear_cache() {
+    let (mut map, hashes) = spilled_map();
+    assert_eq!(map.get(&5), Some(&50));
+    *map.get_or_insert_with(5, || 0) += 1;
+    let before = hashes.get();
+    assert_eq!(map.get(&5), Some(&51));
+    assert_eq!(hashes.get() - before, 1);
+
+    map.upsert(5, 0, |value| *value += 1);
+    assert_eq!(map.get(&5), Some(&52));
+    map.alter(5, |_| None);
+    assert_eq!(map.get(&5), None);
+    *map.get_or_default(500) += 3;
+    assert_eq!(map.get_key_value(&500), Some((&500, &3)));
+    assert_eq!(map.remove_entry(&500), Some((500, 3)));
+    assert_eq!(map.get(&500), None);
+}
+
+#[test]
+fn test_cached_map_cold_lookups_do_not_clone_keys() {
+    /// Panics if cloned.
+    #[derive(PartialEq, Eq, Hash)]
+    struct NoClone(u32);
+
+    impl Clone for NoClone {
+        fn clone(&self) -> Self {
+            panic!("the cache cloned a key");
+        }
+    }
+
+    let map: CachedSmallHashMap<NoClone, u32, 2> = (0..10).map(|i| (NoClone(i), i)).collect();
+    assert!(!map.is_inline());
+    for i in 0..10 {
+        assert_eq!(map.get(&NoClone(i)), Some(&i));
+    }
+    assert_eq!(map.get_key_value(&NoClone(9)).map(|(k, _)| k.0), Some(9));
+}
+
+#[test]
+fn test_cached_map_clone_eq_and_into_iter() {
+    let (map, _) = spilled_map();
+    map.get(&1);
+    let copy = map.clone();
+    assert_eq!(copy, map);
+
+    let mut other: CachedSmallHashMap<u32, u32, 4, CountingBuildHasher> =
+        map.iter().map(|(&k, &v)| (k, v)).collect();
+    assert_eq!(other, map);
+    other.insert(1, 0);
+    assert_ne!(other, map);
+
+    let mut entries: Vec<_> = copy.into_iter().collect();
+    entries.sort_unstable();
+    assert_eq!(entries, (0..100).map(|i| (i, i * 10)).collect::<Vec<_>>());
+    assert_eq!(map.iter().count(), 100);
+
+    fn assert_send<T: Send>(_: &T) {}
+    let map: CachedSmallHashMap<String, u32, 4> = CachedSmallHashMap::new();
+    assert_send(&map);
+    std::thread::spawn(move || drop(map)).join().unwrap();
+}
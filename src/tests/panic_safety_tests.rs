@@ -0,0 +1,316 @@
+use crate::{InlineMap, SmallHashMap};
+use std::cell::Cell;
+use std::hash::{Hash, Hasher};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+thread_local! {
+    /// Number of `Probe`s currently alive on this thread.
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+    /// Panic on the nth `eq` from now; 0 disables.
+    static PANIC_ON_EQ: Cell<usize> = const { Cell::new(0) };
+    /// Panic on the nth `clone` from now; 0 disables.
+    static PANIC_ON_CLONE: Cell<usize> = const { Cell::new(0) };
+    /// Panic on the nth `hash` from now; 0 disables.
+    static PANIC_ON_HASH: Cell<usize> = const { Cell::new(0) };
+    /// Panic when the probe with this id is dropped.
+    static PANIC_ON_DROP: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+fn countdown(cell: &'static std::thread::LocalKey<Cell<usize>>, what: &str) {
+    cell.with(|c| match c.get() {
+        0 => {}
+        1 => {
+            c.set(0);
+            panic!("{} panicked", what);
+        }
+        n => c.set(n - 1),
+    });
+}
+
+fn live() -> isize {
+    LIVE.with(Cell::get)
+}
+
+/// A key or value that counts live instances and can be told to panic in
+/// `eq`, `clone`, `hash` or `drop`.
+#[derive(Debug)]
+struct Probe(u32);
+
+impl Probe {
+    fn new(id: u32) -> Self {
+        LIVE.with(|c| c.set(c.get() + 1));
+        Probe(id)
+    }
+}
+
+impl Clone for Probe {
+    fn clone(&self) -> Self {
+        countdown(&PANIC_ON_CLONE, "clone");
+        Probe::new(self.0)
+    }
+}
+
+impl PartialEq for Probe {
+    fn eq(&self, other: &Self) -> bool {
+        countdown(&PANIC_ON_EQ, "eq");
+        self.0 == other.0
+    }
+}
+
+impl Eq for Probe {}
+
+impl Hash for Probe {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        countdown(&PANIC_ON_HASH, "hash");
+        self.0.hash(state);
+    }
+}
+
+impl Drop for Probe {
+    fn drop(&mut self) {
+        LIVE.with(|c| c.set(c.get() - 1));
+        if PANIC_ON_DROP.with(|c| c.get()) == Some(self.0) {
+            PANIC_ON_DROP.with(|c| c.set(None));
+            panic!("drop panicked");
+        }
+    }
+}
+
+fn probe_map<const N: usize>(ids: std::ops::Range<u32>) -> InlineMap<Probe, Probe, N> {
+    let mut map = InlineMap::new();
+    for id in ids {
+        map.insert(Probe::new(id), Probe::new(id * 10));
+    }
+    map
+}
+
+fn ids<const N: usize>(map: &InlineMap<Probe, Probe, N>) -> Vec<u32> {
+    map.keys().map(|k| k.0).collect()
+}
+
+#[test]
+fn test_clone_panic_drops_partial_clone() {
+    let map = probe_map::<8>(0..5);
+    assert_eq!(live(), 10);
+
+    PANIC_ON_CLONE.with(|c| c.set(6));
+    let result = catch_unwind(AssertUnwindSafe(|| map.clone()));
+    assert!(result.is_err());
+    assert_eq!(live(), 10);
+    assert_eq!(ids(&map), [0, 1, 2, 3, 4]);
+
+    drop(map);
+    assert_eq!(live(), 0);
+}
+
+#[test]
+fn test_eq_panic_during_insert_leaves_map_intact() {
+    let mut map = probe_map::<4>(0..3);
+
+    PANIC_ON_EQ.with(|c| c.set(2));
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        map.insert(Probe::new(2), Probe::new(99));
+    }));
+    assert!(result.is_err());
+    assert_eq!(ids(&map), [0, 1, 2]);
+    assert_eq!(live(), 6);
+
+    map.insert(Probe::new(3), Probe::new(30));
+    assert_eq!(map.len(), 4);
+    drop(map);
+    assert_eq!(live(), 0);
+}
+
+#[test]
+fn test_key_drop_panic_when_replacing_key() {
+    let mut map = probe_map::<4>(0..3);
+    let probe = Probe::new(1);
+    let index = map.find_key_index(&probe);
+    drop(probe);
+
+    PANIC_ON_DROP.with(|c| c.set(Some(1)));
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        map.insert_with_hint(Probe::new(1), Probe::new(11), index);
+    }));
+    assert!(result.is_err());
+    assert_eq!(ids(&map), [0, 1, 2]);
+    let values: Vec<u32> = map.values().map(|v| v.0).collect();
+    assert_eq!(values, [0, 11, 20]);
+    // The old value was handed back and dropped while unwinding.
+    assert_eq!(live(), 6);
+
+    drop(map);
+    assert_eq!(live(), 0);
+}
+
+#[test]
+fn test_retain_predicate_panic_keeps_unvisited_entries() {
+    let mut map = probe_map::<8>(0..6);
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        map.retain(|k, _| {
+            if k.0 == 3 {
+                panic!("predicate panicked");
+            }
+            k.0 % 2 == 1
+        });
+    }));
+    assert!(result.is_err());
+    // 0 and 2 were removed before the panic; 3.. were never visited.
+    assert_eq!(ids(&map), [1, 3, 4, 5]);
+    assert_eq!(live(), 8);
+
+    drop(map);
+    assert_eq!(live(), 0);
+}
+
+#[test]
+fn test_retain_drop_panic_keeps_map_consistent() {
+    let mut map = probe_map::<8>(0..6);
+
+    PANIC_ON_DROP.with(|c| c.set(Some(2)));
+    let result = catch_unwind(AssertUnwindSafe(|| map.retain(|k, _| k.0 % 2 == 1)));
+    assert!(result.is_err());
+    assert_eq!(ids(&map), [1, 3, 4, 5]);
+    assert_eq!(live(), 8);
+
+    map.retain(|k, _| k.0 % 2 == 1);
+    assert_eq!(ids(&map), [1, 3, 5]);
+    drop(map);
+    assert_eq!(live(), 0);
+}
+
+#[test]
+fn test_clear_and_drop_with_panicking_destructor() {
+    let mut map = probe_map::<8>(0..5);
+    PANIC_ON_DROP.with(|c| c.set(Some(2)));
+    assert!(catch_unwind(AssertUnwindSafe(|| map.clear())).is_err());
+    assert!(map.is_empty());
+    assert_eq!(live(), 0);
+
+    let map = probe_map::<8>(0..5);
+    PANIC_ON_DROP.with(|c| c.set(Some(30)));
+    assert!(catch_unwind(AssertUnwindSafe(|| drop(map))).is_err());
+    assert_eq!(live(), 0);
+}
+
+#[test]
+fn test_small_hash_map_survives_panics_in_both_modes() {
+    let mut map: SmallHashMap<Probe, Probe, 2> = SmallHashMap::new();
+    map.insert(Probe::new(0), Probe::new(0));
+    map.insert(Probe::new(1), Probe::new(10));
+
+    PANIC_ON_EQ.with(|c| c.set(1));
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        map.insert(Probe::new(2), Probe::new(20));
+    }));
+    assert!(result.is_err());
+    assert!(map.is_inline());
+    assert_eq!(map.len(), 2);
+
+    map.insert(Probe::new(2), Probe::new(20));
+    assert!(!map.is_inline());
+    PANIC_ON_CLONE.with(|c| c.set(3));
+    assert!(catch_unwind(AssertUnwindSafe(|| map.clone())).is_err());
+    assert_eq!(live(), 6);
+
+    drop(map);
+    assert_eq!(live(), 0);
+}
+
+#[test]
+fn test_reseed_hash_panic_keeps_entries() {
+    let mut map: SmallHashMap<Probe, Probe, 2> = SmallHashMap::new();
+    for id in 0..6 {
+        map.insert(Probe::new(id), Probe::new(id * 10));
+    }
+
+    PANIC_ON_HASH.with(|c| c.set(4));
+    assert!(catch_unwind(AssertUnwindSafe(|| map.reseed())).is_err());
+    assert_eq!(map.len(), 6);
+    assert!((0..6).all(|id| map.get(&Probe::new(id)).map(|v| v.0) == Some(id * 10)));
+
+    map.reseed();
+    assert_eq!(map.len(), 6);
+    drop(map);
+    assert_eq!(live(), 0);
+}
+
+#[test]
+fn test_union_with_merge_panic_removes_entry_in_both_modes() {
+    for len in [2, 6] {
+        let mut map: SmallHashMap<Probe, Probe, 4> = SmallHashMap::new();
+        for id in 0..len {
+            map.insert(Probe::new(id), Probe::new(id * 10));
+        }
+        let inline = map.is_inline();
+        let mut other: SmallHashMap<Probe, Probe, 4> = SmallHashMap::new();
+        other.insert(Probe::new(1), Probe::new(11));
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            map.union_with(other, |_, _, _| panic!("merge panicked"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(live(), 0, "inline: {inline}");
+    }
+}
+
+#[test]
+fn test_split_off_predicate_panic_keeps_unvisited_entries() {
+    for spilled in [false, true] {
+        let mut map: SmallHashMap<Probe, Probe, 8> = SmallHashMap::new();
+        if spilled {
+            map.reserve(16);
+        }
+        for id in 0..6 {
+            map.insert(Probe::new(id), Probe::new(id * 10));
+        }
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            map.split_off(|k, _| {
+                if k.0 == 3 {
+                    panic!("predicate panicked");
+                }
+                k.0 % 2 == 0
+            })
+        }));
+        assert!(result.is_err());
+        // Odd keys are never split off, and the panicking key stays put.
+        for id in [1, 3, 5] {
+            assert!(map.contains_key(&Probe::new(id)));
+        }
+        assert_eq!(live(), 2 * map.len() as isize);
+
+        drop(map);
+        assert_eq!(live(), 0);
+    }
+}
+
+#[test]
+fn test_alter_panic_removes_entry_in_both_modes() {
+    for spilled in [false, true] {
+        let mut map: SmallHashMap<Probe, Probe, 8> = SmallHashMap::new();
+        if spilled {
+            map.reserve(16);
+        }
+        for id in 0..4 {
+            map.insert(Probe::new(id), Probe::new(id * 10));
+        }
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            map.alter(Probe::new(2), |_| panic!("alter panicked"));
+        }));
+        assert!(result.is_err());
+        assert_eq!(map.len(), 3);
+        assert!(!map.contains_key(&Probe::new(2)));
+
+        map.alter(Probe::new(1), |_| None);
+        map.alter(Probe::new(3), |value| value.map(|v| Probe::new(v.0 + 1)));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&Probe::new(3)).map(|v| v.0), Some(31));
+        assert_eq!(live(), 4);
+
+        drop(map);
+        assert_eq!(live(), 0);
+    }
+}
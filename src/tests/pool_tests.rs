@@ -0,0 +1,154 @@
+use crate::{HeapMap, PooledSmallHashMap, SmallHashMap, TablePool, TablePoolStats};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
+use std::rc::Rc;
+
+type Plain = BuildHasherDefault<DefaultHasher>;
+
+/// A hasher whose instances only compare equal when their seeds match.
+#[derive(Debug, Clone, PartialEq)]
+struct Seeded(u64);
+
+impl BuildHasher for Seeded {
+    type Hasher = DefaultHasher;
+
+    fn build_hasher(&self) -> DefaultHasher {
+        let mut hasher = DefaultHasher::new();
+        hasher.write_u64(self.0);
+        hasher
+    }
+}
+
+fn spill<S: BuildHasher + Clone + PartialEq + 'static>(
+    map: &mut PooledSmallHashMap<u32, u32, 4, S>,
+    len: u32,
+) {
+    map.extend((0..len).map(|i| (i, i)));
+    assert!(!map.is_inline());
+}
+
+#[test]
+fn test_pooled_spills_reuse_tables() {
+    for _ in 0..5 {
+        let mut map: PooledSmallHashMap<u32, u32, 4, Plain> = PooledSmallHashMap::new();
+        spill(&mut map, 6);
+        assert_eq!(map.get(&5), Some(&5));
+    }
+    assert_eq!(
+        TablePool::stats(),
+        TablePoolStats {
+            hits: 4,
+            misses: 1,
+            returned: 5,
+            discarded: 0,
+        }
+    );
+}
+
+#[test]
+fn test_unpooled_maps_bypass_pool() {
+    let mut map: SmallHashMap<u32, u32, 4> = SmallHashMap::new();
+    map.extend((0..6).map(|i| (i, i)));
+    drop(map);
+
+    let mut pooled: PooledSmallHashMap<u32, u32, 4, Plain> = PooledSmallHashMap::new();
+    spill(&mut pooled, 6);
+    let mut cloned = pooled.clone();
+    cloned.insert(100, 100);
+    drop((pooled, cloned));
+    assert_eq!(TablePool::stats().misses, 1);
+    assert_eq!(TablePool::stats().returned, 2);
+}
+
+#[test]
+fn test_pool_cap_discards_extra_tables() {
+    TablePool::set_max_per_bucket(1);
+    let maps: Vec<PooledSmallHashMap<u32, u32, 4, Plain>> = (0..3)
+        .map(|_| {
+            let mut map = PooledSmallHashMap::new();
+            spill(&mut map, 6);
+            map
+        })
+        .collect();
+    drop(maps);
+    let stats = TablePool::stats();
+    assert_eq!((stats.returned, stats.discarded), (1, 2));
+
+    TablePool::clear();
+    TablePool::reset_stats();
+    let mut map: PooledSmallHashMap<u32, u32, 4, Plain> = PooledSmallHashMap::new();
+    spill(&mut map, 6);
+    assert_eq!(TablePool::stats().misses, 1);
+}
+
+#[test]
+fn test_large_spill_skips_small_tables() {
+    let mut small: PooledSmallHashMap<u32, u32, 4, Plain> = PooledSmallHashMap::new();
+    spill(&mut small, 6);
+    drop(small);
+
+    let mut large: PooledSmallHashMap<u32, u32, 4, Plain> = PooledSmallHashMap::new();
+    large.reserve(1000);
+    assert!(large.capacity() >= 1000);
+    assert_eq!(TablePool::stats().hits, 0);
+}
+
+#[test]
+fn test_recycled_tables_drop_entries_and_nested_maps() {
+    let token = Rc::new(());
+    let mut outer: PooledSmallHashMap<u32, PooledSmallHashMap<u32, Rc<()>, 1, Plain>, 1, Plain> =
+        PooledSmallHashMap::new();
+    for i in 0..3 {
+        let mut inner = PooledSmallHashMap::new();
+        inner.insert(0, token.clone());
+        inner.insert(1, token.clone());
+        outer.insert(i, inner);
+    }
+    assert_eq!(Rc::strong_count(&token), 7);
+    drop(outer);
+    assert_eq!(Rc::strong_count(&token), 1);
+    assert_eq!(TablePool::stats().returned, 4);
+}
+
+#[test]
+fn test_tables_only_reused_with_equal_hasher() {
+    let mut first = PooledSmallHashMap::with_hasher(Seeded(1));
+    spill(&mut first, 6);
+    drop(first);
+
+    let mut other_seed = PooledSmallHashMap::with_hasher(Seeded(2));
+    spill(&mut other_seed, 6);
+    assert_eq!(TablePool::stats().hits, 0);
+    assert_eq!(other_seed.hasher(), &Seeded(2));
+
+    let mut same_seed = PooledSmallHashMap::with_hasher(Seeded(1));
+    spill(&mut same_seed, 6);
+    assert_eq!(TablePool::stats().hits, 1);
+    assert_eq!(same_seed.get(&5), Some(&5));
+}
+
+#[test]
+fn test_into_iter_returns_table_to_pool() {
+    let mut map: PooledSmallHashMap<u32, u32, 4, Plain> = PooledSmallHashMap::new();
+    spill(&mut map, 6);
+    let mut entries: Vec<_> = map.into_iter().collect();
+    entries.sort_unstable();
+    assert_eq!(entries, (0..6).map(|i| (i, i)).collect::<Vec<_>>());
+    assert_eq!(TablePool::stats().returned, 1);
+
+    let mut reused: PooledSmallHashMap<u32, u32, 4, Plain> = PooledSmallHashMap::new();
+    spill(&mut reused, 6);
+    assert_eq!(TablePool::stats().hits, 1);
+}
+
+#[test]
+fn test_unpooled_heap_map_may_hold_borrows_until_dropped() {
+    // Only pooled tables have a destructor, so a default `HeapMap` of
+    // borrowed keys is still checked like a `HashMap`.
+    let mut map: HeapMap<&str, u32> = HeapMap::new();
+    let keys: Vec<String> = (0..4).map(|i| i.to_string()).collect();
+    for key in &keys {
+        map.insert(key, 0);
+    }
+    assert_eq!(map.len(), 4);
+}
@@ -0,0 +1,204 @@
+use crate::{SmallHashMap, ThinSmallHashMap};
+
+/// Generates the same tests for each map type that shares the core
+/// `SmallHashMap` API.
+macro_rules! shared_map_tests {
+    ($name:ident, $map:ident) => {
+        mod $name {
+            use super::*;
+
+            type Map = $map<String, i32, 4>;
+
+            fn key(i: i32) -> String {
+                format!("key{}", i)
+            }
+
+            #[test]
+            fn test_insert_get_remove_across_transition() {
+                let mut map = Map::default();
+                assert!(map.is_empty());
+                for i in 0..10 {
+                    assert_eq!(map.insert(key(i), i), None);
+                }
+                assert!(!map.is_inline());
+                assert_eq!(map.len(), 10);
+                assert_eq!(map.insert(key(3), 30), Some(3));
+                assert_eq!(map.get(&key(3)), Some(&30));
+                assert_eq!(map.get_key_value(&key(4)), Some((&key(4), &4)));
+                assert!(map.contains_key(&key(9)));
+                assert_eq!(map.remove(&key(9)), Some(9));
+                assert_eq!(map.remove_entry(&key(8)), Some((key(8), 8)));
+                assert_eq!(map.remove(&key(8)), None);
+                assert_eq!(map.len(), 8);
+            }
+
+            #[test]
+            fn test_iterators() {
+                let mut map: Map = (0..3).map(|i| (key(i), i)).collect();
+                let mut keys: Vec<_> = map.keys().cloned().collect();
+                keys.sort();
+                assert_eq!(keys, [key(0), key(1), key(2)]);
+                assert_eq!(map.values().sum::<i32>(), 3);
+                assert_eq!(map.iter().count(), 3);
+
+                for (_, value) in map.iter_mut() {
+                    *value *= 10;
+                }
+                for value in map.values_mut() {
+                    *value += 1;
+                }
+                let mut entries: Vec<_> = map.into_iter().collect();
+                entries.sort();
+                assert_eq!(entries, [(key(0), 1), (key(1), 11), (key(2), 21)]);
+
+                let empty = Map::default();
+                assert_eq!(empty.iter().count(), 0);
+                assert_eq!(empty.into_iter().count(), 0);
+            }
+
+            #[test]
+            fn test_retain_clear_and_get_mut() {
+                let mut map: Map = (0..8).map(|i| (key(i), i)).collect();
+                map.retain(|_, v| *v % 2 == 0);
+                assert_eq!(map.len(), 4);
+                if let Some(value) = map.get_mut(&key(2)) {
+                    *value = 200;
+                }
+                assert_eq!(map.get(&key(2)), Some(&200));
+                map.retain(|_, _| false);
+                assert!(map.is_empty());
+
+                map.extend([(key(1), 1)]);
+                map.clear();
+                assert!(map.is_empty());
+                assert_eq!(map.get(&key(1)), None);
+            }
+
+            #[test]
+            fn test_get_or_insert_and_default() {
+                let mut map = Map::default();
+                *map.get_or_default(key(1)) += 5;
+                *map.get_or_insert_with(key(1), || 100) += 1;
+                assert_eq!(*map.get_or_insert_with(key(2), || 7), 7);
+                assert_eq!(map.get(&key(1)), Some(&6));
+                assert_eq!(map.len(), 2);
+            }
+
+            #[test]
+            fn test_clone_and_eq() {
+                let map: Map = (0..6).map(|i| (key(i), i)).collect();
+                let mut copy = map.clone();
+                assert!(map == copy);
+                copy.insert(key(0), -1);
+                assert!(map != copy);
+                assert!(Map::default() == Map::default());
+                assert!(!format!("{:?}", Map::default()).is_empty());
+            }
+        }
+    };
+}
+
+shared_map_tests!(small, SmallHashMap);
+shared_map_tests!(thin, ThinSmallHashMap);
+
+#[test]
+fn test_thin_map_is_one_pointer_wide() {
+    use std::mem::size_of;
+    assert_eq!(
+        size_of::<ThinSmallHashMap<String, i32, 16>>(),
+        size_of::<usize>()
+    );
+    assert_eq!(
+        size_of::<ThinSmallHashMap<u64, [u8; 1024], 256>>(),
+        size_of::<usize>()
+    );
+}
+
+#[test]
+fn test_thin_map_frees_when_emptied() {
+    let mut map: ThinSmallHashMap<u64, [u8; 1024], 128> = ThinSmallHashMap::new();
+    assert_eq!(map.capacity(), 0);
+    map.extend(std::iter::empty());
+    assert!(!map.is_allocated());
+
+    map.insert(1, [1; 1024]);
+    assert!(map.is_allocated());
+    assert_eq!(map.capacity(), 128);
+    assert_eq!(map.remove(&1).map(|v| v[0]), Some(1));
+    assert!(!map.is_allocated());
+
+    map.extend((0..200).map(|i| (i, [0; 1024])));
+    assert!(!map.is_inline());
+    map.retain(|_, _| false);
+    assert!(!map.is_allocated());
+}
+
+#[test]
+fn test_thin_map_converts_to_and_from_small_hash_map() {
+    let small: SmallHashMap<&str, i32, 4> = [("a", 1), ("b", 2)].into();
+    let thin: ThinSmallHashMap<&str, i32, 4> = small.clone().into();
+    assert_eq!(
+        thin.as_inline().map(|map| map.as_slices()),
+        small.as_inline().map(|map| map.as_slices())
+    );
+    let back: SmallHashMap<&str, i32, 4> = thin.into();
+    assert_eq!(back, small);
+
+    let spilled: SmallHashMap<&str, i32, 1> = [("a", 1), ("b", 2)].into();
+    let thin: ThinSmallHashMap<&str, i32, 1> = spilled.clone().into();
+    assert!(thin.as_inline().is_none());
+    assert_eq!(thin.as_heap().map(|map| map.len()), Some(2));
+    let back: SmallHashMap<&str, i32, 1> = thin.into();
+    assert_eq!(back, spilled);
+
+    let empty: ThinSmallHashMap<&str, i32, 4> = SmallHashMap::new().into();
+    assert!(!empty.is_allocated());
+}
+
+#[test]
+fn test_thin_map_replaces_inline_box_when_spilling() {
+    let mut map: ThinSmallHashMap<u64, [u8; 1024], 4> = ThinSmallHashMap::new();
+    for i in 0..4 {
+        map.insert(i, [i as u8; 1024]);
+    }
+    assert!(map.as_inline().is_some());
+
+    // Replacing a key in a full map stays inline; a new key spills.
+    assert!(map.insert(0, [9; 1024]).is_some());
+    assert!(map.is_inline());
+    let value = map.get_or_insert_with(4, || [4; 1024]);
+    value[0] = 40;
+    assert!(map.as_inline().is_none());
+    assert_eq!(map.as_heap().map(|heap| heap.len()), Some(5));
+    assert_eq!(map.get(&4).map(|v| v[0]), Some(40));
+    assert_eq!(map.get(&0).map(|v| v[0]), Some(9));
+
+    let copy = map.clone();
+    assert!(copy.as_heap().is_some());
+    assert_eq!(copy, map);
+
+    let mut map: ThinSmallHashMap<u32, u32, 2> = ThinSmallHashMap::new();
+    map.extend((0..10).map(|i| (i, i)));
+    assert!(map.as_heap().is_some());
+    assert_eq!(map.len(), 10);
+    map.reserve(100);
+    assert!(map.capacity() >= 110);
+}
+
+#[test]
+fn test_thin_map_try_insert_and_drain() {
+    let mut map: ThinSmallHashMap<&str, i32, 2> = ThinSmallHashMap::new();
+    assert_eq!(map.try_insert("a", 1).ok().copied(), Some(1));
+    assert_eq!(map.try_insert("a", 2).unwrap_err().into_value(), 2);
+    map.insert("b", 2);
+    *map.try_insert("c", 3).unwrap() += 10;
+    assert!(!map.is_inline());
+    assert_eq!(map.get(&"c"), Some(&13));
+    assert_eq!(map.try_insert("c", 0).unwrap_err().existing(), &13);
+
+    let mut drained: Vec<_> = map.drain().collect();
+    drained.sort_unstable();
+    assert_eq!(drained, [("a", 1), ("b", 2), ("c", 13)]);
+    assert!(!map.is_allocated());
+    assert_eq!(map.drain().count(), 0);
+}
@@ -1,4 +1,4 @@
-use crate::SmallHashMap;
+use crate::{InlineMap, SmallHashMap};
 use std::collections::hash_map::RandomState;
 use std::hash::{BuildHasher, Hasher};
 
@@ -375,3 +375,953 @@ fn test_equality_with_different_hashers() {
 
     assert_eq!(map1, map2);
 }
+
+// ==================== try_insert Tests ====================
+
+#[test]
+fn test_try_insert_inline() {
+    let mut map: SmallHashMap<i32, String, 4> = SmallHashMap::new();
+
+    let value = map.try_insert(1, "one".to_string()).unwrap();
+    value.push('!');
+    assert_eq!(map.get(&1), Some(&"one!".to_string()));
+
+    let mut err = map.try_insert(1, "uno".to_string()).unwrap_err();
+    assert_eq!(err.key(), &1);
+    assert_eq!(err.existing(), "one!");
+    assert_eq!(err.value(), "uno");
+    err.existing_mut().push('?');
+    assert_eq!(err.into_value(), "uno");
+
+    assert_eq!(map.get(&1), Some(&"one!?".to_string()));
+    assert_eq!(map.len(), 1);
+    assert!(map.is_inline());
+}
+
+#[test]
+fn test_try_insert_transitions_to_heap() {
+    let mut map: SmallHashMap<i32, i32, 2> = SmallHashMap::new();
+    map.try_insert(1, 10).unwrap();
+    map.try_insert(2, 20).unwrap();
+    assert!(map.is_inline());
+
+    // A duplicate at capacity must not trigger a transition
+    assert!(map.try_insert(2, 21).is_err());
+    assert!(map.is_inline());
+
+    assert_eq!(map.try_insert(3, 30).unwrap(), &mut 30);
+    assert!(!map.is_inline());
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(&1), Some(&10));
+    assert_eq!(map.get(&2), Some(&20));
+}
+
+#[test]
+fn test_try_insert_heap() {
+    let mut map: SmallHashMap<i32, i32, 2> = SmallHashMap::with_capacity(10);
+    map.try_insert(1, 10).unwrap();
+
+    let err = map.try_insert(1, 11).unwrap_err();
+    assert_eq!(err.key(), &1);
+    assert_eq!(err.existing(), &10);
+    *err.into_existing_mut() += 5;
+
+    assert_eq!(map.get(&1), Some(&15));
+    assert_eq!(
+        map.try_insert(1, 0).unwrap_err().to_string(),
+        "failed to insert 0, key 1 already exists with value 15"
+    );
+}
+
+// ==================== Bulk Construction Tests ====================
+
+#[test]
+fn test_try_from_iter_unique() {
+    let map: SmallHashMap<i32, i32, 4> =
+        SmallHashMap::try_from_iter((0..10).map(|i| (i, i * 10))).unwrap();
+    assert_eq!(map.len(), 10);
+    assert!(!map.is_inline());
+    assert_eq!(map.get(&9), Some(&90));
+}
+
+#[test]
+fn test_try_from_iter_duplicate() {
+    let pairs = vec![(1, "a"), (2, "b"), (1, "c"), (2, "d")];
+    let err = SmallHashMap::<i32, &str, 4>::try_from_iter(pairs).unwrap_err();
+    assert_eq!(err.key(), &1);
+    assert_eq!(err.into_key(), 1);
+
+    // Duplicate detected after the transition to heap storage
+    let pairs = (0..10).chain(std::iter::once(7)).map(|i| (i, i));
+    let err = SmallHashMap::<i32, i32, 4>::try_from_iter(pairs).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate key 7");
+}
+
+#[test]
+fn test_from_iter_with_merges_duplicates() {
+    let words = ["a", "b", "a", "c", "a", "b"];
+    let map: SmallHashMap<&str, usize, 2> =
+        SmallHashMap::from_iter_with(words.iter().map(|w| (*w, 1)), |_, count, n| *count += n);
+
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(&"a"), Some(&3));
+    assert_eq!(map.get(&"b"), Some(&2));
+    assert_eq!(map.get(&"c"), Some(&1));
+}
+
+#[test]
+fn test_bulk_construction_ends_inline_when_duplicates_collapse() {
+    // The size hint (12) exceeds N, but only 3 unique keys remain
+    let pairs: Vec<_> = (0..12).map(|i| (i % 3, i)).collect();
+
+    let collected: SmallHashMap<i32, i32, 4> = pairs.iter().cloned().collect();
+    assert!(collected.is_inline());
+    assert_eq!(collected.get(&0), Some(&9));
+
+    let merged: SmallHashMap<i32, i32, 4> =
+        SmallHashMap::from_iter_with(pairs.iter().cloned(), |_, a, b| *a += b);
+    assert!(merged.is_inline());
+    assert_eq!(merged.get(&1), Some(&(1 + 4 + 7 + 10)));
+
+    let unique: SmallHashMap<i32, i32, 4> =
+        SmallHashMap::try_from_iter((0..3).map(|i| (i, i))).unwrap();
+    assert!(unique.is_inline());
+}
+
+// ==================== Bulk Load Tests ====================
+
+#[test]
+fn test_extend_spills_up_front_from_size_hint() {
+    let mut map: SmallHashMap<i32, i32, 4> = SmallHashMap::new();
+    map.extend((0..10).map(|i| (i, i)));
+
+    assert!(!map.is_inline());
+    assert!(map.capacity() >= 10);
+    assert_eq!(map.len(), 10);
+}
+
+#[test]
+fn test_extend_stays_inline_when_hint_fits() {
+    let mut map: SmallHashMap<i32, i32, 4> = SmallHashMap::new();
+    map.insert(1, 1);
+    map.insert(2, 2);
+
+    // Only half of the hint (2 of 4) is counted for a non-empty map, so
+    // updates to existing keys don't force a spill
+    map.extend(vec![(1, 10), (2, 20), (1, 11), (2, 21)]);
+    map.extend(vec![(3, 30)]);
+
+    assert!(map.is_inline());
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(&1), Some(&11));
+    assert_eq!(map.get(&2), Some(&21));
+}
+
+#[test]
+fn test_extend_with_repeated_keys_stays_inline() {
+    let mut map: SmallHashMap<i32, i32, 4> = SmallHashMap::new();
+    map.extend([(1, 1), (2, 2), (3, 3)]);
+
+    // The hint (counted as 3 of 6) would overflow the one free slot, but
+    // it doesn't exceed N, so the map only spills if the keys do
+    map.extend([(1, 10), (2, 20), (3, 30), (1, 11), (2, 21), (4, 40)]);
+
+    assert!(map.is_inline());
+    assert_eq!(map.len(), 4);
+    assert_eq!(map.get(&1), Some(&11));
+}
+
+#[test]
+fn test_insert_unique_unchecked() {
+    let mut map: SmallHashMap<i32, String, 2> = SmallHashMap::new();
+    unsafe {
+        map.insert_unique_unchecked(1, "one".to_string());
+        map.insert_unique_unchecked(2, "two".to_string());
+    }
+    assert!(map.is_inline());
+
+    unsafe { map.insert_unique_unchecked(3, "three".to_string()) }.push('!');
+    assert!(!map.is_inline());
+    assert_eq!(map.get(&1), Some(&"one".to_string()));
+    assert_eq!(map.get(&3), Some(&"three!".to_string()));
+}
+
+#[test]
+fn test_extend_unique_unchecked() {
+    let mut map: SmallHashMap<i32, i32, 8> = SmallHashMap::new();
+    unsafe { map.extend_unique_unchecked((0..4).map(|i| (i, -i))) };
+    assert!(map.is_inline());
+
+    unsafe { map.extend_unique_unchecked((4..20).map(|i| (i, -i))) };
+    assert!(!map.is_inline());
+    assert_eq!(map.len(), 20);
+    assert!((0..20).all(|i| map.get(&i) == Some(&-i)));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "already present")]
+fn test_insert_unique_unchecked_checks_in_debug_inline() {
+    let mut map: SmallHashMap<i32, i32, 4> = SmallHashMap::new();
+    unsafe {
+        map.insert_unique_unchecked(1, 1);
+        map.insert_unique_unchecked(1, 2);
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "already present")]
+fn test_insert_unique_unchecked_checks_in_debug_heap() {
+    let mut map: SmallHashMap<i32, i32, 4> = SmallHashMap::with_capacity(16);
+    unsafe {
+        map.insert_unique_unchecked(1, 1);
+        map.insert_unique_unchecked(1, 2);
+    }
+}
+
+// ==================== Set Algebra and Join Tests ====================
+
+#[test]
+fn test_union_with_stays_inline_when_it_fits() {
+    let a: SmallHashMap<&str, i32, 4> = [("x", 1), ("y", 2)].into_iter().collect();
+    let b: SmallHashMap<&str, i32, 8> = [("y", 20), ("z", 30)].into_iter().collect();
+
+    let merged = a.union_with(b, |_, left, right| left + right);
+    assert!(merged.is_inline());
+    assert_eq!(merged.len(), 3);
+    assert_eq!(merged.get(&"x"), Some(&1));
+    assert_eq!(merged.get(&"y"), Some(&22));
+    assert_eq!(merged.get(&"z"), Some(&30));
+    // Existing keys keep their position; new keys are appended
+    assert_eq!(merged.keys().copied().collect::<Vec<_>>(), ["x", "y", "z"]);
+}
+
+#[test]
+fn test_union_with_transitions_once() {
+    let a: SmallHashMap<i32, i32, 4> = (0..4).map(|i| (i, i)).collect();
+    let b: SmallHashMap<i32, i32, 2, SimpleBuildHasher> = {
+        let mut b = SmallHashMap::with_hasher(SimpleBuildHasher);
+        b.extend((2..8).map(|i| (i, 100)));
+        b
+    };
+
+    let merged = a.union_with(b, |_, left, right| left + right);
+    assert!(!merged.is_inline());
+    assert_eq!(merged.len(), 8);
+    assert_eq!(merged.get(&1), Some(&1));
+    assert_eq!(merged.get(&3), Some(&103));
+    assert_eq!(merged.get(&7), Some(&100));
+}
+
+#[test]
+fn test_intersection_with() {
+    let a: SmallHashMap<i32, &str, 2> = (0..10).map(|i| (i, "a")).collect();
+    let b: SmallHashMap<i32, usize, 4> = [(3, 1), (5, 2), (42, 3)].into_iter().collect();
+    assert!(!a.is_inline());
+
+    let joined = a.intersection_with(b, |k, s, n| format!("{}{}{}", k, s, n));
+    // The result fits in N = 2, so it is inline even though `a` was not
+    assert!(joined.is_inline());
+    assert_eq!(joined.len(), 2);
+    assert_eq!(joined.get(&3), Some(&"3a1".to_string()));
+    assert_eq!(joined.get(&5), Some(&"5a2".to_string()));
+}
+
+#[test]
+fn test_difference_and_is_submap_of() {
+    let a: SmallHashMap<i32, i32, 4> = [(1, 1), (2, 2), (3, 3)].into_iter().collect();
+    let b: SmallHashMap<i32, (), 8, SimpleBuildHasher> = {
+        let mut b = SmallHashMap::with_hasher(SimpleBuildHasher);
+        b.insert(2, ());
+        b
+    };
+
+    let c = a.clone().difference(&b);
+    assert!(c.is_inline());
+    assert_eq!(c.len(), 2);
+    assert!(!c.contains_key(&2));
+
+    assert!(c.is_submap_of(&a));
+    assert!(!a.is_submap_of(&c));
+
+    let mut changed = c.clone();
+    changed.insert(1, 100);
+    assert!(!changed.is_submap_of(&a));
+}
+
+#[test]
+fn test_joins() {
+    let left: SmallHashMap<i32, char, 4> = [(1, 'a'), (2, 'b'), (3, 'c')].into_iter().collect();
+    let right: SmallHashMap<i32, u8, 2> = (2..6).map(|i| (i, i as u8 * 10)).collect();
+    assert!(!right.is_inline());
+
+    let mut inner: Vec<_> = left.inner_join(&right).collect();
+    inner.sort();
+    assert_eq!(inner, [(&2, &'b', &20), (&3, &'c', &30)]);
+
+    let left_join: Vec<_> = left.left_join(&right).collect();
+    assert_eq!(
+        left_join,
+        [
+            (&1, &'a', None),
+            (&2, &'b', Some(&20)),
+            (&3, &'c', Some(&30))
+        ]
+    );
+
+    let mut outer: Vec<_> = left.full_outer_join(&right).collect();
+    outer.sort();
+    assert_eq!(
+        outer,
+        [
+            (&1, Some(&'a'), None),
+            (&2, Some(&'b'), Some(&20)),
+            (&3, Some(&'c'), Some(&30)),
+            (&4, None, Some(&40)),
+            (&5, None, Some(&50)),
+        ]
+    );
+}
+
+// ==================== Append and Merge Tests ====================
+
+#[test]
+fn test_append_inline_into_inline() {
+    let mut a: SmallHashMap<i32, String, 4> = SmallHashMap::new();
+    a.insert(1, "a1".to_string());
+    a.insert(2, "a2".to_string());
+    let mut b: SmallHashMap<i32, String, 2> = SmallHashMap::new();
+    b.insert(2, "b2".to_string());
+    b.insert(3, "b3".to_string());
+
+    a.append(&mut b);
+
+    assert!(b.is_empty());
+    assert!(b.is_inline());
+    assert!(a.is_inline());
+    assert_eq!(a.len(), 3);
+    assert_eq!(a.get(&1), Some(&"a1".to_string()));
+    assert_eq!(a.get(&2), Some(&"b2".to_string()));
+    assert_eq!(a.get(&3), Some(&"b3".to_string()));
+}
+
+#[test]
+fn test_append_from_heap_keeps_other_on_heap() {
+    let mut a: SmallHashMap<i32, i32, 4> = SmallHashMap::new();
+    a.insert(0, 0);
+    let mut b: SmallHashMap<i32, i32, 2> = (1..10).map(|i| (i, i * 10)).collect();
+    assert!(!b.is_inline());
+
+    a.append(&mut b);
+
+    assert!(b.is_empty());
+    assert!(!b.is_inline());
+    assert!(!a.is_inline());
+    assert_eq!(a.len(), 10);
+    assert!(a.capacity() >= 10);
+    assert!((1..10).all(|i| a.get(&i) == Some(&(i * 10))));
+}
+
+#[test]
+fn test_merge_with_combines_existing_values() {
+    let mut totals: SmallHashMap<&str, u32, 2> = SmallHashMap::new();
+    totals.insert("a", 1);
+    totals.insert("b", 2);
+    let mut other: SmallHashMap<&str, u32, 4> = SmallHashMap::new();
+    other.insert("b", 10);
+    other.insert("c", 20);
+
+    let mut merged_keys = Vec::new();
+    totals.merge_with(other, |key, existing, incoming| {
+        merged_keys.push(*key);
+        *existing += incoming;
+    });
+
+    assert_eq!(merged_keys, ["b"]);
+    assert!(!totals.is_inline());
+    assert_eq!(totals.len(), 3);
+    assert_eq!(totals.get(&"a"), Some(&1));
+    assert_eq!(totals.get(&"b"), Some(&12));
+    assert_eq!(totals.get(&"c"), Some(&20));
+}
+
+// ==================== Transformation Tests ====================
+
+#[test]
+fn test_map_values_keeps_storage_mode() {
+    let inline: SmallHashMap<i32, i32, 4> = (0..3).map(|i| (i, i)).collect();
+    let mapped = inline.map_values(|key, value| format!("{}:{}", key, value * 2));
+    assert!(mapped.is_inline());
+    assert_eq!(mapped.get(&2), Some(&"2:4".to_string()));
+    // Inline slots are moved in order.
+    assert_eq!(mapped.keys().copied().collect::<Vec<_>>(), [0, 1, 2]);
+
+    let heap: SmallHashMap<i32, i32, 4> = (0..10).map(|i| (i, i)).collect();
+    let mapped = heap.map_values(|_, value| value + 1);
+    assert!(!mapped.is_inline());
+    assert_eq!(mapped.len(), 10);
+    assert!((0..10).all(|i| mapped.get(&i) == Some(&(i + 1))));
+}
+
+#[test]
+fn test_filter_map_values_keeps_storage_mode() {
+    let heap: SmallHashMap<i32, i32, 2> = (0..6).map(|i| (i, i)).collect();
+    let evens = heap.filter_map_values(|_, value| (value % 2 == 0).then_some(value * 10));
+    // A heap map stays on the heap even when the result would fit inline.
+    assert!(!evens.is_inline());
+    assert_eq!(evens.len(), 3);
+    assert_eq!(evens.get(&4), Some(&40));
+    assert_eq!(evens.get(&1), None);
+}
+
+#[test]
+fn test_partition_and_split_off() {
+    let map: SmallHashMap<i32, i32, 8> = (0..6).map(|i| (i, i)).collect();
+    let (small, large) = map.partition(|key, _| *key < 2);
+    assert!(small.is_inline() && large.is_inline());
+    assert_eq!(small.keys().copied().collect::<Vec<_>>(), [0, 1]);
+    assert_eq!(large.keys().copied().collect::<Vec<_>>(), [2, 3, 4, 5]);
+
+    let mut heap: SmallHashMap<i32, i32, 2> = (0..6).map(|i| (i, i)).collect();
+    let odd = heap.split_off(|_, value| value % 2 == 1);
+    assert!(!heap.is_inline() && !odd.is_inline());
+    assert_eq!(odd.len(), 3);
+    assert_eq!(heap.len(), 3);
+    assert!(odd.keys().all(|key| key % 2 == 1));
+    assert!(heap.keys().all(|key| key % 2 == 0));
+}
+
+// ==================== Capacity Conversion Tests ====================
+
+#[test]
+fn test_into_capacity_moves_inline_slots_in_order() {
+    let map: SmallHashMap<i32, String, 4> = (0..3).map(|i| (i, i.to_string())).collect();
+    let wider: SmallHashMap<i32, String, 16> = map.into_capacity();
+    assert!(wider.is_inline());
+    assert_eq!(wider.capacity(), 16);
+    assert_eq!(wider.keys().copied().collect::<Vec<_>>(), [0, 1, 2]);
+
+    let exact: SmallHashMap<i32, String, 3> = wider.into_capacity();
+    assert!(exact.is_inline());
+    assert_eq!(exact.get(&2), Some(&"2".to_string()));
+}
+
+#[test]
+fn test_into_capacity_heap_conversions() {
+    // Inline entries that don't fit go to the heap.
+    let map: SmallHashMap<i32, i32, 8> = (0..6).map(|i| (i, i)).collect();
+    let narrow: SmallHashMap<i32, i32, 2> = map.into_capacity();
+    assert!(!narrow.is_inline());
+    assert_eq!(narrow.len(), 6);
+
+    // A heap map that still doesn't fit keeps its table.
+    let capacity = narrow.capacity();
+    let still_heap: SmallHashMap<i32, i32, 4> = narrow.into_capacity();
+    assert!(!still_heap.is_inline());
+    assert_eq!(still_heap.capacity(), capacity);
+
+    // A heap map that fits moves back inline.
+    let inline: SmallHashMap<i32, i32, 8> = still_heap.into_capacity();
+    assert!(inline.is_inline());
+    assert!((0..6).all(|i| inline.get(&i) == Some(&i)));
+}
+
+#[test]
+fn test_inline_map_into_capacity_rejects_overflow() {
+    let mut map: InlineMap<i32, i32, 4> = InlineMap::new();
+    for i in 0..3 {
+        map.insert(i, i);
+    }
+    let map = map.into_capacity::<2>().unwrap_err();
+    assert_eq!(map.len(), 3);
+    let map: InlineMap<i32, i32, 3> = map.into_capacity().unwrap();
+    assert_eq!(map.iter().map(|(k, _)| *k).collect::<Vec<_>>(), [0, 1, 2]);
+}
+
+// ==================== Conversion Tests ====================
+
+#[test]
+fn test_from_hash_map_adopts_or_moves_inline() {
+    use std::collections::HashMap;
+
+    let small: HashMap<i32, i32> = (0..3).map(|i| (i, i)).collect();
+    let map: SmallHashMap<i32, i32, 4> = small.clone().into();
+    assert!(map.is_inline());
+    assert_eq!(map, small);
+    assert_eq!(small, map);
+
+    let large: HashMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+    let capacity = large.capacity();
+    let map: SmallHashMap<i32, i32, 4> = SmallHashMap::from(large.clone());
+    assert!(!map.is_inline());
+    assert_eq!(map.capacity(), capacity);
+    assert_eq!(map, large);
+
+    let back = map.into_hash_map();
+    assert_eq!(back.capacity(), capacity);
+    assert_eq!(back, large);
+}
+
+#[test]
+fn test_into_hash_map_from_inline() {
+    use std::collections::HashMap;
+
+    let map: SmallHashMap<i32, &str, 4> = [(1, "a"), (2, "b")].into();
+    let std_map = HashMap::from(map);
+    assert_eq!(std_map, HashMap::from([(1, "a"), (2, "b")]));
+}
+
+#[test]
+fn test_vec_and_btree_map_conversions() {
+    use std::collections::BTreeMap;
+
+    let map: SmallHashMap<i32, char, 4> = vec![(2, 'b'), (1, 'a'), (2, 'c')].into();
+    assert_eq!(map.len(), 2);
+    assert_eq!(Vec::from(map.clone()), [(2, 'c'), (1, 'a')]);
+
+    let sorted = BTreeMap::from(map);
+    assert_eq!(sorted.into_iter().collect::<Vec<_>>(), [(1, 'a'), (2, 'c')]);
+
+    let from_btree: SmallHashMap<i32, char, 1> = BTreeMap::from([(1, 'x'), (2, 'y')]).into();
+    assert!(!from_btree.is_inline());
+    assert_eq!(from_btree.get(&2), Some(&'y'));
+}
+
+// ==================== Storage Access Tests ====================
+
+#[test]
+fn test_storage_views_follow_mode() {
+    use crate::{Storage, StorageMut};
+
+    let mut map: SmallHashMap<i32, i32, 2> = [(1, 10)].into();
+    assert!(matches!(map.storage(), Storage::InlineMap(m) if m.len() == 1));
+    assert!(map.as_heap().is_none() && map.as_heap_mut().is_none());
+    if let StorageMut::InlineMap(inline) = map.storage_mut() {
+        *inline.get_mut(&1).unwrap() += 1;
+    }
+    map.as_inline_mut().unwrap().insert(2, 20);
+    assert_eq!(map.get(&1), Some(&11));
+    assert_eq!(map.len(), 2);
+
+    map.insert(3, 30);
+    assert!(matches!(map.storage(), Storage::HeapMap(m) if m.len() == 3));
+    assert!(map.as_inline().is_none() && map.as_inline_mut().is_none());
+    map.as_heap_mut().unwrap().remove(&3);
+    assert_eq!(map.as_heap().unwrap().len(), 2);
+    assert_eq!(map.len(), 2);
+}
+
+#[test]
+#[should_panic(expected = "InlineMap is full")]
+fn test_as_inline_mut_does_not_transition() {
+    let mut map: SmallHashMap<i32, i32, 1> = [(1, 10)].into();
+    map.as_inline_mut().unwrap().insert(2, 20);
+}
+
+#[test]
+fn test_inline_slices() {
+    let mut map: SmallHashMap<u8, i32, 4> = [(3, 30), (1, 10)].into();
+    assert_eq!(map.inline_slices(), Some((&[3, 1][..], &[30, 10][..])));
+
+    for value in map.as_inline_mut().unwrap().values_mut_slice() {
+        *value *= 2;
+    }
+    assert_eq!(map.get(&1), Some(&20));
+
+    map.extend((4..8).map(|i| (i, 0)));
+    assert!(map.inline_slices().is_none());
+}
+
+#[test]
+fn test_inline_map_parts_round_trip() {
+    let mut map: InlineMap<String, String, 4> = InlineMap::new();
+    map.insert("a".to_string(), "1".to_string());
+    map.insert("b".to_string(), "2".to_string());
+
+    let (keys, values, len) = map.into_parts();
+    assert_eq!(len, 2);
+    // SAFETY: The parts come straight from into_parts.
+    let mut map: InlineMap<String, String, 4> = unsafe { InlineMap::from_parts(keys, values, len) };
+    assert_eq!(map.get(&"b".to_string()), Some(&"2".to_string()));
+    map.insert("c".to_string(), "3".to_string());
+    assert_eq!(map.as_slices().0, ["a", "b", "c"]);
+}
+
+// ==================== Cursor Tests ====================
+
+#[test]
+fn test_cursor_inline_remove_replace_insert() {
+    let mut map: SmallHashMap<i32, &str, 4> = [(1, "a"), (2, "b"), (3, "c")].into();
+
+    let mut cursor = map.cursor_mut();
+    assert_eq!(cursor.replace_value("A"), Ok("a"));
+    cursor.move_next();
+    assert_eq!(cursor.remove_current(), Some((2, "b")));
+    assert_eq!(cursor.current().map(|(k, _)| *k), Some(3));
+    assert_eq!(cursor.insert_before(9, "z"), Ok(()));
+    assert_eq!(cursor.insert_before(1, "dup"), Err((1, "dup")));
+    assert_eq!(cursor.current().map(|(k, _)| *k), Some(3));
+    cursor.move_next();
+    assert!(cursor.current().is_none());
+    assert_eq!(cursor.remove_current(), None);
+    assert_eq!(cursor.replace_value("x"), Err("x"));
+    drop(cursor);
+
+    assert!(map.is_inline());
+    assert_eq!(
+        map.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(),
+        [(1, "A"), (9, "z"), (3, "c")]
+    );
+}
+
+#[test]
+fn test_cursor_insert_before_never_transitions() {
+    let mut map: SmallHashMap<i32, i32, 2> = [(1, 1), (2, 2)].into();
+    assert_eq!(map.cursor_mut().insert_before(3, 3), Err((3, 3)));
+    assert!(map.is_inline());
+
+    map.insert(3, 3);
+    assert_eq!(map.cursor_mut().insert_before(4, 4), Err((4, 4)));
+    assert_eq!(map.len(), 3);
+}
+
+#[test]
+fn test_cursor_heap_restores_entries_on_early_stop() {
+    let mut map: SmallHashMap<i32, i32, 2> = (0..10).map(|i| (i, i)).collect();
+    let capacity = map.capacity();
+
+    let mut removed = Vec::new();
+    let mut cursor = map.cursor_mut();
+    while let Some((key, value)) = cursor.current() {
+        if *key == 7 {
+            break;
+        }
+        if key % 2 == 0 {
+            removed.push(cursor.remove_current().unwrap().0);
+        } else {
+            *value *= 10;
+            cursor.move_next();
+        }
+    }
+    drop(cursor);
+
+    assert!(!map.is_inline());
+    assert_eq!(map.capacity(), capacity);
+    assert_eq!(map.len(), 10 - removed.len());
+    assert!(removed.iter().all(|key| !map.contains_key(key)));
+    for key in [1, 3, 5, 7, 9] {
+        assert!(map.contains_key(&key));
+    }
+    assert_eq!(map.get(&7), Some(&7));
+}
+
+#[test]
+fn test_cursor_walks_keys_that_are_not_clone() {
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    struct Key(u32);
+
+    let mut inline: SmallHashMap<Key, u32, 4> = SmallHashMap::new();
+    let mut heap: SmallHashMap<Key, u32, 4> = SmallHashMap::new();
+    for i in 0..2 {
+        inline.insert(Key(i), i);
+    }
+    for i in 0..10 {
+        heap.insert(Key(i), i);
+    }
+
+    for map in [&mut inline, &mut heap] {
+        let is_inline = map.is_inline();
+        let mut cursor = map.cursor_mut();
+        let mut seen = 0;
+        while let Some((key, value)) = cursor.current() {
+            assert_eq!(key.0, *value);
+            seen += 1;
+            if key.0 == 1 {
+                assert_eq!(cursor.remove_current(), Some((Key(1), 1)));
+            } else {
+                cursor.move_next();
+            }
+        }
+        drop(cursor);
+        assert_eq!(map.is_inline(), is_inline);
+        assert_eq!(map.len(), seen - 1);
+        assert!(!map.contains_key(&Key(1)));
+    }
+}
+
+// ==================== Hash and Ord Tests ====================
+
+fn hash_of<T: std::hash::Hash>(value: &T) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn test_hash_is_order_and_mode_independent() {
+    let forward: SmallHashMap<i32, i32, 8> = (0..6).map(|i| (i, i * 2)).collect();
+    let backward: SmallHashMap<i32, i32, 8> = (0..6).rev().map(|i| (i, i * 2)).collect();
+    let heap: SmallHashMap<i32, i32, 2> = (0..6).map(|i| (i, i * 2)).collect();
+    let custom: SmallHashMap<i32, i32, 8, SimpleBuildHasher> = (0..6).map(|i| (i, i * 2)).collect();
+    assert!(forward.is_inline() && !heap.is_inline());
+
+    assert_eq!(hash_of(&forward), hash_of(&backward));
+    assert_eq!(hash_of(&forward), hash_of(&heap));
+    assert_eq!(hash_of(&forward), hash_of(&custom));
+
+    let different: SmallHashMap<i32, i32, 8> = (0..6).map(|i| (i, i)).collect();
+    assert_ne!(hash_of(&forward), hash_of(&different));
+
+    let mut seen = std::collections::HashSet::new();
+    assert!(seen.insert(forward));
+    assert!(!seen.insert(backward));
+}
+
+#[test]
+fn test_ord_compares_sorted_entries() {
+    use std::cmp::Ordering;
+
+    let a: SmallHashMap<i32, i32, 4> = [(2, 0), (1, 0)].into();
+    let b: SmallHashMap<i32, i32, 1> = [(1, 0), (3, 0)].into();
+    let c: SmallHashMap<i32, i32, 4> = [(1, 0), (2, 1)].into();
+    assert_eq!(a.partial_cmp(&b), Some(Ordering::Less));
+    assert!(a < c);
+
+    let heap: SmallHashMap<i32, i32, 1> = [(1, 0), (2, 0)].into();
+    assert_eq!(a.partial_cmp(&heap), Some(Ordering::Equal));
+    assert_eq!(a, heap);
+
+    let set: std::collections::BTreeSet<_> = [c.clone(), a.clone(), c].into_iter().collect();
+    assert_eq!(
+        set.into_iter().collect::<Vec<_>>(),
+        [a.clone(), [(1, 0), (2, 1)].into()]
+    );
+}
+
+// ==================== Sorted Output Tests ====================
+
+#[test]
+fn test_iter_sorted_matches_across_modes() {
+    let inline: SmallHashMap<i32, i32, 16> = (0..10).rev().map(|i| (i, i * i)).collect();
+    let heap: SmallHashMap<i32, i32, 2> = (0..10).rev().map(|i| (i, i * i)).collect();
+    assert!(inline.is_inline() && !heap.is_inline());
+
+    let expected: Vec<_> = (0..10).map(|i| (i, i * i)).collect();
+    let sorted =
+        |map: Vec<(&i32, &i32)>| map.into_iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+    assert_eq!(sorted(inline.iter_sorted().collect()), expected);
+    assert_eq!(sorted(heap.iter_sorted().collect()), expected);
+    assert_eq!(inline.iter_sorted().len(), 10);
+    assert!(inline.keys_sorted().eq(heap.keys_sorted()));
+    assert_eq!(inline.to_string(), heap.to_string());
+
+    let empty: SmallHashMap<i32, i32, 4> = SmallHashMap::new();
+    assert_eq!(empty.iter_sorted().next(), None);
+    assert_eq!(empty.to_string(), "{}");
+}
+
+#[test]
+fn test_sorted_debug_reports_storage() {
+    let mut map: SmallHashMap<i32, &str, 2> = [(2, "b"), (1, "a")].into();
+    assert_eq!(
+        format!("{:?}", map.sorted_debug()),
+        r#"SmallHashMap { storage: inline, capacity: 2, entries: {1: "a", 2: "b"} }"#
+    );
+
+    map.insert(0, "z");
+    let output = format!("{:?}", map.sorted_debug());
+    assert!(output.starts_with("SmallHashMap { storage: heap, capacity: "));
+    assert!(output.ends_with(r#"entries: {0: "z", 1: "a", 2: "b"} }"#));
+    assert_eq!(map.to_string(), "{0: z, 1: a, 2: b}");
+}
+
+// ==================== Closure Update Tests ====================
+
+#[test]
+fn test_alter_inline_keeps_position() {
+    let mut map: SmallHashMap<i32, i32, 3> = [(1, 10), (2, 20), (3, 30)].into();
+
+    map.alter(2, |value| value.map(|v| v + 1));
+    assert_eq!(map.keys().copied().collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(map.get(&2), Some(&21));
+
+    map.alter(1, |_| None);
+    assert_eq!(map.keys().copied().collect::<Vec<_>>(), [2, 3]);
+
+    map.alter(9, |_| None);
+    assert_eq!(map.len(), 2);
+}
+
+#[test]
+fn test_alter_transitions_and_works_on_heap() {
+    let mut map: SmallHashMap<i32, i32, 2> = [(1, 10), (2, 20)].into();
+    map.alter(3, |value| Some(value.unwrap_or(0) + 30));
+    assert!(!map.is_inline());
+    assert_eq!(map.get(&3), Some(&30));
+
+    map.alter(3, |value| value.map(|v| v * 2));
+    assert_eq!(map.get(&3), Some(&60));
+    map.alter(1, |_| None);
+    map.alter(4, |_| Some(40));
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(&1), None);
+    assert_eq!(map.get(&4), Some(&40));
+}
+
+#[test]
+fn test_alter_removes_entry_when_closure_panics() {
+    let mut map: SmallHashMap<i32, String, 4> = [(1, "a".to_string()), (2, "b".to_string())].into();
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        map.alter(1, |_| panic!("boom"));
+    }));
+    assert!(result.is_err());
+    assert_eq!(map.len(), 1);
+    assert_eq!(map.get(&2), Some(&"b".to_string()));
+}
+
+#[test]
+fn test_get_or_insert_with_and_upsert_across_transition() {
+    let mut map: SmallHashMap<i32, Vec<i32>, 2> = SmallHashMap::new();
+    for i in 0..6 {
+        map.get_or_insert_with(i % 3, Vec::new).push(i);
+    }
+    assert!(!map.is_inline());
+    assert_eq!(map.get(&2), Some(&vec![2, 5]));
+
+    let mut counts: SmallHashMap<&str, u32, 2> = SmallHashMap::new();
+    for word in ["a", "b", "a", "c", "a", "c"] {
+        counts.upsert(word, 1, |count| *count += 1);
+        *counts.get_or_default("total") += 1;
+    }
+    assert_eq!(counts.get(&"a"), Some(&3));
+    assert_eq!(counts.get(&"c"), Some(&2));
+    assert_eq!(counts.get(&"total"), Some(&6));
+    assert!(counts.update(&"b", |count| *count = 0));
+    assert_eq!(counts.get(&"b"), Some(&0));
+}
+
+// ==================== Rehash Tests ====================
+
+#[test]
+fn test_rehash_with_both_modes() {
+    let inline: SmallHashMap<i32, i32, 4, SimpleBuildHasher> = (0..3).map(|i| (i, i)).collect();
+    let inline: SmallHashMap<i32, i32, 4> = inline.rehash_with(RandomState::new());
+    assert!(inline.is_inline());
+    assert_eq!(inline.keys().copied().collect::<Vec<_>>(), [0, 1, 2]);
+
+    let heap: SmallHashMap<i32, i32, 2, SimpleBuildHasher> = (0..20).map(|i| (i, i * 2)).collect();
+    let mut heap: SmallHashMap<i32, i32, 2> = heap.rehash_with(RandomState::new());
+    assert!(!heap.is_inline());
+    assert_eq!(heap.len(), 20);
+    assert!((0..20).all(|i| heap.get(&i) == Some(&(i * 2))));
+
+    // The new hasher is used for transitions and inserts afterwards.
+    heap.insert(100, 1);
+    assert_eq!(heap.get(&100), Some(&1));
+}
+
+#[test]
+fn test_reseed_keeps_entries() {
+    let mut map: SmallHashMap<String, usize, 2> = (0..10).map(|i| (i.to_string(), i)).collect();
+    map.reseed();
+    assert!(!map.is_inline());
+    assert_eq!(map.len(), 10);
+    assert!((0..10).all(|i| map.get(&i.to_string()) == Some(&i)));
+
+    let mut small: SmallHashMap<i32, i32, 4> = [(1, 1)].into();
+    small.reseed();
+    assert!(small.is_inline());
+    small.extend((2..8).map(|i| (i, i)));
+    assert_eq!(small.len(), 7);
+    assert_eq!(small.get(&7), Some(&7));
+}
+
+// ==================== Large-N Tests ====================
+
+#[test]
+fn test_inline_map_new_boxed_large_map() {
+    type Big = InlineMap<u64, [u8; 512], 256>;
+    assert!(std::mem::size_of::<Big>() > 128 * 1024);
+
+    let mut map: Box<Big> = InlineMap::new_boxed();
+    assert!(map.is_empty());
+    assert_eq!(map.capacity(), 256);
+    for i in 0..256 {
+        map.insert(i, [i as u8; 512]);
+    }
+    assert_eq!(map.len(), 256);
+    assert_eq!(map.get(&255).map(|v| v[0]), Some(255));
+    assert_eq!(map.remove(&0).map(|v| v[511]), Some(0));
+}
+
+#[test]
+fn test_small_hash_map_new_boxed_spills_and_shrinks() {
+    type Big = SmallHashMap<u64, [u8; 1024], 128>;
+    assert!(std::mem::size_of::<Big>() > 128 * 1024);
+
+    let mut map: Box<Big> = SmallHashMap::new_boxed();
+    assert!(map.is_inline());
+    assert_eq!(map.capacity(), 128);
+    for i in 0..200 {
+        map.insert(i, [i as u8; 1024]);
+    }
+    assert!(!map.is_inline());
+    assert_eq!(map.len(), 200);
+    assert_eq!(map.get(&199).map(|v| v[0]), Some(199));
+
+    map.retain(|k, _| *k < 10);
+    assert_eq!(map.len(), 10);
+    map.clear();
+    assert!(map.is_empty());
+}
+
+#[test]
+fn test_new_boxed_matches_new_for_small_maps() {
+    let mut boxed: Box<SmallHashMap<&str, i32, 4>> = SmallHashMap::new_boxed();
+    let mut plain: SmallHashMap<&str, i32, 4> = SmallHashMap::new();
+    for (i, key) in ["a", "b", "c", "d", "e"].into_iter().enumerate() {
+        boxed.insert(key, i as i32);
+        plain.insert(key, i as i32);
+    }
+    assert_eq!(*boxed, plain);
+    assert_eq!(boxed.is_inline(), plain.is_inline());
+}
+
+#[test]
+fn test_inline_variant_tag_uses_niche() {
+    use crate::map::MapKind;
+    use crate::HeapMap;
+    use std::mem::size_of;
+
+    // The inline/heap tag must not widen the storage beyond the heap variant.
+    assert_eq!(
+        size_of::<MapKind<u8, u8, 4>>(),
+        size_of::<HeapMap<u8, u8>>()
+    );
+}
+
+#[derive(Default, Clone)]
+struct WideState([u64; 20]);
+
+impl BuildHasher for WideState {
+    type Hasher = std::collections::hash_map::DefaultHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        let mut hasher = Self::Hasher::default();
+        hasher.write_u64(self.0[0]);
+        hasher
+    }
+}
+
+#[test]
+fn test_new_boxed_with_wide_hasher() {
+    let mut map: Box<SmallHashMap<u8, u8, 2, WideState>> = SmallHashMap::new_boxed();
+    assert!(map.is_inline());
+    assert!(map.is_empty());
+    for i in 0..5 {
+        map.insert(i, i);
+    }
+    assert!(!map.is_inline());
+    assert_eq!(map.get(&4), Some(&4));
+}
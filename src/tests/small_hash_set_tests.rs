@@ -0,0 +1,142 @@
+use crate::{InlineMap, SmallHashSet};
+use std::mem::size_of;
+
+#[test]
+fn test_set_insert_contains_remove() {
+    let mut set: SmallHashSet<i32, 4> = SmallHashSet::new();
+    assert!(set.is_empty());
+
+    assert!(set.insert(1));
+    assert!(set.insert(2));
+    assert!(!set.insert(1));
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(&1));
+    assert!(!set.contains(&3));
+
+    assert!(set.remove(&1));
+    assert!(!set.remove(&1));
+    assert_eq!(set.len(), 1);
+}
+
+#[test]
+fn test_set_transitions_to_heap() {
+    let mut set: SmallHashSet<i32, 2> = SmallHashSet::new();
+    set.insert(1);
+    set.insert(2);
+    assert!(set.is_inline());
+
+    // Re-inserting at capacity must not transition
+    set.insert(2);
+    assert!(set.is_inline());
+
+    set.insert(3);
+    assert!(!set.is_inline());
+    assert!((1..=3).all(|i| set.contains(&i)));
+}
+
+#[test]
+fn test_set_take_and_replace() {
+    let mut set: SmallHashSet<String, 2> = SmallHashSet::new();
+    assert_eq!(set.replace("a".to_string()), None);
+    assert_eq!(set.replace("a".to_string()), Some("a".to_string()));
+    assert_eq!(set.get(&"a".to_string()), Some(&"a".to_string()));
+    assert_eq!(set.take(&"a".to_string()), Some("a".to_string()));
+    assert_eq!(set.take(&"a".to_string()), None);
+
+    // Same behavior after the transition
+    set.extend(["x", "y", "z"].map(String::from));
+    assert!(!set.is_inline());
+    assert_eq!(set.replace("y".to_string()), Some("y".to_string()));
+    assert_eq!(set.len(), 3);
+    assert_eq!(set.take(&"y".to_string()), Some("y".to_string()));
+    assert_eq!(set.len(), 2);
+}
+
+#[test]
+fn test_set_replace_keeps_inline_order() {
+    let mut set: SmallHashSet<&str, 4> = SmallHashSet::new();
+    set.extend(["a", "b", "c"]);
+    set.replace("a");
+    assert_eq!(set.iter().copied().collect::<Vec<_>>(), ["a", "b", "c"]);
+}
+
+#[test]
+fn test_set_operations() {
+    let a: SmallHashSet<i32, 4> = [1, 2, 3].into_iter().collect();
+    let b: SmallHashSet<i32, 4> = [3, 4, 5, 6, 7].into_iter().collect();
+    assert!(a.is_inline());
+    assert!(!b.is_inline());
+
+    let sorted = |mut v: Vec<i32>| {
+        v.sort();
+        v
+    };
+    assert_eq!(
+        sorted(a.union(&b).copied().collect()),
+        [1, 2, 3, 4, 5, 6, 7]
+    );
+    assert_eq!(sorted(a.intersection(&b).copied().collect()), [3]);
+    assert_eq!(sorted(b.intersection(&a).copied().collect()), [3]);
+    assert_eq!(sorted(a.difference(&b).copied().collect()), [1, 2]);
+    assert_eq!(sorted(b.difference(&a).copied().collect()), [4, 5, 6, 7]);
+    assert_eq!(
+        sorted(a.symmetric_difference(&b).copied().collect()),
+        [1, 2, 4, 5, 6, 7]
+    );
+}
+
+#[test]
+fn test_set_relations() {
+    let small: SmallHashSet<i32, 4> = [1, 2].into_iter().collect();
+    let large: SmallHashSet<i32, 8> = (0..6).collect();
+    let other: SmallHashSet<i32, 4> = [10, 11].into_iter().collect();
+
+    assert!(small.is_subset(&large));
+    assert!(!large.is_subset(&small));
+    assert!(large.is_superset(&small));
+    assert!(small.is_disjoint(&other));
+    assert!(!small.is_disjoint(&large));
+}
+
+#[test]
+fn test_set_equality_across_storage_modes() {
+    let inline: SmallHashSet<i32, 8> = (0..5).collect();
+    let mut heap: SmallHashSet<i32, 2> = SmallHashSet::with_capacity(16);
+    heap.extend((0..5).rev());
+
+    assert!(inline.is_inline());
+    assert!(!heap.is_inline());
+    assert_eq!(inline, heap);
+
+    heap.remove(&0);
+    assert_ne!(inline, heap);
+}
+
+#[test]
+fn test_set_iteration() {
+    let mut set: SmallHashSet<i32, 4> = SmallHashSet::new();
+    set.extend([3, 1, 2]);
+
+    // Inline iteration preserves insertion order
+    assert_eq!(set.iter().copied().collect::<Vec<_>>(), [3, 1, 2]);
+    assert_eq!((&set).into_iter().count(), 3);
+
+    set.retain(|v| *v != 1);
+    let mut values: Vec<_> = set.into_iter().collect();
+    values.sort();
+    assert_eq!(values, [2, 3]);
+}
+
+#[test]
+fn test_set_inline_tier_stores_no_values() {
+    // The values array of `()` is zero-sized, so the inline tier holds only
+    // the elements and the length.
+    assert_eq!(
+        size_of::<InlineMap<u64, (), 8>>(),
+        size_of::<[u64; 8]>() + size_of::<usize>()
+    );
+    assert_eq!(
+        size_of::<InlineMap<[u8; 16], (), 4>>(),
+        size_of::<[[u8; 16]; 4]>() + size_of::<usize>()
+    );
+}
@@ -0,0 +1,588 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::NonNull;
+
+use super::error::OccupiedError;
+use super::heap_map::HeapMap;
+use super::inline_map::InlineMap;
+use super::small_hash_map::{
+    SmallHashMap, SmallHashMapIntoIter, SmallHashMapIter, SmallHashMapIterMut, SmallHashMapKeys,
+    SmallHashMapValues, SmallHashMapValuesMut,
+};
+
+/// A pointer-sized handle to a `SmallHashMap`, for embedding in structs
+/// that are created in large numbers and are usually empty.
+///
+/// An empty map is a null pointer and owns no memory. The first insert
+/// allocates a boxed `SmallHashMap`, built in place with
+/// `SmallHashMap::new_boxed`, that holds the inline entries. When the map
+/// spills, that box is freed and replaced by a boxed heap table, so a
+/// spilled map doesn't keep its unused inline storage alive. Removing the
+/// last entry (or calling `clear`) frees the allocation again.
+///
+/// The hasher is created with `S::default()` on the first insert, since an
+/// empty handle has nowhere to keep one.
+///
+/// The handle forwards the non-structural parts of the `SmallHashMap` API.
+/// Like `SmallHashMap`, lookups take `&K` rather than a borrowed form. There
+/// is no `entry` API; use `get_or_insert_with` or `try_insert` instead.
+///
+/// # Example
+///
+/// ```
+/// use small_hash_map::ThinSmallHashMap;
+///
+/// let mut map: ThinSmallHashMap<&str, i32, 8> = ThinSmallHashMap::new();
+/// assert_eq!(std::mem::size_of_val(&map), std::mem::size_of::<usize>());
+/// assert!(!map.is_allocated());
+///
+/// map.insert("a", 1);
+/// assert!(map.is_allocated());
+/// assert_eq!(map.get(&"a"), Some(&1));
+///
+/// map.remove(&"a");
+/// assert!(!map.is_allocated());
+/// ```
+pub struct ThinSmallHashMap<K, V, const N: usize, S = RandomState> {
+    /// `None` for an empty map. Otherwise a `Box<SmallHashMap>` while the
+    /// entries are inline, or a `Box<HeapMap>` tagged with `HEAP_TAG` once
+    /// the map has spilled.
+    ptr: Option<NonNull<u8>>,
+    /// The handle owns one of the two boxes.
+    marker: PhantomData<ThinBox<K, V, N, S>>,
+}
+
+/// Set in the low bit of the pointer to a spilled map's `HeapMap`. Both
+/// boxed types are at least pointer-aligned, so the bit is otherwise clear.
+const HEAP_TAG: usize = 1;
+
+/// The owned storage behind a `ThinSmallHashMap`.
+enum ThinBox<K, V, const N: usize, S> {
+    Empty,
+    Inline(Box<SmallHashMap<K, V, N, S>>),
+    Heap(Box<HeapMap<K, V, S>>),
+}
+
+/// A borrowed view of the storage behind a `ThinSmallHashMap`.
+enum ThinRef<'m, K, V, const N: usize, S> {
+    Empty,
+    Inline(&'m SmallHashMap<K, V, N, S>),
+    Heap(&'m HeapMap<K, V, S>),
+}
+
+/// A mutably borrowed view of the storage behind a `ThinSmallHashMap`.
+enum ThinMut<'m, K, V, const N: usize, S> {
+    Empty,
+    Inline(&'m mut SmallHashMap<K, V, N, S>),
+    Heap(&'m mut HeapMap<K, V, S>),
+}
+
+// SAFETY: The handle owns its box like a `Box` field would.
+unsafe impl<K, V, const N: usize, S> Send for ThinSmallHashMap<K, V, N, S>
+where
+    SmallHashMap<K, V, N, S>: Send,
+    HeapMap<K, V, S>: Send,
+{
+}
+
+// SAFETY: Shared access only hands out shared references into the box.
+unsafe impl<K, V, const N: usize, S> Sync for ThinSmallHashMap<K, V, N, S>
+where
+    SmallHashMap<K, V, N, S>: Sync,
+    HeapMap<K, V, S>: Sync,
+{
+}
+
+impl<K, V, const N: usize, S> ThinSmallHashMap<K, V, N, S> {
+    /// Creates an empty map without allocating.
+    pub const fn new() -> Self {
+        Self {
+            ptr: None,
+            marker: PhantomData,
+        }
+    }
+
+    fn from_box(storage: ThinBox<K, V, N, S>) -> Self {
+        let ptr = match storage {
+            ThinBox::Empty => None,
+            ThinBox::Inline(map) => Some(NonNull::from(Box::leak(map)).cast()),
+            ThinBox::Heap(map) => {
+                const { assert!(mem::align_of::<HeapMap<K, V, S>>() > HEAP_TAG) };
+                let ptr = NonNull::from(Box::leak(map)).cast::<u8>();
+                NonNull::new(ptr.as_ptr().map_addr(|addr| addr | HEAP_TAG))
+            }
+        };
+        Self {
+            ptr,
+            marker: PhantomData,
+        }
+    }
+
+    /// Takes the storage out, leaving the handle empty.
+    fn take(&mut self) -> ThinBox<K, V, N, S> {
+        match self.ptr.take() {
+            None => ThinBox::Empty,
+            // SAFETY: A tagged pointer came from `Box<HeapMap>::leak`, and the
+            // handle no longer refers to it.
+            Some(ptr) if ptr.addr().get() & HEAP_TAG != 0 => ThinBox::Heap(unsafe {
+                Box::from_raw(ptr.as_ptr().map_addr(|addr| addr & !HEAP_TAG).cast())
+            }),
+            // SAFETY: An untagged pointer came from `Box<SmallHashMap>::leak`.
+            Some(ptr) => ThinBox::Inline(unsafe { Box::from_raw(ptr.as_ptr().cast()) }),
+        }
+    }
+
+    fn storage(&self) -> ThinRef<'_, K, V, N, S> {
+        match self.ptr {
+            None => ThinRef::Empty,
+            // SAFETY: As in `take`; the box lives as long as `self`.
+            Some(ptr) if ptr.addr().get() & HEAP_TAG != 0 => {
+                ThinRef::Heap(unsafe { &*ptr.as_ptr().map_addr(|addr| addr & !HEAP_TAG).cast() })
+            }
+            // SAFETY: As in `take`.
+            Some(ptr) => ThinRef::Inline(unsafe { ptr.cast().as_ref() }),
+        }
+    }
+
+    fn storage_mut(&mut self) -> ThinMut<'_, K, V, N, S> {
+        match self.ptr {
+            None => ThinMut::Empty,
+            // SAFETY: As in `take`; `&mut self` gives exclusive access.
+            Some(ptr) if ptr.addr().get() & HEAP_TAG != 0 => ThinMut::Heap(unsafe {
+                &mut *ptr.as_ptr().map_addr(|addr| addr & !HEAP_TAG).cast()
+            }),
+            // SAFETY: As in `take`.
+            Some(ptr) => ThinMut::Inline(unsafe { ptr.cast().as_mut() }),
+        }
+    }
+
+    /// Returns `true` if the map currently owns an allocation. An empty map
+    /// doesn't, unless `reserve` was called or a panic interrupted an
+    /// insert.
+    pub fn is_allocated(&self) -> bool {
+        self.ptr.is_some()
+    }
+}
+
+impl<K, V, const N: usize, S> Drop for ThinSmallHashMap<K, V, N, S> {
+    fn drop(&mut self) {
+        drop(self.take());
+    }
+}
+
+impl<K, V, const N: usize, S> ThinSmallHashMap<K, V, N, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Returns the inline entries, or `None` if the map is empty or has
+    /// spilled.
+    pub fn as_inline(&self) -> Option<&InlineMap<K, V, N>> {
+        match self.storage() {
+            ThinRef::Inline(map) => map.as_inline(),
+            ThinRef::Empty | ThinRef::Heap(_) => None,
+        }
+    }
+
+    /// Returns the heap table, or `None` if the map is empty or inline.
+    pub fn as_heap(&self) -> Option<&HeapMap<K, V, S>> {
+        match self.storage() {
+            ThinRef::Inline(map) => map.as_heap(),
+            ThinRef::Heap(map) => Some(map),
+            ThinRef::Empty => None,
+        }
+    }
+
+    /// Returns the number of elements in the map.
+    pub fn len(&self) -> usize {
+        match self.storage() {
+            ThinRef::Empty => 0,
+            ThinRef::Inline(map) => map.len(),
+            ThinRef::Heap(map) => map.len(),
+        }
+    }
+
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of elements the map can hold without allocating
+    /// or growing; 0 while the map is empty.
+    pub fn capacity(&self) -> usize {
+        match self.storage() {
+            ThinRef::Empty => 0,
+            ThinRef::Inline(map) => map.capacity(),
+            ThinRef::Heap(map) => map.capacity(),
+        }
+    }
+
+    /// Returns `true` if the map is empty or using inline storage.
+    pub fn is_inline(&self) -> bool {
+        match self.storage() {
+            ThinRef::Empty => true,
+            ThinRef::Inline(map) => map.is_inline(),
+            ThinRef::Heap(_) => false,
+        }
+    }
+
+    /// Removes all elements and frees the allocation.
+    pub fn clear(&mut self) {
+        drop(self.take());
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        match self.storage() {
+            ThinRef::Empty => None,
+            ThinRef::Inline(map) => map.get(key),
+            ThinRef::Heap(map) => map.get(key),
+        }
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        match self.storage_mut() {
+            ThinMut::Empty => None,
+            ThinMut::Inline(map) => map.get_mut(key),
+            ThinMut::Heap(map) => map.get_mut(key),
+        }
+    }
+
+    /// Returns the key-value pair corresponding to the key.
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        match self.storage() {
+            ThinRef::Empty => None,
+            ThinRef::Inline(map) => map.get_key_value(key),
+            ThinRef::Heap(map) => map.get_key_value(key),
+        }
+    }
+
+    /// Returns `true` if the map contains a value for the key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    /// Frees the allocation if the map becomes empty.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Removes a key from the map, returning the stored key and value if the
+    /// key was present. Frees the allocation if the map becomes empty.
+    pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
+        let entry = match self.storage_mut() {
+            ThinMut::Empty => None,
+            ThinMut::Inline(map) => map.remove_entry(key),
+            ThinMut::Heap(map) => map.remove_entry(key),
+        };
+        if self.is_empty() {
+            self.clear();
+        }
+        entry
+    }
+
+    /// Returns an iterator over the key-value pairs.
+    pub fn iter(&self) -> SmallHashMapIter<'_, K, V, N> {
+        match self.storage() {
+            ThinRef::Empty => SmallHashMapIter::InlineMap([].iter().zip([].iter())),
+            ThinRef::Inline(map) => map.iter(),
+            ThinRef::Heap(map) => SmallHashMapIter::HeapMap(map.iter()),
+        }
+    }
+
+    /// Returns an iterator over the keys.
+    pub fn keys(&self) -> SmallHashMapKeys<'_, K, V, N> {
+        match self.storage() {
+            ThinRef::Empty => SmallHashMapKeys::InlineMap([].iter()),
+            ThinRef::Inline(map) => map.keys(),
+            ThinRef::Heap(map) => SmallHashMapKeys::HeapMap(map.keys()),
+        }
+    }
+
+    /// Returns an iterator over the values.
+    pub fn values(&self) -> SmallHashMapValues<'_, K, V, N> {
+        match self.storage() {
+            ThinRef::Empty => SmallHashMapValues::InlineMap([].iter()),
+            ThinRef::Inline(map) => map.values(),
+            ThinRef::Heap(map) => SmallHashMapValues::HeapMap(map.values()),
+        }
+    }
+
+    /// Returns an iterator over the key-value pairs, with mutable references
+    /// to the values.
+    pub fn iter_mut(&mut self) -> SmallHashMapIterMut<'_, K, V, N> {
+        match self.storage_mut() {
+            ThinMut::Empty => SmallHashMapIterMut::InlineMap([].iter().zip([].iter_mut())),
+            ThinMut::Inline(map) => map.iter_mut(),
+            ThinMut::Heap(map) => SmallHashMapIterMut::HeapMap(map.iter_mut()),
+        }
+    }
+
+    /// Returns an iterator over mutable references to the values.
+    pub fn values_mut(&mut self) -> SmallHashMapValuesMut<'_, K, V, N> {
+        match self.storage_mut() {
+            ThinMut::Empty => SmallHashMapValuesMut::InlineMap([].iter_mut()),
+            ThinMut::Inline(map) => map.values_mut(),
+            ThinMut::Heap(map) => SmallHashMapValuesMut::HeapMap(map.values_mut()),
+        }
+    }
+
+    /// Retains only the elements specified by the predicate. Frees the
+    /// allocation if the map becomes empty.
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        match self.storage_mut() {
+            ThinMut::Empty => {}
+            ThinMut::Inline(map) => map.retain(f),
+            ThinMut::Heap(map) => map.retain(f),
+        }
+        if self.is_empty() {
+            self.clear();
+        }
+    }
+
+    /// Removes every entry, returning them as an owning iterator, and frees
+    /// the allocation.
+    pub fn drain(&mut self) -> SmallHashMapIntoIter<K, V, N> {
+        mem::take(self).into_iter()
+    }
+}
+
+impl<K, V, const N: usize, S> ThinSmallHashMap<K, V, N, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default + Clone,
+{
+    /// Allocates the map if the handle is empty.
+    fn allocate(&mut self) {
+        if self.ptr.is_none() {
+            *self = Self::from_box(ThinBox::Inline(SmallHashMap::new_boxed()));
+        }
+    }
+
+    /// Replaces a boxed `SmallHashMap` that has spilled with a box holding
+    /// just its heap table, freeing the inline storage.
+    fn unbox_spilled(&mut self) {
+        if let ThinMut::Inline(map) = self.storage_mut() {
+            if let Some(heap) = map.as_heap_mut() {
+                let heap = mem::take(heap);
+                *self = Self::from_box(ThinBox::Heap(Box::new(heap)));
+            }
+        }
+    }
+
+    /// Returns the storage that `key` should be inserted into, allocating the
+    /// map if it's empty and moving it to the heap first if `key` would
+    /// overflow the inline storage.
+    fn storage_for_insert(&mut self, key: &K) -> ThinMut<'_, K, V, N, S> {
+        match self.storage() {
+            ThinRef::Empty => self.allocate(),
+            ThinRef::Inline(map) if map.len() >= N && !map.contains_key(key) => {
+                self.reserve(N.max(1));
+            }
+            ThinRef::Inline(_) | ThinRef::Heap(_) => {}
+        }
+        self.storage_mut()
+    }
+
+    /// Inserts a key-value pair into the map, returning the old value if the
+    /// key was present. Allocates the map on the first insert.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.storage_for_insert(&key) {
+            ThinMut::Inline(map) => map.insert(key, value),
+            ThinMut::Heap(map) => map.insert(key, value),
+            ThinMut::Empty => unreachable!("storage_for_insert allocates"),
+        }
+    }
+
+    /// Inserts a key-value pair if the key is absent, returning a mutable
+    /// reference to the value. If the key is present, the map is left
+    /// unchanged and the error holds the existing entry and `value`.
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<&mut V, OccupiedError<'_, K, V>> {
+        match self.storage_for_insert(&key) {
+            ThinMut::Inline(map) => map.try_insert(key, value),
+            ThinMut::Heap(map) => map.try_insert(key, value),
+            ThinMut::Empty => unreachable!("storage_for_insert allocates"),
+        }
+    }
+
+    /// Returns a mutable reference to the value for `key`, inserting the
+    /// result of `f` first if the key is absent.
+    pub fn get_or_insert_with<F>(&mut self, key: K, f: F) -> &mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self.storage_for_insert(&key) {
+            ThinMut::Inline(map) => map.get_or_insert_with(key, f),
+            ThinMut::Heap(map) => map.entry(key).or_insert_with(f),
+            ThinMut::Empty => unreachable!("storage_for_insert allocates"),
+        }
+    }
+
+    /// Returns a mutable reference to the value for `key`, inserting
+    /// `V::default()` first if the key is absent.
+    pub fn get_or_default(&mut self, key: K) -> &mut V
+    where
+        V: Default,
+    {
+        self.get_or_insert_with(key, V::default)
+    }
+
+    /// Reserves capacity for at least `additional` more elements.
+    /// Does nothing for an empty map when `additional` is 0.
+    pub fn reserve(&mut self, additional: usize) {
+        if additional == 0 {
+            return;
+        }
+        self.allocate();
+        match self.storage_mut() {
+            ThinMut::Inline(map) => map.reserve(additional),
+            ThinMut::Heap(map) => map.reserve(additional),
+            ThinMut::Empty => unreachable!("the map was just allocated"),
+        }
+        self.unbox_spilled();
+    }
+}
+
+impl<K, V, const N: usize, S> Clone for ThinSmallHashMap<K, V, N, S>
+where
+    K: Clone,
+    V: Clone,
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self::from_box(match self.storage() {
+            ThinRef::Empty => ThinBox::Empty,
+            ThinRef::Inline(map) => ThinBox::Inline(Box::new(map.clone())),
+            ThinRef::Heap(map) => ThinBox::Heap(Box::new(map.clone())),
+        })
+    }
+}
+
+impl<K, V, const N: usize, S> fmt::Debug for ThinSmallHashMap<K, V, N, S>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, const N: usize, S> Default for ThinSmallHashMap<K, V, N, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, const N: usize, S> PartialEq for ThinSmallHashMap<K, V, N, S>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K, V, const N: usize, S> Eq for ThinSmallHashMap<K, V, N, S>
+where
+    K: Hash + Eq,
+    V: Eq,
+    S: BuildHasher,
+{
+}
+
+impl<K, V, const N: usize, S> IntoIterator for ThinSmallHashMap<K, V, N, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    type Item = (K, V);
+    type IntoIter = SmallHashMapIntoIter<K, V, N>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        match self.take() {
+            ThinBox::Empty => SmallHashMapIntoIter::InlineMap(Vec::new().into_iter()),
+            ThinBox::Inline(map) => map.into_iter(),
+            ThinBox::Heap(map) => SmallHashMapIntoIter::HeapMap(map.into_iter()),
+        }
+    }
+}
+
+impl<K, V, const N: usize, S> Extend<(K, V)> for ThinSmallHashMap<K, V, N, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default + Clone,
+{
+    /// Extends the map, allocating it only if the iterator yields an entry.
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let mut iter = iter.into_iter().peekable();
+        if iter.peek().is_none() {
+            return;
+        }
+        self.allocate();
+        match self.storage_mut() {
+            ThinMut::Inline(map) => map.extend(iter),
+            ThinMut::Heap(map) => map.extend(iter),
+            ThinMut::Empty => unreachable!("the map was just allocated"),
+        }
+        self.unbox_spilled();
+    }
+}
+
+impl<K, V, const N: usize, S> std::iter::FromIterator<(K, V)> for ThinSmallHashMap<K, V, N, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default + Clone,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, V, const N: usize, S> From<SmallHashMap<K, V, N, S>> for ThinSmallHashMap<K, V, N, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Boxes the map, or drops it if it's empty. A spilled map gives up its
+    /// heap table without rehashing.
+    fn from(map: SmallHashMap<K, V, N, S>) -> Self {
+        Self::from_box(if map.is_empty() {
+            ThinBox::Empty
+        } else if map.is_inline() {
+            ThinBox::Inline(Box::new(map))
+        } else {
+            ThinBox::Heap(Box::new(map.into_hash_map().into()))
+        })
+    }
+}
+
+impl<K, V, const N: usize, S> From<ThinSmallHashMap<K, V, N, S>> for SmallHashMap<K, V, N, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default + Clone,
+{
+    /// Unboxes the map, or creates an empty one with the default hasher. A
+    /// spilled map's table is adopted without rehashing, unless its entries
+    /// now fit inline.
+    fn from(mut map: ThinSmallHashMap<K, V, N, S>) -> Self {
+        match map.take() {
+            ThinBox::Empty => SmallHashMap::new(),
+            ThinBox::Inline(map) => *map,
+            ThinBox::Heap(map) => map.into_inner().into(),
+        }
+    }
+}